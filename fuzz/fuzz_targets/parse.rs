@@ -0,0 +1,14 @@
+#![no_main]
+use code_ls::ParseOptions;
+use libfuzzer_sys::fuzz_target;
+
+// Whatever the bytes, parsing returns rather than panics, in strict and in
+// lenient mode.
+fuzz_target!(|data: &[u8]| {
+    let _ = code_ls::parse_buffer(data);
+    let lenient = ParseOptions {
+        lenient: true,
+        ..ParseOptions::default()
+    };
+    let _ = code_ls::parse_buffer_with(data, lenient);
+});
@@ -0,0 +1,166 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// The gutter is cut off past this many columns.
+const MAX_GUTTER: usize = 32;
+
+// What a line defines or calls, e.g. "function:main call:printf".
+fn label(sym: &Symbol) -> Option<String> {
+    match sym.mark() {
+        FileMark::FunctionCall => Some(format!("call:{}", sym.display_name())),
+        FileMark::FunctionDefinition
+        | FileMark::Define
+        | FileMark::ClassDefinition
+        | FileMark::EnumDefinition
+        | FileMark::OtherGlobalDefinition
+        | FileMark::EnumStructUnionMemberGlobalDefinition
+        | FileMark::StructDefinition
+        | FileMark::TypedefDefinition
+        | FileMark::UnionDefinition => {
+            Some(format!("{}:{}", sym.mark().name(), sym.display_name()))
+        }
+        _ => None,
+    }
+}
+
+// Cut text down to `columns`, marking that it was with '…'.
+fn truncate(text: &str, columns: usize) -> String {
+    if width(text) <= columns {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    for c in text.chars() {
+        if width(&out) + width(c.encode_utf8(&mut [0; 4])) + 1 > columns {
+            break;
+        }
+        out.push(c);
+    }
+    out.push('…');
+    out
+}
+
+// A source file with a gutter of the symbols each line defines or calls.
+pub struct Annotation {
+    fname: String,
+    // The source, or only the lines the database has when the file is gone.
+    lines: BTreeMap<u64, String>,
+    labels: BTreeMap<u64, Vec<String>>,
+}
+
+impl Annotation {
+    // The file of the database named `fname`, or ending in "/<fname>".
+    pub fn new(cscope: &Cscope, fname: &str, dir: &Path) -> Option<Annotation> {
+        let suffix = format!("/{}", fname.trim_start_matches("./"));
+        let file = cscope
+            .symbols()
+            .iter()
+            .map(|s| s.filename())
+            .find(|f| *f == fname || f.ends_with(&suffix))?;
+        let symbols = cscope.symbols().iter().filter(|s| s.filename() == file);
+
+        let mut lines: BTreeMap<u64, String> = BTreeMap::new();
+        let mut labels: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+        for sym in symbols {
+            if !sym.is_end_mark() {
+                lines
+                    .entry(sym.line_number())
+                    .or_insert_with(|| sym.line_text().to_string());
+            }
+            if let Some(label) = label(sym) {
+                labels.entry(sym.line_number()).or_default().push(label);
+            }
+        }
+        if let Ok(text) = std::fs::read_to_string(dir.join(file)) {
+            lines = text
+                .lines()
+                .enumerate()
+                .map(|(idx, line)| (idx as u64 + 1, line.to_string()))
+                .collect();
+        }
+        Some(Annotation {
+            fname: file.to_string(),
+            lines,
+            labels,
+        })
+    }
+}
+
+impl std::fmt::Display for Annotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let gutters: BTreeMap<u64, String> = self
+            .labels
+            .iter()
+            .map(|(line, labels)| (*line, truncate(&labels.join(" "), MAX_GUTTER)))
+            .collect();
+        let len = gutters.values().map(|g| width(g)).max().unwrap_or(0);
+        let number_len = self.lines.keys().last().map_or(1, |n| n.to_string().len());
+        writeln!(f, "•{}:", self.fname)?;
+        for (line, text) in self.lines.iter() {
+            let gutter = gutters.get(line).map_or("", |g| g.as_str());
+            writeln!(
+                f,
+                "{:>number_len$} {} │ {}",
+                line,
+                pad(gutter, len),
+                text,
+                number_len = number_len
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    fn annotated(dir: &Path) -> String {
+        let cscope = parse_buffer(&database(
+            "\t@src/main.c\n\n\
+             1 #define \n\t#MAX\n 10\n\n\t)\n\n\
+             3 int \n\t$main\n(void) {\n\n\
+             4 \n\t`printf\n(\"%d\", MAX); \n\t`a_function_with_a_long_name\n();\n\n\
+             5 }\n\n\t}\n\n\
+             \t@\n",
+        ))
+        .unwrap();
+        Annotation::new(&cscope, "main.c", dir).unwrap().to_string()
+    }
+
+    #[test]
+    fn lines_get_a_gutter_of_what_they_define_and_call() {
+        let dir = std::env::temp_dir().join(format!("code-ls-annotate-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let source = "#define MAX 10\n\nint main(void) {\n  printf(\"%d\", MAX); \
+                      a_function_with_a_long_name();\n}\n";
+        std::fs::write(dir.join("src/main.c"), source).unwrap();
+        let out = annotated(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            out,
+            "•src/main.c:\n\
+             1 macro:MAX                        │ #define MAX 10\n\
+             2                                  │ \n\
+             3 function:main                    │ int main(void) {\n\
+             4 call:printf call:a_function_wit… │   printf(\"%d\", MAX); \
+             a_function_with_a_long_name();\n\
+             5                                  │ }\n"
+        );
+    }
+
+    #[test]
+    fn without_the_source_only_the_lines_of_the_database_are_shown() {
+        assert_eq!(
+            annotated(Path::new("/nonexistent")),
+            "•src/main.c:\n\
+             1 macro:MAX                        │ #define MAX 10\n\
+             3 function:main                    │ int main(void) {\n\
+             4 call:printf call:a_function_wit… │ printf(\"%d\", MAX); \
+             a_function_with_a_long_name();\n"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::cscope::{Cscope, FileMark};
+use crate::report;
+use serde::Serialize;
+use std::collections::HashSet;
+
+// What a badge counts.
+#[derive(Clone, Copy)]
+pub enum Metric {
+    Functions,
+    Files,
+    Dead,
+}
+
+impl Metric {
+    pub fn from_name(name: &str) -> Option<Metric> {
+        match name {
+            "functions" => Some(Metric::Functions),
+            "files" => Some(Metric::Files),
+            "dead" => Some(Metric::Dead),
+            _ => None,
+        }
+    }
+}
+
+// The JSON a shields.io endpoint badge is made from.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    schema_version: u8,
+    label: String,
+    message: String,
+    color: &'static str,
+}
+
+impl Badge {
+    pub fn new(cscope: &Cscope, metric: Metric) -> Badge {
+        let (label, message, color) = match metric {
+            Metric::Functions => {
+                let functions = cscope
+                    .symbols()
+                    .iter()
+                    .filter(|s| s.mark() == FileMark::FunctionDefinition)
+                    .count();
+                (
+                    "functions",
+                    format!("{} indexed", thousands(functions)),
+                    "blue",
+                )
+            }
+            Metric::Files => {
+                let files: HashSet<&str> = cscope.symbols().iter().map(|s| s.filename()).collect();
+                (
+                    "files",
+                    format!("{} indexed", thousands(files.len())),
+                    "blue",
+                )
+            }
+            Metric::Dead => {
+                let dead = report::dead_functions(cscope).len();
+                let color = if dead == 0 { "brightgreen" } else { "orange" };
+                ("dead functions", thousands(dead), color)
+            }
+        };
+        Badge {
+            schema_version: 1,
+            label: label.to_string(),
+            message,
+            color,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+// 1234567 as "1,234,567".
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::{calls, sample};
+
+    #[test]
+    fn badges_are_shields_io_endpoints() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        assert_eq!(
+            Badge::new(&cscope, Metric::Functions).to_json(),
+            r#"{"schemaVersion":1,"label":"functions","message":"1 indexed","color":"blue"}"#
+        );
+        assert_eq!(
+            Badge::new(&cscope, Metric::Files).to_json(),
+            r#"{"schemaVersion":1,"label":"files","message":"1 indexed","color":"blue"}"#
+        );
+    }
+
+    #[test]
+    fn dead_badge_leaves_out_main() {
+        let cscope = parse_buffer(&calls(&[("main", &["used"]), ("used", &[])])).unwrap();
+        assert_eq!(
+            Badge::new(&cscope, Metric::Dead).to_json(),
+            r#"{"schemaVersion":1,"label":"dead functions","message":"0","color":"brightgreen"}"#
+        );
+        let cscope = parse_buffer(&calls(&[("main", &[]), ("unused", &[])])).unwrap();
+        let badge = Badge::new(&cscope, Metric::Dead);
+        assert_eq!(badge.message, "1");
+        assert_eq!(badge.color, "orange");
+    }
+
+    #[test]
+    fn counts_are_grouped_by_thousands() {
+        assert_eq!(thousands(0), "0");
+        assert_eq!(thousands(999), "999");
+        assert_eq!(thousands(1000), "1,000");
+        assert_eq!(thousands(1234567), "1,234,567");
+    }
+}
@@ -0,0 +1,208 @@
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// 64 bit FNV-1a, stable across builds unlike the std hashers.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+// $XDG_CACHE_HOME/code-ls, or ~/.cache/code-ls.
+fn cache_dir() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(dir.join("code-ls"))
+}
+
+// The command line without what does not change the output: the database
+// path (its content is hashed instead) and the cache and watch options.
+pub fn normalize_query(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut query = vec![];
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--exec" => {
+                args.next();
+            }
+            "--no-cache" | "--watch" => (),
+            _ if arg.starts_with("-f") => (),
+            _ => query.push(arg),
+        }
+    }
+    query
+}
+
+// The saved output of one query against one version of the database, with
+// the warnings its parse printed.
+pub struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    pub fn new(db: &Path, query: &[String], inputs: &[PathBuf]) -> Result<Cache, Error> {
+        let dir = cache_dir().ok_or_else(|| Error::other("No cache directory."))?;
+        Cache::in_dir(&dir, db, query, inputs)
+    }
+
+    // Key the entry by the database as it is on disk, its path, size and
+    // modification time, as the sidecar is, so a hit reads none of it.  The
+    // query and the files that decide which paths are left out go in too,
+    // and the version, which can change the output.
+    fn in_dir(dir: &Path, db: &Path, query: &[String], inputs: &[PathBuf]) -> Result<Cache, Error> {
+        let meta = std::fs::metadata(db)?;
+        let modified = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(Error::other)?;
+        let db = std::fs::canonicalize(db)?;
+        let mut hash = fnv1a(FNV_OFFSET, env!("CARGO_PKG_VERSION").as_bytes());
+        hash = fnv1a(hash, db.as_os_str().as_encoded_bytes());
+        hash = fnv1a(hash, &meta.len().to_le_bytes());
+        hash = fnv1a(hash, &modified.as_nanos().to_le_bytes());
+        for arg in query.iter() {
+            hash = fnv1a(hash, arg.as_bytes());
+            hash = fnv1a(hash, &[0]);
+        }
+        for input in inputs.iter() {
+            hash = fnv1a(hash, &std::fs::read(input).unwrap_or_default());
+            hash = fnv1a(hash, &[0]);
+        }
+        Ok(Cache {
+            path: dir.join(format!("{:016x}", hash)),
+        })
+    }
+
+    // The warnings and the output, saved as the length of the warnings on a
+    // line of its own followed by the two.
+    pub fn get(&self) -> Option<(String, Vec<u8>)> {
+        let bytes = std::fs::read(&self.path).ok()?;
+        let newline = bytes.iter().position(|b| *b == b'\n')?;
+        let len: usize = std::str::from_utf8(&bytes[..newline]).ok()?.parse().ok()?;
+        let rest = bytes.get(newline + 1..)?;
+        let warnings = std::str::from_utf8(rest.get(..len)?).ok()?;
+        Some((warnings.to_string(), rest[len..].to_vec()))
+    }
+
+    // Written to a temporary file first so a reader never sees half of it.
+    pub fn put(&self, warnings: &str, bytes: &[u8]) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = self
+            .path
+            .with_extension(format!("tmp{}", std::process::id()));
+        let mut entry = format!("{}\n{}", warnings.len(), warnings).into_bytes();
+        entry.extend_from_slice(bytes);
+        std::fs::write(&tmp, entry)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A scratch directory holding a database and the cache.
+    fn scratch(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("code-ls-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cscope.out"), "cscope 15 $HOME/src 0000000000\n").unwrap();
+        dir
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn queries_leave_out_the_database_and_the_cache_options() {
+        let query = normalize_query(
+            args(&[
+                "-f",
+                "a.out",
+                "--no-cache",
+                "query",
+                "-fb.out",
+                "--watch",
+                "--exec",
+                "make",
+                "main",
+            ])
+            .into_iter(),
+        );
+        assert_eq!(query, args(&["query", "main"]));
+        assert_eq!(
+            normalize_query(args(&["-f", "a.out", "callers", "main"]).into_iter()),
+            normalize_query(args(&["-f", "b.out", "callers", "main"]).into_iter())
+        );
+    }
+
+    #[test]
+    fn hits_give_back_the_output_and_its_warnings() {
+        let dir = scratch("hit");
+        let db = dir.join("cscope.out");
+        let query = args(&["callers", "main"]);
+        let cache = Cache::in_dir(&dir.join("cache"), &db, &query, &[]).unwrap();
+        assert!(cache.get().is_none());
+        cache
+            .put("warning: 1 unknown mark\n", b"main.c:3\n")
+            .unwrap();
+        let again = Cache::in_dir(&dir.join("cache"), &db, &query, &[]).unwrap();
+        assert_eq!(
+            again.get(),
+            Some((
+                "warning: 1 unknown mark\n".to_string(),
+                b"main.c:3\n".to_vec()
+            ))
+        );
+        cache.put("", b"").unwrap();
+        assert_eq!(again.get(), Some((String::new(), vec![])));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn entries_are_missed_once_the_database_or_the_query_changes() {
+        let dir = scratch("miss");
+        let db = dir.join("cscope.out");
+        let ignore = dir.join(".code-lsignore");
+        let query = args(&["callers", "main"]);
+        let key = || {
+            Cache::in_dir(
+                &dir.join("cache"),
+                &db,
+                &query,
+                std::slice::from_ref(&ignore),
+            )
+            .unwrap()
+        };
+        key().put("", b"before").unwrap();
+        assert_eq!(key().get().unwrap().1, b"before");
+
+        let other =
+            Cache::in_dir(&dir.join("cache"), &db, &args(&["callers", "draw"]), &[]).unwrap();
+        assert!(other.get().is_none());
+
+        std::fs::write(&ignore, "vendor/\n").unwrap();
+        assert!(key().get().is_none());
+        key().put("", b"ignoring").unwrap();
+
+        // The same length written at another time.
+        let file = std::fs::File::options().write(true).open(&db).unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+        assert!(key().get().is_none());
+        key().put("", b"touched").unwrap();
+
+        std::fs::write(&db, "cscope 15 $HOME/src -c 0000000000\n").unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+        assert!(key().get().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width};
+use crate::table::{Cell, Table};
+use std::collections::BTreeMap;
+
+// Records that give a name its meaning, rather than use it.
+fn is_definition(mark: FileMark) -> bool {
+    matches!(
+        mark,
+        FileMark::FunctionDefinition
+            | FileMark::Define
+            | FileMark::ClassDefinition
+            | FileMark::EnumDefinition
+            | FileMark::OtherGlobalDefinition
+            | FileMark::EnumStructUnionMemberGlobalDefinition
+            | FileMark::StructDefinition
+            | FileMark::TypedefDefinition
+            | FileMark::UnionDefinition
+    )
+}
+
+// Definitions whose names differ only by case, e.g. Foo_bar and foo_bar.
+// They clash on case insensitive linkers and are easy to mistake for each
+// other when renaming.
+pub struct CaseCollisions<'a> {
+    // Keyed by the lower case name, with the definitions of every spelling.
+    groups: BTreeMap<String, Vec<&'a Symbol>>,
+}
+
+impl<'a> CaseCollisions<'a> {
+    pub fn new(cscope: &'a Cscope) -> CaseCollisions<'a> {
+        let mut groups: BTreeMap<String, Vec<&Symbol>> = BTreeMap::new();
+        for sym in cscope.symbols().iter().filter(|s| is_definition(s.mark())) {
+            groups
+                .entry(sym.name().to_lowercase())
+                .or_default()
+                .push(sym);
+        }
+        groups.retain(|_, syms| {
+            let first = syms[0].name();
+            syms.iter().any(|s| s.name() != first)
+        });
+        for syms in groups.values_mut() {
+            syms.sort_by(|a, b| a.name().cmp(b.name()));
+        }
+        CaseCollisions { groups }
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "case-collisions",
+            &["folded", "name", "kind", "file", "line"],
+        );
+        for (folded, syms) in self.groups.iter() {
+            for sym in syms.iter() {
+                table.rows.push(vec![
+                    Cell::from(folded.as_str()),
+                    Cell::from(sym.name()),
+                    Cell::from(sym.mark().name()),
+                    Cell::from(sym.filename()),
+                    Cell::from(sym.line_number()),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for CaseCollisions<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (folded, syms) in self.groups.iter() {
+            let len = syms.iter().map(|s| width(s.name())).max().unwrap_or(0);
+            write!(f, "•{}:\n╰─╮\n", folded)?;
+            for sym in syms.iter() {
+                writeln!(
+                    f,
+                    "  ├ {} {:<8} {}:{}",
+                    pad(sym.name(), len),
+                    sym.mark().name(),
+                    sym.filename(),
+                    sym.line_number()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
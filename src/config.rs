@@ -0,0 +1,140 @@
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+// The project configuration file, read from the directory holding the
+// database.  It takes precedence over the user's configuration file.
+pub const CONFIG_FILE: &str = ".code-ls.conf";
+
+// Generated sources left out of the output unless configured otherwise.
+pub const DEFAULT_EXCLUDES: &[&str] = &[
+    "*.pb-c.c",
+    "*.pb-c.h",
+    "*.pb.cc",
+    "*.pb.h",
+    "*_generated.h",
+    "*_generated.c",
+    "build/**",
+];
+
+// $XDG_CONFIG_HOME/code-ls/config, or ~/.config/code-ls/config.
+pub fn user_config_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("code-ls").join("config"))
+}
+
+// Settings read from "<key> = <value>" lines, those starting with '#' are
+// comments.
+#[derive(Default)]
+pub struct Config {
+    // Replaces DEFAULT_EXCLUDES, whitespace or comma separated gitignore
+    // patterns.  An empty value excludes nothing by default.
+    pub default_excludes: Option<Vec<String>>,
+}
+
+impl Config {
+    // Load $XDG_CONFIG_HOME/code-ls/config (or ~/.config/code-ls/config) and
+    // then the project's CONFIG_FILE, later settings override earlier ones.
+    pub fn load(db_dir: &Path) -> Result<Config, Error> {
+        let mut config = Config::default();
+        let mut paths: Vec<PathBuf> = user_config_path().into_iter().collect();
+        paths.push(db_dir.join(CONFIG_FILE));
+        for path in paths.iter().filter(|p| p.is_file()) {
+            let text = std::fs::read_to_string(path)?;
+            config.parse(&text).map_err(|e| {
+                Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+            })?;
+        }
+        Ok(config)
+    }
+
+    fn parse(&mut self, text: &str) -> Result<(), String> {
+        for (idx, line) in text.lines().enumerate() {
+            // Only a whole line is a comment, '#' being part of patterns
+            // such as \#* and gen#x.
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("line {}: Expected '<key> = <value>'.", idx + 1)),
+            };
+            match key {
+                "default-excludes" => {
+                    let patterns = value
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|p| !p.is_empty())
+                        .map(|p| p.to_string());
+                    self.default_excludes = Some(patterns.collect());
+                }
+                _ => return Err(format!("line {}: Unknown setting '{}'.", idx + 1, key)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        config.parse(text)?;
+        Ok(config)
+    }
+
+    #[test]
+    fn excludes_are_split_at_commas_and_blanks() {
+        let config = parsed("default-excludes = *.pb.c, build/**  gen/*\n").unwrap();
+        assert_eq!(
+            config.default_excludes,
+            Some(vec![
+                "*.pb.c".to_string(),
+                "build/**".to_string(),
+                "gen/*".to_string()
+            ])
+        );
+        assert_eq!(
+            parsed("default-excludes =").unwrap().default_excludes,
+            Some(vec![])
+        );
+        assert_eq!(parsed("").unwrap().default_excludes, None);
+    }
+
+    #[test]
+    fn only_lines_starting_with_a_hash_are_comments() {
+        let config = parsed(
+            "# generated\n\
+             \x20 # indented too\n\
+             \n\
+             default-excludes = \\#* gen#x\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.default_excludes,
+            Some(vec!["\\#*".to_string(), "gen#x".to_string()])
+        );
+    }
+
+    #[test]
+    fn later_settings_override_earlier_ones() {
+        let config = parsed("default-excludes = a\ndefault-excludes = b\n").unwrap();
+        assert_eq!(config.default_excludes, Some(vec!["b".to_string()]));
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            parsed("# ok\nexcludes\n").err(),
+            Some("line 2: Expected '<key> = <value>'.".to_string())
+        );
+        assert_eq!(
+            parsed("colour = never\n").err(),
+            Some("line 1: Unknown setting 'colour'.".to_string())
+        );
+    }
+}
@@ -1,7 +1,17 @@
+use crate::encoding::Encoding;
+use crate::error::{CscopeError, Location};
+use crate::grammar::{self, RawSymbol, Record, RuleError};
+use crate::render::{pad, width, RenderOptions};
+use crate::sort::{sort_within_files, WithinFile};
+use crate::table::{Cell, Table};
+use crate::warnings::{Warning, WarningKind};
+use crate::writer::{write_database, WriteOptions};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Seek, SeekFrom};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 // Resources:
 // The cscope database format is internal to cscope and is not published.
@@ -10,64 +20,868 @@ use std::path::{Path, PathBuf};
 // from the aforementioned older man page:
 // https://codecat.tistory.com/entry/cscope-manpage
 
-#[derive(Debug)]
-struct Symbol {
+// The most source lines a prototype is allowed to span.
+const MAX_SIGNATURE_LINES: u64 = 32;
+
+// Records taking up fewer bytes than this are parsed on one thread.
+const PARALLEL_MIN_BYTES: usize = 1 << 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Symbol {
     mark: FileMark,
-    filename: String,
+    // Shared by the records of the file.
+    filename: Arc<str>,
     line_number: u64,
     name: String,
     non_sym_text1: String,
     non_sym_text2: String,
+    // The text of the whole source line, shared by the symbols on it.
+    line_text: Arc<str>,
+    // Where the name starts within the line text.
+    column: usize,
+    signature: Option<String>,
+    demangled: Option<String>,
+    // The line of the closing function end mark, for function definitions.
+    end_line: Option<u64>,
+    // Where the record starts in the database file.
+    offset: u64,
 }
 
-#[derive(Debug)]
-struct Cscope {
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cscope {
     version: u32,
     current_dir: PathBuf,
     trailer_offset: u64,
     header_raw: String,
     symbols: Vec<Symbol>,
+    warnings: Vec<Warning>,
+    options: ParseOptions,
+    trailer: Trailer,
+}
+
+// The lists following the records: the directories searched for source
+// files (the viewpath), those searched for #include files, and the source
+// files the database was built from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trailer {
+    pub source_dirs: Vec<String>,
+    pub include_dirs: Vec<String>,
+    pub source_files: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParseOptions {
+    // Skip malformed records instead of giving up on the database.
+    pub lenient: bool,
+    pub encoding: Encoding,
+    pub unknown_marks: UnknownMarks,
+    // Only keep the records of these kinds, the end marks closing them are
+    // kept along with function and #define definitions.
+    pub kinds: Option<Kinds>,
+}
+
+impl ParseOptions {
+    fn wants(&self, mark: FileMark) -> bool {
+        let kinds = match self.kinds {
+            Some(kinds) => kinds,
+            None => return true,
+        };
+        kinds.contains(mark)
+            || (mark == FileMark::FunctionEnd && kinds.contains(FileMark::FunctionDefinition))
+            || (mark == FileMark::DefineEnd && kinds.contains(FileMark::Define))
+    }
+}
+
+// A set of record kinds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Kinds(u128);
+
+impl Kinds {
+    pub fn insert(&mut self, mark: FileMark) {
+        self.0 |= 1 << (mark as u8 & 0x7f);
+    }
+
+    pub fn contains(&self, mark: FileMark) -> bool {
+        self.0 & (1 << (mark as u8 & 0x7f)) != 0
+    }
+}
+
+// What to do about mark characters the parser does not know.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum UnknownMarks {
+    Error,
+    #[default]
+    Warn,
+    Ignore,
 }
 
 impl Cscope {
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    // What the parser got past: unknown marks, invalid text and skipped records.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn retain_symbols(&mut self, keep: impl FnMut(&Symbol) -> bool) {
+        self.symbols.retain(keep);
+    }
+
+    // Each record along with the function definition whose body it is in.
+    pub fn with_enclosing_functions(&self) -> Vec<(&Symbol, Option<&Symbol>)> {
+        let mut current: Option<&Symbol> = None;
+        self.symbols
+            .iter()
+            .map(|sym| {
+                if sym.mark == FileMark::FunctionDefinition {
+                    current = Some(sym);
+                    return (sym, None);
+                }
+                let enclosing = current.filter(|f| {
+                    f.filename == sym.filename
+                        && f.line_number <= sym.line_number
+                        && f.end_line.is_some_and(|end| sym.line_number <= end)
+                });
+                (sym, enclosing)
+            })
+            .collect()
+    }
+
+    // Order the files by `cmp`, keeping the records of each file in order.
+    pub fn sort_files(&mut self, cmp: impl Fn(&str, &str) -> std::cmp::Ordering) {
+        self.symbols.sort_by(|a, b| cmp(&a.filename, &b.filename));
+    }
+
+    pub fn trailer(&self) -> &Trailer {
+        &self.trailer
+    }
+
+    // Add the records of another database, e.g. one of several a project is
+    // split into.  Its file names are made relative to this database's
+    // directory when they can be, and the files this database already has
+    // keep their own records.
+    pub fn merge(&mut self, other: Cscope) {
+        let (here, there) = (self.current_dir.clone(), other.current_dir.clone());
+        let rename = |name: &str| -> String {
+            if here == there || Path::new(name).is_absolute() {
+                return name.to_string();
+            }
+            let path = there.join(name);
+            match path.strip_prefix(&here) {
+                Ok(rel) if rel == Path::new("") => ".".to_string(),
+                Ok(rel) => rel.to_string_lossy().into_owned(),
+                // Without the "/." of a directory listed as ".".
+                Err(_) => path
+                    .components()
+                    .collect::<PathBuf>()
+                    .to_string_lossy()
+                    .into_owned(),
+            }
+        };
+        let have: HashSet<Arc<str>> = self.symbols.iter().map(|s| s.filename.clone()).collect();
+        let mut renamed: HashMap<Arc<str>, Option<Arc<str>>> = HashMap::new();
+        for mut sym in other.symbols.into_iter() {
+            let name = renamed
+                .entry(sym.filename.clone())
+                .or_insert_with(|| {
+                    let name: Arc<str> = rename(&sym.filename).into();
+                    Some(name).filter(|n| !have.contains(n))
+                })
+                .clone();
+            if let Some(name) = name {
+                sym.filename = name;
+                self.symbols.push(sym);
+            }
+        }
+        let trailer = &mut self.trailer;
+        let lists = [
+            (&mut trailer.source_dirs, other.trailer.source_dirs),
+            (&mut trailer.include_dirs, other.trailer.include_dirs),
+            (&mut trailer.source_files, other.trailer.source_files),
+        ];
+        for (list, more) in lists {
+            for name in more.iter().map(|n| rename(n)) {
+                if !list.contains(&name) {
+                    list.push(name);
+                }
+            }
+        }
+        self.warnings.extend(other.warnings);
+    }
+
+    // The header and options alone, to parse some of the records into.
+    fn without_records(&self) -> Cscope {
+        Cscope {
+            version: self.version,
+            current_dir: self.current_dir.clone(),
+            trailer_offset: self.trailer_offset,
+            header_raw: self.header_raw.clone(),
+            symbols: vec![],
+            warnings: vec![],
+            options: self.options,
+            trailer: Trailer::default(),
+        }
+    }
+
+    // Write the records back out as a valid uncompressed database (cscope -c),
+    // e.g. after some were left out with retain_symbols.
+    pub fn write_to(&self, out: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        out.write_all(&write_database(self, WriteOptions::default()))
+    }
+
+    // The format version in the header, 15 for cscope 15.x.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    // The directory cscope was run in, the file names are relative to it.
+    pub fn current_dir(&self) -> &Path {
+        &self.current_dir
+    }
+
+    // Built with '-q', the number of terms in the inverted index cscope keeps
+    // next to the database (cscope.in.out and cscope.po.out).  The database
+    // itself is laid out the same either way.
+    pub fn inverted_index_terms(&self) -> Option<u64> {
+        let words: Vec<&str> = self.header_raw.split_whitespace().collect();
+        let idx = words.iter().position(|w| *w == "-q")?;
+        words.get(idx + 1)?.parse().ok()
+    }
+
+    // The header line as written, options and all.
+    pub fn header(&self) -> &str {
+        self.header_raw.trim_end()
+    }
+
+    // Where the header says the trailer starts.
+    pub fn trailer_offset(&self) -> u64 {
+        self.trailer_offset
+    }
+
+    // Built without '-c', the non-symbol text is compressed.
     pub fn is_compressed(&self) -> bool {
-        match self.header_raw.split(" ").into_iter().find(|c| *c == "-c") {
-            Some(_) => true,
-            None => false,
+        !self.header_raw.split_whitespace().any(|c| c == "-c")
+    }
+
+    // Have the records of a file, and the symbols of a line, share one
+    // string for it again as the parser makes them; deserializing gives
+    // each record a copy of its own.
+    pub(crate) fn share_strings(&mut self) {
+        let mut seen: HashSet<Arc<str>> = HashSet::new();
+        let mut share = |text: &mut Arc<str>| match seen.get(text) {
+            Some(shared) => *text = shared.clone(),
+            None => {
+                seen.insert(text.clone());
+            }
+        };
+        for sym in self.symbols.iter_mut() {
+            share(&mut sym.filename);
+            share(&mut sym.line_text);
+        }
+    }
+
+    // Databases built over C++ can contain Itanium-mangled names (_Z...).
+    pub fn demangle_names(&mut self) {
+        for sym in self.symbols.iter_mut() {
+            sym.demangled = demangle(&sym.name);
+        }
+    }
+
+    // The class each member function definition belongs to: the one named by
+    // the qualifier of an out-of-line definition (Class::method), or the one
+    // whose body holds an inline definition.
+    fn member_classes(&self) -> HashMap<*const Symbol, String> {
+        let mut classes: HashMap<*const Symbol, String> = HashMap::new();
+        for scope in crate::outline::scopes(self, |m| m == FileMark::ClassDefinition) {
+            for member in scope.members.iter() {
+                if member.mark == FileMark::FunctionDefinition {
+                    classes.insert(*member, scope.parent.display_name().to_string());
+                }
+            }
+        }
+
+        // <class> :: <method>, the qualifiers are the symbols preceding the
+        // name on its source line.
+        for (idx, sym) in self.symbols.iter().enumerate() {
+            if sym.mark != FileMark::FunctionDefinition {
+                continue;
+            }
+            let mut qualifiers: Vec<&str> = vec![];
+            let mut cur = sym;
+            for prev in self.symbols[..idx].iter().rev() {
+                if cur.non_sym_text1.trim() != "::"
+                    || prev.line_number != sym.line_number
+                    || prev.filename != sym.filename
+                {
+                    break;
+                }
+                qualifiers.push(prev.display_name());
+                cur = prev;
+            }
+            if !qualifiers.is_empty() {
+                qualifiers.reverse();
+                classes.insert(sym, qualifiers.join("::"));
+            }
+        }
+        classes
+    }
+
+    // Pair each function definition with the function end mark closing it.
+    fn find_function_ends(&mut self) {
+        let mut open: Option<usize> = None;
+        for idx in 0..self.symbols.len() {
+            let sym = &self.symbols[idx];
+            match sym.mark {
+                FileMark::FunctionDefinition => open = Some(idx),
+                FileMark::FunctionEnd => {
+                    if let Some(def) = open.take() {
+                        if self.symbols[def].filename == sym.filename {
+                            self.symbols[def].end_line = Some(sym.line_number);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // Piece together the complete prototype of every function definition and
+    // declaration.
+    // A record only carries the text of its own source line, so a return
+    // type or parameter list split across lines has to be collected from the
+    // neighbouring records, or from the source file when those lines hold no
+    // symbols and were never recorded.
+    fn reconstruct_signatures(&mut self) {
+        let mut start = 0;
+        while start < self.symbols.len() {
+            let fname = self.symbols[start].filename.clone();
+            let end = self.symbols[start..]
+                .iter()
+                .position(|s| s.filename != fname)
+                .map_or(self.symbols.len(), |n| start + n);
+            let file = &self.symbols[start..end];
+            let lines = line_texts(file);
+            let mut source = SourceText::new(self.current_dir.join(&*fname), self.options.encoding);
+            let signatures: Vec<Option<String>> = file
+                .iter()
+                .map(|sym| {
+                    if sym.mark == FileMark::FunctionDefinition || sym.is_declaration() {
+                        signature(sym, &lines, &mut source)
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            for (sym, sig) in self.symbols[start..end].iter_mut().zip(signatures) {
+                sym.signature = sig;
+            }
+            start = end;
+        }
+    }
+}
+
+// Source lines for a file, only read from disk when the database lacks them.
+struct SourceText {
+    path: PathBuf,
+    lines: Option<Vec<String>>,
+    encoding: Encoding,
+}
+
+impl SourceText {
+    fn new(path: PathBuf, encoding: Encoding) -> SourceText {
+        SourceText {
+            path,
+            lines: None,
+            encoding,
+        }
+    }
+
+    fn line(&mut self, line_number: u64) -> Option<&str> {
+        if self.lines.is_none() {
+            // Only regular files, a database can name anything as a source.
+            let text = match std::fs::metadata(&self.path) {
+                Ok(meta) if meta.is_file() => std::fs::read(&self.path).unwrap_or_default(),
+                _ => vec![],
+            };
+            self.lines = Some(
+                self.encoding
+                    .decode_lossy(&text)
+                    .lines()
+                    .map(|l| l.to_string())
+                    .collect(),
+            );
+        }
+        let idx = line_number.checked_sub(1)? as usize;
+        self.lines.as_ref()?.get(idx).map(|l| l.as_str())
+    }
+}
+
+// The text of each recorded source line of a file.
+fn line_texts(symbols: &[Symbol]) -> BTreeMap<u64, String> {
+    let mut lines: BTreeMap<u64, String> = BTreeMap::new();
+    for sym in symbols.iter().filter(|s| !s.is_end_mark()) {
+        lines
+            .entry(sym.line_number)
+            .or_insert_with(|| sym.line_text.to_string());
+    }
+    lines
+}
+
+// Return the byte index just past the parenthesis closing the first opening
+// one, if the parameter list is complete.
+fn parameter_list_end(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (idx, ch) in text.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' if depth == 1 => return Some(idx + 1),
+            ')' if depth > 1 => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+// A line preceding a definition that looks like the tail of a return type,
+// e.g. "static struct point *" above "make_point(int x, int y)".
+fn is_return_type(text: &str) -> bool {
+    let text = text.trim();
+    !text.is_empty()
+        && !text.starts_with('#')
+        && !text.ends_with(&[';', '{', '}', ')', '/', ','][..])
+}
+
+// The signature of a function definition or declaration, none when its
+// column does not fall within its line, as in a damaged database.
+fn signature(
+    sym: &Symbol,
+    lines: &BTreeMap<u64, String>,
+    source: &mut SourceText,
+) -> Option<String> {
+    let line_number = sym.line_number;
+    let mut get_line = |n: u64| -> Option<String> {
+        match lines.get(&n) {
+            Some(text) => Some(text.clone()),
+            None => source.line(n).map(|l| l.to_string()),
+        }
+    };
+
+    // <return type> on the same line as the name, or on the line above.  A
+    // line number can be recorded more than once, the record's own text is
+    // the one its column is in.
+    let text = sym.line_text.as_ref();
+    let mut head = text.get(..sym.column)?.to_string();
+    if head.trim().is_empty() && line_number > 1 {
+        if let Some(prev) = get_line(line_number - 1).filter(|t| is_return_type(t)) {
+            head = prev + " ";
+        }
+    }
+
+    // <name> <parameter list>, which can continue over the following lines.
+    let mut tail = text.get(sym.column..)?.to_string();
+    let mut next = line_number.saturating_add(1);
+    while parameter_list_end(&tail).is_none() && next - line_number < MAX_SIGNATURE_LINES {
+        match get_line(next) {
+            Some(more) => {
+                tail.push(' ');
+                tail.push_str(&more);
+            }
+            None => break,
+        }
+        next = next.saturating_add(1);
+    }
+    match parameter_list_end(&tail) {
+        Some(end) => tail.truncate(end),
+        None => {
+            if let Some(brace) = tail.find('{') {
+                tail.truncate(brace);
+            }
+        }
+    }
+
+    // Collapse the whitespace left over from joining lines.
+    Some(
+        format!("{}{}", head, tail)
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .replace("( ", "(")
+            .replace(" )", ")"),
+    )
+}
+
+// Demangle an Itanium C++ ABI name, None if the name is not mangled.
+fn demangle(name: &str) -> Option<String> {
+    if !name.starts_with("_Z") {
+        return None;
+    }
+    let sym = cpp_demangle::Symbol::new(name).ok()?;
+    sym.demangle().ok()
+}
+
+impl Symbol {
+    pub fn mark(&self) -> FileMark {
+        self.mark
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    // The name as recorded in the database.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // The non-symbol text before and after the symbol, as recorded.
+    pub fn text_before(&self) -> &str {
+        &self.non_sym_text1
+    }
+
+    pub fn text_after(&self) -> &str {
+        &self.non_sym_text2
+    }
+
+    // The source line as recorded, with the blanks cscope kept.
+    pub fn raw_line_text(&self) -> &str {
+        &self.line_text
+    }
+
+    // Where the name starts within raw_line_text(), in bytes.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // The first and last line of a function definition.
+    pub fn extent(&self) -> Option<(u64, u64)> {
+        self.end_line.map(|end| (self.line_number, end))
+    }
+
+    // The number of source lines a function definition spans.
+    pub fn function_lines(&self) -> Option<u64> {
+        self.end_line
+            .map(|end| end.saturating_sub(self.line_number) + 1)
+    }
+
+    pub fn demangled(&self) -> Option<&str> {
+        self.demangled.as_deref()
+    }
+
+    // The demangled name when one is known, otherwise the name as recorded.
+    pub fn display_name(&self) -> &str {
+        self.demangled.as_deref().unwrap_or(&self.name)
+    }
+
+    // The source line the symbol was found on.
+    pub fn line_text(&self) -> &str {
+        self.line_text.trim()
+    }
+
+    // cscope leaves the names in function declarations (prototypes) unmarked.
+    pub fn is_declaration(&self) -> bool {
+        self.mark == FileMark::Reference && self.non_sym_text2.trim_start().starts_with('(')
+    }
+
+    // cscope squeezes leading whitespace into a single blank, so for the first
+    // symbol on a line this tells whether the source line was indented.
+    pub fn is_indented(&self) -> bool {
+        self.non_sym_text1.starts_with(' ')
+    }
+
+    // Function and #define end marks close the preceding definition, they
+    // carry no symbol of their own, only the text of the line they follow.
+    pub fn is_end_mark(&self) -> bool {
+        self.mark == FileMark::FunctionEnd || self.mark == FileMark::DefineEnd
+    }
+}
+
+// The records of each function definition nested beneath it.
+type Locals<'a> = HashMap<*const Symbol, Vec<&'a Symbol>>;
+
+// A file's function definitions, grouped by the class they are members of.
+type FunctionGroups<'a> = Vec<(Option<&'a str>, Vec<&'a Symbol>)>;
+
+// The functions of each file, the default view of the database.
+pub struct Tree<'a> {
+    cscope: &'a Cscope,
+    options: RenderOptions,
+    order: WithinFile,
+    reverse: bool,
+    dirs: bool,
+    verbose: bool,
+}
+
+// The directory part of a path, and the file name.
+fn split_dir(path: &str) -> (Option<&str>, &str) {
+    match path.rsplit_once('/') {
+        Some((dir, name)) => (Some(dir), name),
+        None => (None, path),
+    }
+}
+
+impl<'a> Tree<'a> {
+    pub fn new(cscope: &'a Cscope, options: RenderOptions) -> Tree<'a> {
+        Tree {
+            cscope,
+            options,
+            order: WithinFile::Database,
+            reverse: false,
+            dirs: false,
+            verbose: false,
+        }
+    }
+
+    // Nest the parameters and block-local definitions of each function
+    // beneath it.
+    pub fn verbose_functions(mut self) -> Tree<'a> {
+        self.verbose = true;
+        self
+    }
+
+    // Nest the files under a heading for each directory they are in, those
+    // of each directory ahead of its subdirectories.
+    pub fn with_dirs(mut self) -> Tree<'a> {
+        self.dirs = true;
+        self
+    }
+
+    // List the functions of each file in `order` rather than as recorded.
+    pub fn sorted(mut self, order: WithinFile, reverse: bool) -> Tree<'a> {
+        self.order = order;
+        self.reverse = reverse;
+        self
+    }
+
+    // The function definitions in the order they are listed.
+    fn functions(&self) -> Vec<&'a Symbol> {
+        let mut functions: Vec<&Symbol> = self
+            .cscope
+            .symbols
+            .iter()
+            .filter(|s| s.mark == FileMark::FunctionDefinition)
+            .collect();
+        sort_within_files(&mut functions, |s| s, self.order, self.reverse);
+        functions
+    }
+
+    // The parameters and block-local definitions of each function, with
+    // verbose_functions.
+    fn locals(&self) -> Locals<'a> {
+        let mut locals = Locals::new();
+        if !self.verbose {
+            return locals;
+        }
+        for (sym, function) in self.cscope.with_enclosing_functions() {
+            if let (
+                FileMark::FunctionParameterDefinition | FileMark::FunctionBlockLocalDefinition,
+                Some(function),
+            ) = (sym.mark, function)
+            {
+                locals
+                    .entry(function as *const Symbol)
+                    .or_default()
+                    .push(sym);
+            }
+        }
+        locals
+    }
+
+    // One function per line: <prefix> <name> <signature>, line:<n>, then
+    // its locals beneath it.
+    fn write_function(
+        &self,
+        f: &mut std::fmt::Formatter,
+        prefix: &str,
+        sym: &Symbol,
+        len: usize,
+        locals: &Locals,
+    ) -> std::fmt::Result {
+        let sig = sym.signature.as_deref().unwrap_or("");
+        self.options
+            .write_row(f, prefix, &pad(sym.display_name(), len), sig, sym)?;
+        let locals = match locals.get(&(sym as *const Symbol)) {
+            Some(locals) => locals,
+            None => return Ok(()),
+        };
+        // Beneath the function's branch, which carries on as "│".
+        let carry_on: String = prefix
+            .chars()
+            .map(|c| match c {
+                '├' => '│',
+                '╰' => ' ',
+                c => c,
+            })
+            .collect();
+        for (i, local) in locals.iter().enumerate() {
+            let branch = if i + 1 == locals.len() { "╰" } else { "├" };
+            writeln!(
+                f,
+                "{} {} {} {}, {}",
+                carry_on,
+                branch,
+                local.mark.name(),
+                local.display_name(),
+                self.options.location(local)
+            )?;
+        }
+        Ok(())
+    }
+
+    // The functions with the class they are a member of, if any.
+    pub fn table(&self) -> Table {
+        let classes = self.cscope.member_classes();
+        let mut table = Table::new(
+            "functions",
+            &["file", "class", "name", "demangled", "signature", "line"],
+        );
+        for sym in self.functions() {
+            let class = classes.get(&(sym as *const Symbol));
+            table.rows.push(vec![
+                Cell::from(sym.filename()),
+                Cell::from(class.map_or("", |c| c.as_str())),
+                Cell::from(sym.name()),
+                Cell::from(sym.demangled().unwrap_or("")),
+                Cell::from(sym.signature().unwrap_or("")),
+                Cell::from(sym.line_number()),
+            ]);
         }
+        table
     }
 }
 
 impl std::fmt::Display for Cscope {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut fname: &str = "";
+        Tree::new(self, RenderOptions::default()).fmt(f)
+    }
+}
+
+impl<'a> std::fmt::Display for Tree<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let max_len: usize = self
+            .cscope
             .symbols
             .iter()
             .map(|s| {
                 if s.mark == FileMark::FunctionDefinition {
-                    s.name.len()
+                    width(s.display_name())
                 } else {
-                    0 as usize
+                    0
                 }
             })
             .max()
-            .unwrap();
-        for sym in self.symbols.iter() {
-            if sym.filename != fname && sym.mark == FileMark::FunctionDefinition {
-                fname = &sym.filename;
-                write!(f, "•{}:\n╰─╮\n", fname)?;
+            .unwrap_or(0);
+
+        // Group the functions of each file, the member functions of a class
+        // are gathered under one heading placed where the first of them is.
+        let classes = self.cscope.member_classes();
+        let mut structs: HashMap<&str, usize> = HashMap::new();
+        let mut files: Vec<(&str, FunctionGroups)> = vec![];
+        for sym in self.cscope.symbols.iter() {
+            if sym.mark == FileMark::StructDefinition {
+                *structs.entry(&sym.filename).or_insert(0) += 1;
             }
-            if sym.mark == FileMark::FunctionDefinition {
-                let sig = format!("{} {}", sym.non_sym_text1, sym.non_sym_text2);
+        }
+        for sym in self.functions() {
+            if files.last().map(|f| f.0) != Some(&*sym.filename) {
+                files.push((&sym.filename, vec![]));
+            }
+            let class = classes.get(&(sym as *const Symbol)).map(|c| c.as_str());
+            if let Some((_, groups)) = files.last_mut() {
+                match groups.iter_mut().find(|g| class.is_some() && g.0 == class) {
+                    Some(group) => group.1.push(sym),
+                    None => groups.push((class, vec![sym])),
+                }
+            }
+        }
+
+        if self.dirs {
+            // By the directories of the path, so src/net comes right after
+            // src rather than after src-old.
+            files.sort_by_key(|(fname, _)| {
+                split_dir(fname)
+                    .0
+                    .map_or(vec![], |dir| dir.split('/').collect::<Vec<&str>>())
+            });
+        }
+
+        let locals = self.locals();
+        // The directories of the last file, each with a heading above it.
+        let mut open: Vec<&str> = vec![];
+        for (fname, groups) in files.iter() {
+            let (parts, name): (Vec<&str>, &str) = match split_dir(fname) {
+                (Some(dir), name) if self.dirs => (dir.split('/').collect(), name),
+                _ => (vec![], fname),
+            };
+            let same = open
+                .iter()
+                .zip(parts.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            open.truncate(same);
+            for part in parts[same..].iter() {
+                let indent = "  ".repeat(open.len());
                 write!(
                     f,
-                    "  ├ {name:<len$} {: <16}, line:{}\n",
-                    sig,
-                    sym.line_number,
-                    name = sym.name,
-                    len = max_len
+                    "{}•{}/\n{}╰─╮\n",
+                    indent,
+                    self.options.file(part),
+                    indent
                 )?;
+                open.push(part);
+            }
+            let indent = "  ".repeat(open.len());
+            let functions: usize = groups.iter().map(|g| g.1.len()).sum();
+            write!(
+                f,
+                "{}•{}: ({} fns, {} structs)\n{}╰─╮\n",
+                indent,
+                self.options.file(name),
+                functions,
+                structs.get(fname).unwrap_or(&0),
+                indent
+            )?;
+            for (class, syms) in groups.iter() {
+                match class {
+                    None => {
+                        for sym in syms.iter() {
+                            let prefix = format!("{}  ├", indent);
+                            self.write_function(f, &prefix, sym, max_len, &locals)?;
+                        }
+                    }
+                    Some(class) => {
+                        writeln!(f, "{}  ├ {}::", indent, class)?;
+                        for (i, sym) in syms.iter().enumerate() {
+                            let branch = if i + 1 == syms.len() {
+                                "  │ ╰"
+                            } else {
+                                "  │ ├"
+                            };
+                            let prefix = format!("{}{}", indent, branch);
+                            // Keep the signatures aligned with the unnested ones.
+                            self.write_function(
+                                f,
+                                &prefix,
+                                sym,
+                                max_len.saturating_sub(2),
+                                &locals,
+                            )?;
+                        }
+                    }
+                }
             }
         }
         Ok(())
@@ -75,29 +889,93 @@ impl std::fmt::Display for Cscope {
 }
 
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum FileMark {
-    File = '@' as u8,
-    FunctionDefinition = '$' as u8,
-    FunctionCall = '`' as u8,
-    FunctionEnd = '}' as u8,
-    Define = '#' as u8,
-    DefineEnd = ')' as u8,
-    IncludeDirective = '~' as u8,
-    DirectAssingmentIncDec = '=' as u8,
-    EnumStructUnionDefinitonEnd = ';' as u8,
-    ClassDefinition = 'c' as u8,
-    EnumDefinition = 'e' as u8,
-    OtherGlobalDefinition = 'g' as u8,
-    FunctionBlockLocalDefinition = 'l' as u8,
-    EnumStructUnionMemberGlobalDefinition = 'm' as u8,
-    FunctionParameterDefinition = 'p' as u8,
-    StructDefinition = 's' as u8,
-    TypedefDefinition = 't' as u8,
-    UnionDefinition = 'u' as u8,
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FileMark {
+    File = b'@',
+    FunctionDefinition = b'$',
+    FunctionCall = b'`',
+    FunctionEnd = b'}',
+    Define = b'#',
+    DefineEnd = b')',
+    IncludeDirective = b'~',
+    DirectAssingmentIncDec = b'=',
+    EnumStructUnionDefinitonEnd = b';',
+    ClassDefinition = b'c',
+    EnumDefinition = b'e',
+    OtherGlobalDefinition = b'g',
+    FunctionBlockLocalDefinition = b'l',
+    EnumStructUnionMemberGlobalDefinition = b'm',
+    FunctionParameterDefinition = b'p',
+    StructDefinition = b's',
+    TypedefDefinition = b't',
+    UnionDefinition = b'u',
+    Reference = b' ',
     WTF = 0,
 }
 
+impl FileMark {
+    // A short name for the kind of record.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileMark::File => "file",
+            FileMark::FunctionDefinition => "function",
+            FileMark::FunctionCall => "call",
+            FileMark::FunctionEnd => "function-end",
+            FileMark::Define => "macro",
+            FileMark::DefineEnd => "macro-end",
+            FileMark::IncludeDirective => "include",
+            FileMark::DirectAssingmentIncDec => "assignment",
+            FileMark::EnumStructUnionDefinitonEnd => "type-end",
+            FileMark::ClassDefinition => "class",
+            FileMark::EnumDefinition => "enum",
+            FileMark::OtherGlobalDefinition => "global",
+            FileMark::FunctionBlockLocalDefinition => "local",
+            FileMark::EnumStructUnionMemberGlobalDefinition => "member",
+            FileMark::FunctionParameterDefinition => "parameter",
+            FileMark::StructDefinition => "struct",
+            FileMark::TypedefDefinition => "typedef",
+            FileMark::UnionDefinition => "union",
+            FileMark::Reference => "reference",
+            FileMark::WTF => "unknown",
+        }
+    }
+
+    // Whether the record defines its name, rather than using it or closing
+    // a definition.
+    pub fn is_definition(&self) -> bool {
+        !matches!(
+            self,
+            FileMark::File
+                | FileMark::FunctionCall
+                | FileMark::FunctionEnd
+                | FileMark::DefineEnd
+                | FileMark::IncludeDirective
+                | FileMark::DirectAssingmentIncDec
+                | FileMark::EnumStructUnionDefinitonEnd
+                | FileMark::Reference
+                | FileMark::WTF
+        )
+    }
+
+    // A definition made outside of function bodies, the ones looked up by
+    // name: anything but locals and parameters.
+    pub fn is_outer_definition(&self) -> bool {
+        self.is_definition()
+            && !matches!(
+                self,
+                FileMark::FunctionBlockLocalDefinition | FileMark::FunctionParameterDefinition
+            )
+    }
+
+    // The kind of record going by a short name, see name().
+    pub fn from_name(name: &str) -> Option<FileMark> {
+        (0..=127u8)
+            .map(FileMark::from)
+            .find(|m| *m != FileMark::WTF && m.name() == name)
+    }
+}
+
 impl From<u8> for FileMark {
     fn from(val: u8) -> FileMark {
         match val as char {
@@ -119,272 +997,1028 @@ impl From<u8> for FileMark {
             's' => FileMark::StructDefinition,
             't' => FileMark::TypedefDefinition,
             'u' => FileMark::UnionDefinition,
+            ' ' => FileMark::Reference,
             _ => FileMark::WTF,
         }
     }
 }
 
-fn parse_header(fp: &mut BufReader<File>) -> Result<Cscope, Error> {
-    let header: String;
-    let mut buf: Vec<u8> = vec![];
-    match fp.read_until('\n' as u8, &mut buf) {
-        Ok(_) => match std::str::from_utf8(&buf) {
-            Ok(s) => header = s.to_string(),
-            Err(_) => return Err(Error::new(ErrorKind::NotFound, "Invalid line data.")),
-        },
-        Err(err) => return Err(err),
+// Where a part of the database starts in it.
+fn offset_in(db: &[u8], part: &[u8]) -> u64 {
+    (part.as_ptr() as usize).saturating_sub(db.as_ptr() as usize) as u64
+}
+
+// A rule that did not match, as an error saying where and what was expected.
+fn rule_error(db: &[u8], e: nom::Err<RuleError>) -> CscopeError {
+    let e = match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e,
+        nom::Err::Incomplete(_) => {
+            return CscopeError::Truncated {
+                offset: db.len() as u64,
+            }
+        }
+    };
+    let offset = offset_in(db, e.input);
+    if e.input.is_empty() {
+        return CscopeError::Truncated { offset };
     }
+    CscopeError::Syntax {
+        at: Location::new(db, offset),
+        expected: e.expected,
+    }
+}
 
-    let words: Vec<&str> = header.split(' ').collect();
-    if words.len() < 4 || words[0] != "cscope" {
-        return Err(Error::new(ErrorKind::InvalidData, "Invalid header."));
+fn parse_header(db: &[u8]) -> Result<(Cscope, &[u8]), CscopeError> {
+    let (body, header) = grammar::header(db).map_err(|e| rule_error(db, e))?;
+    if header.trailer_offset < header.line.len() as u64 {
+        return Err(CscopeError::Invalid {
+            at: Location::new(db, offset_in(db, header.offset)),
+            what: "trailer offset",
+        });
     }
+    let cscope = Cscope {
+        version: header.version,
+        current_dir: PathBuf::from(String::from_utf8_lossy(header.current_dir).into_owned()),
+        trailer_offset: header.trailer_offset,
+        header_raw: String::from_utf8_lossy(header.line).into_owned(),
+        symbols: vec![],
+        warnings: vec![],
+        options: ParseOptions::default(),
+        trailer: Trailer::default(),
+    };
+    Ok((cscope, body))
+}
+
+// Pairs of characters cscope packs into a single byte when compressing, the
+// first from DICHAR1 and the second from DICHAR2: byte 0x80 + 8 * i + j
+// stands for DICHAR1[i] followed by DICHAR2[j].
+pub const DICHAR1: &[u8] = b" teisaprnl(of)=c";
+pub const DICHAR2: &[u8] = b" tnerpla";
 
-    let ver: u32;
-    match words[1].parse() {
-        Ok(v) => ver = v,
-        Err(_) => {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "Failed to parse version.",
-            ))
+// The keywords cscope writes as the control character of their index, with
+// the blank or " (" which follows them.  Indexes 0, 9 and 10 stand for
+// nothing, tab and newline, which are never compressed.
+pub const KEYWORDS: [(&str, &str); 32] = [
+    ("", ""),
+    ("#define", " "),
+    ("#include", " "),
+    ("break", ""),
+    ("case", " "),
+    ("char", " "),
+    ("continue", ""),
+    ("default", ""),
+    ("double", " "),
+    ("\t", ""),
+    ("\n", ""),
+    ("else", " "),
+    ("enum", " "),
+    ("extern", " "),
+    ("float", " "),
+    ("for", " ("),
+    ("goto", " "),
+    ("if", " ("),
+    ("int", " "),
+    ("long", " "),
+    ("register", " "),
+    ("return", ""),
+    ("short", " "),
+    ("sizeof", ""),
+    ("static", " "),
+    ("struct", " "),
+    ("switch", " ("),
+    ("typedef", " "),
+    ("union", " "),
+    ("unsigned", " "),
+    ("void", " "),
+    ("while", " ("),
+];
+
+// Expand the digraphs and keywords of compressed non-symbol text.
+fn decompress(buf: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(buf.len() * 2);
+    for &b in buf {
+        match b {
+            0x80..=0xff => {
+                let code = (b & 0x7f) as usize;
+                out.push(DICHAR1[code / 8]);
+                out.push(DICHAR2[code & 7]);
+            }
+            b'\t' | b'\n' | 0 => out.push(b),
+            0x01..=0x1f => {
+                let (keyword, delim) = KEYWORDS[b as usize];
+                out.extend_from_slice(keyword.as_bytes());
+                out.extend_from_slice(delim.as_bytes());
+            }
+            _ => out.push(b),
         }
     }
+    out
+}
 
-    let path: PathBuf = PathBuf::from(words[2]);
-    let trailer: u64;
-    let offset = words.last().unwrap().trim_start_matches("0").trim_end();
-    match offset.parse() {
-        Ok(t) => trailer = t,
-        Err(e) => return Err(Error::new(ErrorKind::InvalidData, e)),
+// Non-symbol text keeps its blanks: they separate the text from the symbols
+// when a source line is pieced back together.  Only this text is ever
+// compressed.
+fn text(db: &[u8], bytes: &[u8], cscope: &mut Cscope) -> String {
+    let offset = offset_in(db, bytes);
+    let encoding = cscope.options.encoding;
+    if cscope.is_compressed() {
+        decode(&decompress(bytes), encoding, offset, &mut cscope.warnings)
+    } else {
+        decode(bytes, encoding, offset, &mut cscope.warnings)
     }
+}
 
-    Ok(Cscope {
-        version: ver,
-        current_dir: path,
-        trailer_offset: trailer,
-        header_raw: header,
-        symbols: vec![],
-    })
+fn name(db: &[u8], bytes: &[u8], cscope: &mut Cscope) -> String {
+    let offset = offset_in(db, bytes);
+    decode(bytes, cscope.options.encoding, offset, &mut cscope.warnings)
+        .trim()
+        .to_string()
 }
 
-// This consumes 2 characters: <tab><mark>
-fn parse_file_mark(fp: &mut BufReader<File>) -> Result<FileMark, Error> {
-    // Read in the tab character
-    let mut ch: [u8; 1] = [0];
-    fp.read(&mut ch)?;
-    if ch[0] != '\t' as u8 {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Expected tab character.",
-        ));
+fn decode(buf: &[u8], encoding: Encoding, offset: u64, warnings: &mut Vec<Warning>) -> String {
+    match encoding.decode(buf) {
+        Some(s) => s,
+        None => {
+            warnings.push(Warning {
+                kind: WarningKind::InvalidUtf8,
+                offset,
+                mark: None,
+            });
+            "<invalid utf8>".to_string()
+        }
     }
+}
 
-    // Read the mark character.
-    fp.read(&mut ch)?;
-    Ok(ch[0].into())
+// The file and line the parser is at, and the text of that line.
+struct Position {
+    fname: Arc<str>,
+    line_number: u64,
+    line_text: Arc<str>,
 }
 
-fn parse_file_path(fp: &mut BufReader<File>) -> Result<String, Error> {
-    let mut buf: Vec<u8> = vec![];
-    fp.read_until('\n' as u8, &mut buf)?;
-    Ok(std::str::from_utf8(&buf).unwrap().trim().to_string())
+// The records of each file until the trailer, `after_file` is called once
+// those of a file have been parsed.  True when the records stop before the
+// input does, at the empty file name ending them or a truncation.
+fn parse_records(
+    db: &[u8],
+    mut input: &[u8],
+    cscope: &mut Cscope,
+    mut after_file: impl FnMut(&mut Cscope) -> Result<(), CscopeError>,
+) -> Result<bool, CscopeError> {
+    let mut ended = false;
+    while !input.is_empty() && !ended {
+        // <file mark> <file path>
+        // <empty line>
+        let (rest, path) = match grammar::file_line(input) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                end_early(db, input, cscope, rule_error(db, e))?;
+                return Ok(true);
+            }
+        };
+        let fname = match cscope.options.encoding.decode(path) {
+            Some(s) => s.trim().to_string(),
+            None if cscope.options.lenient => {
+                cscope.warnings.push(Warning {
+                    kind: WarningKind::InvalidUtf8,
+                    offset: offset_in(db, path),
+                    mark: None,
+                });
+                cscope
+                    .options
+                    .encoding
+                    .decode_lossy(path)
+                    .trim()
+                    .to_string()
+            }
+            None => {
+                return Err(CscopeError::Invalid {
+                    at: Location::new(db, offset_in(db, path)),
+                    what: "file path",
+                })
+            }
+        };
+        if fname.is_empty() {
+            return Ok(true);
+        }
+        let rest = match grammar::empty_line(rest) {
+            Ok((rest, _)) => rest,
+            Err(e) => {
+                end_early(db, input, cscope, rule_error(db, e))?;
+                return Ok(true);
+            }
+        };
+        let mut pos = Position {
+            fname: Arc::from(fname),
+            line_number: 0,
+            line_text: Arc::from(""),
+        };
+
+        // For each source line, up to the next file mark.
+        input = rest;
+        while !input.is_empty() && !input.starts_with(b"\t@") {
+            let first = cscope.symbols.len();
+            let parsed = grammar::record(input)
+                .map_err(|e| rule_error(db, e))
+                .and_then(|(rest, record)| add_record(db, record, cscope, &mut pos).map(|_| rest));
+            match parsed {
+                Ok(rest) => input = rest,
+                // Leave out the malformed record and carry on with the next one.
+                Err(e) if cscope.options.lenient && e.is_recoverable() => {
+                    cscope.symbols.truncate(first);
+                    cscope.warnings.push(Warning {
+                        kind: WarningKind::SkippedRecord,
+                        offset: offset_in(db, input),
+                        mark: None,
+                    });
+                    input = match grammar::skip_record(input) {
+                        Ok((rest, _)) => rest,
+                        // Nothing ends it, the database stops first.
+                        Err(_) => {
+                            note_truncated(db, input, cscope);
+                            ended = true;
+                            &[]
+                        }
+                    };
+                }
+                // Keep what was read before the database stops.
+                Err(CscopeError::Truncated { .. }) if cscope.options.lenient => {
+                    cscope.symbols.truncate(first);
+                    note_truncated(db, input, cscope);
+                    ended = true;
+                    input = &[];
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        after_file(cscope)?;
+    }
+    Ok(ended)
 }
 
-fn parse_empty_line(fp: &mut BufReader<File>) -> Result<(), Error> {
-    let mut ch: [u8; 1] = [0];
-    fp.read(&mut ch)?;
-    if ch[0] as char != '\n' {
-        return Err(Error::new(
-            ErrorKind::InvalidData,
-            "Expected newline/empty_line.",
-        ));
+// The records of each file parsed at once on their own threads and put back
+// together in order, for databases large enough for it to pay off.  What
+// follows records that stop early is left out, as a parse of them in turn
+// would never get to it.
+fn parse_files(db: &[u8], records: &[u8], cscope: &mut Cscope) -> Result<(), CscopeError> {
+    if records.len() < PARALLEL_MIN_BYTES {
+        return parse_records(db, records, cscope, |_| Ok(())).map(|_| ());
     }
-    Ok(())
+    parse_files_in_parallel(db, records, cscope)
 }
 
-fn parse_line_number_and_blank(fp: &mut BufReader<File>) -> Result<u64, Error> {
-    // Read up to the blank, thus consuming the blank character (space).
-    let mut buf: Vec<u8> = vec![];
-    fp.read_until(' ' as u8, &mut buf)?;
-    let line = std::str::from_utf8(&buf).unwrap().to_string();
+fn parse_files_in_parallel(
+    db: &[u8],
+    records: &[u8],
+    cscope: &mut Cscope,
+) -> Result<(), CscopeError> {
+    let parts: Vec<Result<(Cscope, bool), CscopeError>> = file_sections(records)
+        .par_iter()
+        .map(|section| {
+            let mut part = cscope.without_records();
+            let ended = parse_records(db, section, &mut part, |_| Ok(()))?;
+            Ok((part, ended))
+        })
+        .collect();
+    for part in parts {
+        let (part, ended) = part?;
+        cscope.symbols.extend(part.symbols);
+        cscope.warnings.extend(part.warnings);
+        if ended {
+            break;
+        }
+    }
+    Ok(())
+}
 
-    match line.trim().parse() {
-        Ok(n) => Ok(n),
-        Err(_) => Err(Error::new(
-            ErrorKind::InvalidData,
-            "Failed to parse line number.",
-        )),
+// The records split before each line starting with a file mark.
+fn file_sections(records: &[u8]) -> Vec<&[u8]> {
+    let mut sections = vec![];
+    let mut start = 0;
+    while let Some(idx) = records
+        .get(start + 1..)
+        .unwrap_or(&[])
+        .windows(3)
+        .position(|w| w == b"\n\t@")
+    {
+        let next = start + 1 + idx + 1;
+        sections.push(&records[start..next]);
+        start = next;
     }
+    sections.push(&records[start..]);
+    sections
 }
 
-fn parse_to_end(fp: &mut BufReader<File>) -> Result<String, Error> {
-    let mut buf: Vec<u8> = vec![];
-    fp.read_until('\n' as u8, &mut buf)?;
-    Ok(from_utf8(&buf))
+// Notes that the records stop partway through the one at `input`.
+fn note_truncated(db: &[u8], input: &[u8], cscope: &mut Cscope) {
+    cscope.warnings.push(Warning {
+        kind: WarningKind::Truncated,
+        offset: offset_in(db, input),
+        mark: None,
+    });
 }
 
-fn peek(fp: &mut BufReader<File>) -> u8 {
-    let mut ch: [u8; 1] = [0];
-    let res = fp.read(&mut ch);
-    if let Err(_) = fp.seek(SeekFrom::Current(-1)) {
-        return 0;
+// A lenient parse keeps the records read before the database stops, any
+// other error ends the parse.
+fn end_early(
+    db: &[u8],
+    input: &[u8],
+    cscope: &mut Cscope,
+    e: CscopeError,
+) -> Result<(), CscopeError> {
+    match e {
+        CscopeError::Truncated { .. } if cscope.options.lenient => {
+            note_truncated(db, input, cscope);
+            Ok(())
+        }
+        e => Err(e),
     }
+}
 
-    match res {
-        Ok(_) => ch[0],
-        Err(_) => 0,
+// The symbols of a record, those of the kinds asked for.
+fn add_record(
+    db: &[u8],
+    record: Record,
+    cscope: &mut Cscope,
+    pos: &mut Position,
+) -> Result<(), CscopeError> {
+    match record {
+        // An end mark belongs to the source line above.
+        Record::End(raw) => {
+            let mark = symbol_mark(db, &raw, cscope)?;
+            let name = name(db, raw.name, cscope);
+            if !cscope.options.wants(mark) {
+                return Ok(());
+            }
+            cscope.symbols.push(Symbol {
+                mark,
+                filename: pos.fname.clone(),
+                line_number: pos.line_number,
+                name,
+                non_sym_text1: String::new(),
+                non_sym_text2: String::new(),
+                line_text: pos.line_text.clone(),
+                column: 0,
+                signature: None,
+                demangled: None,
+                end_line: None,
+                offset: offset_in(db, raw.at),
+            });
+        }
+        Record::Line {
+            number,
+            text: line_start,
+            symbols,
+        } => {
+            pos.line_number = number;
+            let mut non_sym_text1 = text(db, line_start, cscope);
+            let first = cscope.symbols.len();
+            let mut line_text = non_sym_text1.clone();
+            let empty: Arc<str> = Arc::from("");
+            for raw in symbols.iter() {
+                let mark = symbol_mark(db, raw, cscope)?;
+                let name = name(db, raw.name, cscope);
+                let non_sym_text2 = text(db, raw.text, cscope);
+                let column = line_text.len();
+                line_text.push_str(&name);
+                line_text.push_str(&non_sym_text2);
+                let text_before = std::mem::replace(&mut non_sym_text1, non_sym_text2.clone());
+                if !cscope.options.wants(mark) {
+                    continue;
+                }
+                cscope.symbols.push(Symbol {
+                    mark,
+                    filename: pos.fname.clone(),
+                    line_number: pos.line_number,
+                    name,
+                    non_sym_text1: text_before,
+                    non_sym_text2,
+                    line_text: empty.clone(),
+                    column,
+                    signature: None,
+                    demangled: None,
+                    end_line: None,
+                    offset: offset_in(db, raw.at),
+                });
+            }
+            pos.line_text = Arc::from(line_text.as_str());
+            for sym in cscope.symbols[first..].iter_mut() {
+                sym.line_text = pos.line_text.clone();
+            }
+        }
     }
+    Ok(())
 }
 
-fn parse_optional_mark(fp: &mut BufReader<File>) -> Result<Option<FileMark>, Error> {
-    if peek(fp) == '\t' as u8 {
-        match parse_file_mark(fp) {
-            Ok(m) => return Ok(Some(m)),
-            Err(e) => return Err(e),
+// A symbol's mark, noting the ones this parser does not know.
+fn symbol_mark(db: &[u8], raw: &RawSymbol, cscope: &mut Cscope) -> Result<FileMark, CscopeError> {
+    let byte = match raw.mark {
+        Some(byte) => byte,
+        None => return Ok(FileMark::Reference),
+    };
+    let mark = FileMark::from(byte);
+    let offset = offset_in(db, raw.at);
+    if mark == FileMark::WTF {
+        match cscope.options.unknown_marks {
+            UnknownMarks::Error => {
+                return Err(CscopeError::UnknownMark {
+                    at: Location::new(db, offset),
+                    mark: byte,
+                })
+            }
+            UnknownMarks::Warn => cscope.warnings.push(Warning {
+                kind: WarningKind::UnknownMark,
+                offset,
+                mark: Some(byte),
+            }),
+            UnknownMarks::Ignore => (),
         }
     }
-    Ok(None)
+    Ok(mark)
 }
 
-fn from_utf8(buf: &Vec<u8>) -> String {
-    match std::str::from_utf8(buf) {
-        Ok(s) => s.trim().to_string(),
-        Err(_) => "<invalid utf8>".to_string(),
+fn parse_trailer(db: &[u8], cscope: &Cscope) -> Result<Trailer, CscopeError> {
+    let invalid = || CscopeError::Invalid {
+        at: Location::new(db, cscope.trailer_offset),
+        what: "trailer",
+    };
+    let trailer = db
+        .get(cscope.trailer_offset as usize..)
+        .ok_or_else(invalid)?;
+    let (_, raw) = grammar::trailer(trailer).map_err(|e| rule_error(db, e))?;
+    let names = |lines: &[&[u8]]| -> Vec<String> {
+        lines
+            .iter()
+            .map(|l| cscope.options.encoding.decode_lossy(l))
+            .collect()
+    };
+
+    // The source files are counted, followed by the space their names take
+    // up, anything in between the directories and them is passed over.
+    let mut lines: Vec<&[u8]> = raw.rest.split(|b| *b == b'\n').collect();
+    if lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
     }
+    let count = |idx: usize| -> Option<usize> {
+        std::str::from_utf8(lines.get(idx)?)
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    let files = (0..lines.len()).find_map(|idx| {
+        let n = count(idx)?;
+        count(idx + 1)?;
+        lines.get(idx + 2..).filter(|files| files.len() == n)
+    });
+    Ok(Trailer {
+        source_dirs: names(&raw.source_dirs),
+        include_dirs: names(&raw.include_dirs),
+        source_files: names(files.ok_or_else(invalid)?),
+    })
 }
 
-fn parse_until_empty_line(fp: &mut BufReader<File>) -> Result<String, Error> {
-    let mut buf: Vec<u8> = vec![];
-    loop {
-        let num_read: usize;
-        match fp.read_until('\n' as u8, &mut buf) {
-            Ok(n) => num_read = n,
-            Err(e) => return Err(e),
-        }
-        if num_read == 1 {
-            return Ok(from_utf8(&buf));
+pub fn parse_database(filename: &Path) -> Result<Cscope, CscopeError> {
+    parse_database_with(filename, ParseOptions::default())
+}
+
+pub fn parse_database_with(filename: &Path, options: ParseOptions) -> Result<Cscope, CscopeError> {
+    let db = DatabaseBytes::open(filename)?;
+    parse(&db, options).map_err(|e| match index_database(filename) {
+        Some(database) => CscopeError::InvertedIndex {
+            index: filename.to_path_buf(),
+            database,
+        },
+        None => e,
+    })
+}
+
+// The bytes of a database file, decompressed when kept gzip or zstd
+// compressed.
+pub fn read_database(filename: &Path) -> Result<impl std::ops::Deref<Target = [u8]>, CscopeError> {
+    Ok(DatabaseBytes::open(filename)?)
+}
+
+// The header of a database and its trailer, leaving the records between
+// them alone, so databases too damaged to parse can still be looked at.
+pub fn parse_header_and_trailer(
+    db: &[u8],
+) -> Result<(Cscope, Result<Trailer, CscopeError>), CscopeError> {
+    let (cscope, _) = parse_header(db)?;
+    let trailer = if cscope.trailer_offset > db.len() as u64 {
+        Err(CscopeError::TrailerPastEnd {
+            trailer_offset: cscope.trailer_offset,
+        })
+    } else {
+        parse_trailer(db, &cscope)
+    };
+    Ok((cscope, trailer))
+}
+
+// The bytes of a database file, mapped into memory so the parser works over
+// them in place rather than on a copy.  Files that cannot be mapped (pipes,
+// empty files on some systems) are read instead, and those compressed with
+// gzip or zstd decompressed.
+enum DatabaseBytes {
+    Mapped(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+
+impl DatabaseBytes {
+    fn open(filename: &Path) -> Result<DatabaseBytes, std::io::Error> {
+        let file = std::fs::File::open(filename)?;
+        // Safety: the map is only read, and cscope writes a new database
+        // beside the old one before renaming it into place, so the mapped
+        // file is not truncated under the parser.
+        let db = match unsafe { memmap2::Mmap::map(&file) } {
+            Ok(map) => DatabaseBytes::Mapped(map),
+            Err(_) => DatabaseBytes::Read(std::fs::read(filename)?),
+        };
+        let mut out = vec![];
+        if db.starts_with(GZIP_MAGIC) {
+            use std::io::Read;
+            flate2::read::MultiGzDecoder::new(&db[..]).read_to_end(&mut out)?;
+        } else if db.starts_with(ZSTD_MAGIC) {
+            out = zstd::stream::decode_all(&db[..])?;
+        } else {
+            return Ok(db);
         }
+        Ok(DatabaseBytes::Read(out))
     }
 }
 
-fn parse_until_next_source_line(fp: &mut BufReader<File>) -> Result<Vec<String>, Error> {
-    let mut lines: Vec<String> = vec![];
-    while let Ok(line) = parse_until_empty_line(fp) {
-        lines.push(line);
-        let ch = peek(fp) as char;
-        if ch.is_digit(10) {
-            return Ok(lines);
-        } else if at_filemark(fp) {
-            return Ok(lines);
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+impl std::ops::Deref for DatabaseBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            DatabaseBytes::Mapped(map) => map,
+            DatabaseBytes::Read(bytes) => bytes,
         }
     }
+}
 
-    Err(Error::new(
-        ErrorKind::InvalidData,
-        "Failed to locate empty line.",
-    ))
+// The database an inverted index file (cscope.in.out or cscope.po.out)
+// belongs to.
+fn index_database(filename: &Path) -> Option<PathBuf> {
+    let name = filename.file_name()?.to_str()?;
+    let stem = name
+        .strip_suffix(".in.out")
+        .or_else(|| name.strip_suffix(".po.out"))?;
+    Some(filename.with_file_name(format!("{}.out", stem)))
 }
 
-fn at_filemark(fp: &mut BufReader<File>) -> bool {
-    let found: bool;
-    let idx = fp.seek(SeekFrom::Current(0)).unwrap_or(0);
-    match parse_optional_mark(fp) {
-        Ok(opt) => match opt {
-            Some(m) => found = m == FileMark::File,
-            None => found = false,
-        },
-        Err(_) => found = false,
+// Parse a database held in memory.
+pub fn parse_buffer(bytes: &[u8]) -> Result<Cscope, CscopeError> {
+    parse_buffer_with(bytes, ParseOptions::default())
+}
+
+pub fn parse_buffer_with(bytes: &[u8], options: ParseOptions) -> Result<Cscope, CscopeError> {
+    parse(bytes, options)
+}
+
+// Parse a database a file at a time: `each` is handed the database holding
+// only the records of one file, complete with signatures and extents, which
+// are let go of afterwards.  However large the database, only the records
+// of its largest file are held at once (along with the bytes of the file).
+// The database returned has the header, the trailer and the warnings, but
+// none of the records.
+pub fn parse_database_streaming(
+    filename: &Path,
+    options: ParseOptions,
+    mut each: impl FnMut(&mut Cscope) -> Result<(), CscopeError>,
+) -> Result<Cscope, CscopeError> {
+    let db = DatabaseBytes::open(filename)?;
+    parse_with(&db, options, |db, records, cscope| {
+        parse_records(db, records, cscope, |cscope| {
+            cscope.reconstruct_signatures();
+            cscope.find_function_ends();
+            each(cscope)?;
+            cscope.symbols.clear();
+            Ok(())
+        })
+        .map(|_| ())
+    })
+}
+
+// Any input, however malformed, must end in an Err rather than a panic.
+fn parse(db: &[u8], options: ParseOptions) -> Result<Cscope, CscopeError> {
+    let mut cscope = parse_with(db, options, parse_files)?;
+    cscope.reconstruct_signatures();
+    cscope.find_function_ends();
+    Ok(cscope)
+}
+
+// Parse the header and trailer, and the records in between with `records`.
+fn parse_with(
+    db: &[u8],
+    options: ParseOptions,
+    records: impl FnOnce(&[u8], &[u8], &mut Cscope) -> Result<(), CscopeError>,
+) -> Result<Cscope, CscopeError> {
+    let (mut cscope, body) = parse_header(db)?;
+    cscope.options = options;
+    let past_end = cscope.trailer_offset > db.len() as u64;
+    if past_end && !options.lenient {
+        return Err(CscopeError::TrailerPastEnd {
+            trailer_offset: cscope.trailer_offset,
+        });
     }
-    match fp.seek(SeekFrom::Start(idx)) {
-        Ok(_) => found,
-        Err(_) => false,
+    let end = (cscope.trailer_offset as usize).min(db.len());
+    let body = &db[db.len() - body.len()..end];
+    records(db, body, &mut cscope)?;
+    // The trailer went with the end of the database.
+    if past_end {
+        if !cscope
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::Truncated)
+        {
+            cscope.warnings.push(Warning {
+                kind: WarningKind::Truncated,
+                offset: db.len() as u64,
+                mark: None,
+            });
+        }
+        return Ok(cscope);
     }
+    match parse_trailer(db, &cscope) {
+        Ok(trailer) => cscope.trailer = trailer,
+        Err(_) if options.lenient => cscope.warnings.push(Warning {
+            kind: WarningKind::InvalidTrailer,
+            offset: cscope.trailer_offset,
+            mark: None,
+        }),
+        Err(e) => return Err(e),
+    }
+    Ok(cscope)
 }
 
-// Parse the symbols for a file.
-fn parse_symbol_data(fp: &mut BufReader<File>, cscope: &mut Cscope) -> Result<(), Error> {
-    // <file mark> <file path>
-    let mut mark = parse_file_mark(fp)?;
-    if mark != FileMark::File {
-        return Err(Error::new(
-            ErrorKind::NotFound,
-            "Failed to find file marker.",
-        ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdb::{database, database_in, sample};
+
+    // xorshift64, the same inputs on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            (0..len).map(|_| self.next() as u8).collect()
+        }
     }
-    let fname = parse_file_path(fp)?;
 
-    // <empty line>
-    parse_empty_line(fp)?;
+    #[test]
+    fn parses_sample() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        let marks: Vec<FileMark> = cscope.symbols().iter().map(|s| s.mark()).collect();
+        assert_eq!(
+            marks,
+            vec![
+                FileMark::StructDefinition,
+                FileMark::FunctionDefinition,
+                FileMark::FunctionCall,
+                FileMark::FunctionEnd
+            ]
+        );
+        assert_eq!(cscope.symbols()[1].extent(), Some((3, 5)));
+        assert_eq!(cscope.trailer().source_dirs, vec!["."]);
+        assert_eq!(cscope.trailer().source_files, vec!["src/main.c"]);
+    }
 
-    // For each source line. (Should have used a parser combinator for this...)
-    while fp.seek(SeekFrom::Current(0))? < cscope.trailer_offset {
-        if at_filemark(fp) {
-            break;
+    #[test]
+    fn parses_compressed_database() {
+        assert_eq!(decompress(b"\x12x\x80\x11y)"), b"int x  if (y)".to_vec());
+        let cscope = parse_buffer(&sample()).unwrap();
+        let options = crate::writer::WriteOptions {
+            compressed: true,
+            ..Default::default()
+        };
+        let compressed = crate::writer::write_database(&cscope, options);
+        assert!(compressed.len() < sample().len());
+        let copy = parse_buffer(&compressed).unwrap();
+        assert!(copy.is_compressed() && !cscope.is_compressed());
+        let texts = |c: &Cscope| -> Vec<(String, String)> {
+            c.symbols()
+                .iter()
+                .map(|s| (s.name().to_string(), s.line_text().to_string()))
+                .collect()
+        };
+        assert_eq!(texts(&copy), texts(&cscope));
+    }
+
+    #[test]
+    fn demangles_only_the_mangled_names() {
+        let db = database(
+            "\t@src/shape.cc\n\n\
+             1 double \n\t$_ZN5shape4areaEv\n() {\n\n\
+             2 }\n\n\t}\n\n\
+             3 int \n\t$main\n(void) {\n\n\
+             4 }\n\n\t}\n\n\
+             5 int \n\t$_Z_private\n(void) {\n\n\
+             6 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let mut cscope = parse_buffer(&db).unwrap();
+        cscope.demangle_names();
+        let names: Vec<(&str, Option<&str>)> = cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.mark() == FileMark::FunctionDefinition)
+            .map(|s| (s.display_name(), s.demangled()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("shape::area()", Some("shape::area()")),
+                ("main", None),
+                // Not a mangled name after all.
+                ("_Z_private", None),
+            ]
+        );
+        assert!(cscope.to_string().contains("├ shape::area()"));
+    }
+
+    #[test]
+    fn merged_databases_keep_the_first_records_of_each_file() {
+        let db = |dir: &str, function: &str| {
+            let body = format!(
+                "\t@src/main.c\n\n1 int \n\t${}\n(void) {{\n\n2 }}\n\n\t}}\n\n\t@\n",
+                function
+            );
+            parse_buffer(&database_in(dir, &body)).unwrap()
+        };
+        let mut cscope = db("/proj", "main");
+        cscope.merge(db("/proj/lib", "helper"));
+        cscope.merge(db("/proj", "again"));
+        cscope.merge(db("/elsewhere", "other"));
+        let functions: Vec<(&str, &str)> = cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.mark() == FileMark::FunctionDefinition)
+            .map(|s| (s.filename(), s.name()))
+            .collect();
+        // Made relative to /proj where they can be.
+        assert_eq!(
+            functions,
+            vec![
+                ("src/main.c", "main"),
+                ("lib/src/main.c", "helper"),
+                ("/elsewhere/src/main.c", "other"),
+            ]
+        );
+        assert_eq!(cscope.trailer().source_dirs, vec![".", "lib", "/elsewhere"]);
+        assert_eq!(
+            cscope.trailer().source_files,
+            vec!["src/main.c", "lib/src/main.c", "/elsewhere/src/main.c"]
+        );
+    }
+
+    #[test]
+    fn reads_inverted_index_header() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        let options = crate::writer::WriteOptions {
+            inverted_index: true,
+            ..Default::default()
+        };
+        let indexed = parse_buffer(&crate::writer::write_database(&cscope, options)).unwrap();
+        assert_eq!(indexed.inverted_index_terms(), Some(3));
+        assert_eq!(indexed.symbols().len(), cscope.symbols().len());
+        assert_eq!(cscope.inverted_index_terms(), None);
+        assert_eq!(
+            index_database(Path::new("db/cscope.in.out")),
+            Some(PathBuf::from("db/cscope.out"))
+        );
+    }
+
+    #[test]
+    fn tree_dirs_heads_each_directory_once() {
+        let db = database(
+            "\t@src/a.c\n\n1 int \n\t$a\n(void) {\n\n2 }\n\n\t}\n\n\
+             \t@src-old/b.c\n\n1 int \n\t$b\n(void) {\n\n2 }\n\n\t}\n\n\
+             \t@src/net/c.c\n\n1 int \n\t$c\n(void) {\n\n2 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let cscope = parse_buffer(&db).unwrap();
+        let tree = Tree::new(&cscope, RenderOptions::default())
+            .with_dirs()
+            .to_string();
+        assert_eq!(tree.matches("•src/\n").count(), 1, "{}", tree);
+    }
+
+    #[test]
+    fn streams_the_records_of_each_file() {
+        let path = std::env::temp_dir().join(format!("code-ls-stream-{}.out", std::process::id()));
+        std::fs::write(&path, sample()).unwrap();
+        let mut streamed: Vec<(FileMark, Option<(u64, u64)>)> = vec![];
+        let cscope = parse_database_streaming(&path, ParseOptions::default(), |file| {
+            streamed.extend(file.symbols().iter().map(|s| (s.mark(), s.extent())));
+            Ok(())
+        });
+        std::fs::remove_file(&path).unwrap();
+        let cscope = cscope.unwrap();
+        assert!(cscope.symbols().is_empty());
+        assert_eq!(cscope.trailer().source_files, vec!["src/main.c"]);
+        let parsed: Vec<(FileMark, Option<(u64, u64)>)> = parse_buffer(&sample())
+            .unwrap()
+            .symbols()
+            .iter()
+            .map(|s| (s.mark(), s.extent()))
+            .collect();
+        assert_eq!(streamed, parsed);
+    }
+
+    #[test]
+    fn reads_gzip_and_zstd_databases() {
+        use std::io::Write;
+        let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gzip.write_all(&sample()).unwrap();
+        let compressed = [
+            ("gz", gzip.finish().unwrap()),
+            ("zst", zstd::stream::encode_all(&sample()[..], 0).unwrap()),
+        ];
+        let expected = format!("{:?}", parse_buffer(&sample()).unwrap().symbols());
+        for (ext, bytes) in compressed {
+            let path =
+                std::env::temp_dir().join(format!("code-ls-{}.out.{}", std::process::id(), ext));
+            std::fs::write(&path, bytes).unwrap();
+            let cscope = parse_database(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(format!("{:?}", cscope.unwrap().symbols()), expected);
         }
-        // <line number> <blank> <non-symbol text>
-        let line_number = parse_line_number_and_blank(fp)?;
-        let mut non_sym_text1 = parse_to_end(fp)?;
-        non_sym_text1.retain(|c| c != '\n');
+    }
 
-        // <optional mark> <symbol>
-        match parse_optional_mark(fp)? {
-            Some(m) => mark = m,
-            None => mark = FileMark::WTF,
+    #[test]
+    fn serialized_database_reads_back_the_same() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        let json = serde_json::to_string(&cscope).unwrap();
+        let copy: Cscope = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", copy), format!("{:?}", cscope));
+    }
+
+    #[test]
+    fn errors_say_where_and_what_was_expected() {
+        let db = String::from_utf8(sample())
+            .unwrap()
+            .replace("\n4 \n", "\nX \n");
+        let offset = db.find("X \n").unwrap() as u64;
+        match parse_buffer(db.as_bytes()) {
+            Err(CscopeError::Syntax { at, expected }) => {
+                assert_eq!(expected, Some("a line number"));
+                assert_eq!(at.offset, offset);
+                assert_eq!(at.line, 12);
+                assert!(at.found.starts_with(b"X \n\t`puts"));
+            }
+            _ => panic!("not a syntax error"),
         }
-        let symbol = parse_to_end(fp)?.trim().to_string();
+    }
 
-        // <non-symbol text>
-        let source = parse_until_next_source_line(fp)?;
-        let mut non_sym_text2: String = if source.is_empty() {
-            " ".to_string()
-        } else {
-            source[0].clone()
+    #[test]
+    fn lenient_parse_skips_malformed_records() {
+        let db = String::from_utf8(sample())
+            .unwrap()
+            .replace("\n4 \n", "\nX \n");
+        assert!(parse_buffer(db.as_bytes()).is_err());
+        let options = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
         };
-        non_sym_text2.retain(|c| c != '\n');
-
-        let sym = Symbol {
-            mark: mark,
-            filename: fname.clone(),
-            line_number: line_number,
-            name: symbol,
-            non_sym_text1: non_sym_text1,
-            non_sym_text2: non_sym_text2,
+        let cscope = parse(db.as_bytes(), options).unwrap();
+        assert_eq!(cscope.symbols().len(), 3);
+        assert_eq!(cscope.warnings().len(), 1);
+        assert_eq!(cscope.warnings()[0].kind, WarningKind::SkippedRecord);
+    }
+
+    #[test]
+    fn lenient_parse_keeps_the_records_before_a_truncation() {
+        let db = sample();
+        let cut = String::from_utf8_lossy(&db).find("(\"hi\")").unwrap();
+        let options = ParseOptions {
+            lenient: true,
+            ..ParseOptions::default()
         };
-        cscope.symbols.push(sym);
+        let cscope = parse(&db[..cut], options).unwrap();
+        let names: Vec<&str> = cscope.symbols().iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["point", "main"]);
+        assert_eq!(cscope.warnings()[0].kind, WarningKind::Truncated);
+    }
 
-        // Stop if we reach a file marker prefix (tab character).
-        // This normally is a line number but will be a tab
-        // when we reach the trailer start or file mark.
-        if peek(fp) == '\t' as u8 {
-            break;
+    #[test]
+    fn parallel_parse_matches_one_thread() {
+        use crate::fixture::{generate, FixtureOptions};
+        use crate::writer::WriteOptions;
+        let records = |cscope: &Cscope| -> Vec<(String, u64, String)> {
+            cscope
+                .symbols()
+                .iter()
+                .map(|s| {
+                    (
+                        s.filename().to_string(),
+                        s.line_number(),
+                        s.name().to_string(),
+                    )
+                })
+                .collect()
+        };
+        for truncated in [false, true] {
+            let db = generate(&FixtureOptions {
+                files: 40,
+                symbols_per_file: 25,
+                write: WriteOptions::default(),
+                truncated,
+            });
+            let options = ParseOptions {
+                lenient: truncated,
+                ..ParseOptions::default()
+            };
+            let one = parse_with(&db, options, |db, records, cscope| {
+                parse_records(db, records, cscope, |_| Ok(())).map(|_| ())
+            })
+            .unwrap();
+            let parallel = parse_with(&db, options, parse_files_in_parallel).unwrap();
+            assert_eq!(records(&one), records(&parallel));
+            assert_eq!(one.warnings().len(), parallel.warnings().len());
         }
     }
-    Ok(())
-}
 
-fn parse_body(fp: &mut BufReader<File>, cscope: &mut Cscope) -> Result<(), Error> {
-    // Parse the symbol data until we reach the trailer.
-    while fp.seek(SeekFrom::Current(0))? < cscope.trailer_offset {
-        parse_symbol_data(fp, cscope)?;
-        // Stop if we are at newline before the trailer marker (just before the trailer).
-        if fp.seek(SeekFrom::Current(0))? + 3 == cscope.trailer_offset {
-            break;
+    #[test]
+    fn random_bytes_are_rejected() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..2000 {
+            let len = rng.below(512);
+            let bytes = rng.bytes(len);
+            assert!(parse_buffer(&bytes).is_err());
         }
     }
-    Ok(())
-}
 
-pub fn parse_database(filename: &Path) -> Result<(), Error> {
-    let mut fp = BufReader::new(File::open(filename)?);
-    let mut cscope = parse_header(&mut fp)?;
-    if !cscope.is_compressed() {
-        return Err(Error::new(
-            ErrorKind::InvalidInput,
-            "The cscope database must not be compressed.  See the '-c' option in the cscope manpage.",
-        ));
+    #[test]
+    fn random_bodies_do_not_panic() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let len = rng.below(512);
+            let mut bytes =
+                format!("cscope 15 /nonexistent -c {:010}\n", rng.below(600)).into_bytes();
+            bytes.extend(rng.bytes(len));
+            let _ = parse_buffer(&bytes);
+        }
+    }
+
+    #[test]
+    fn truncated_databases_are_rejected() {
+        let db = sample();
+        let body_end = db.len() - b"1\n.\n0\n0\n1\n12\nsrc/main.c\n".len() - 3;
+        for len in 0..body_end {
+            assert!(parse_buffer(&db[..len]).is_err(), "truncated at {}", len);
+        }
+    }
+
+    #[test]
+    fn mutated_databases_do_not_panic() {
+        // A line number recorded again with a longer text, the name of the
+        // second record past the end of the first's.
+        let repeated = database(
+            "\t@src/main.c\n\n\
+             5 x\n\ty\n;\n\n\
+             5 static int very_long_prefix_here \n\t$f\n(void)\n\n\
+             \t@\n",
+        );
+        let _ = parse_buffer(&repeated);
+
+        let db = sample();
+        let mut rng = Rng(0xd1b5_4a32_d192_ed03);
+        for _ in 0..5000 {
+            let mut bytes = db.clone();
+            for _ in 0..1 + rng.below(4) {
+                let pos = rng.below(bytes.len());
+                match rng.below(3) {
+                    0 => bytes[pos] = rng.next() as u8,
+                    1 => bytes.insert(pos, b"\t\n @$}0 9"[rng.below(9)]),
+                    _ => {
+                        bytes.remove(pos);
+                    }
+                }
+            }
+            let _ = parse_buffer(&bytes);
+            let _ = parse(
+                &bytes,
+                ParseOptions {
+                    lenient: true,
+                    ..ParseOptions::default()
+                },
+            );
+        }
     }
-    parse_body(&mut fp, &mut cscope)?;
-    println!("{}", cscope);
-    Ok(())
 }
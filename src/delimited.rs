@@ -0,0 +1,97 @@
+use crate::table::Table;
+
+// A field as RFC 4180 has it, quoted when it holds the delimiter, a quote or
+// a line break, its quotes doubled.
+fn csv_field(text: &str) -> String {
+    if text.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+// Tab separated values have no quoting, the tabs and line breaks of a field
+// are written as spaces.
+fn tsv_field(text: &str) -> String {
+    text.replace(&['\t', '\n', '\r'][..], " ")
+}
+
+fn write(tables: &[Table], delimiter: &str, field: fn(&str) -> String) -> String {
+    let mut out = String::new();
+    for (i, table) in tables.iter().enumerate() {
+        // Several tables, e.g. those of stats, are apart by a blank line.
+        if i > 0 {
+            out.push('\n');
+        }
+        let header: Vec<String> = table.header.iter().map(|h| field(h)).collect();
+        out.push_str(&header.join(delimiter));
+        out.push('\n');
+        for row in table.rows.iter() {
+            let cells: Vec<String> = row.iter().map(|c| field(&c.to_string())).collect();
+            out.push_str(&cells.join(delimiter));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// Comma separated values with a header row, for spreadsheets and pandas.
+pub fn csv(tables: &[Table]) -> String {
+    write(tables, ",", csv_field)
+}
+
+// Tab separated values with a header row.
+pub fn tsv(tables: &[Table]) -> String {
+    write(tables, "\t", tsv_field)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Cell;
+
+    fn table() -> Table {
+        let mut table = Table::new("macros", &["name", "line", "value"]);
+        for (name, line, value) in [
+            ("MAX", 1, "max(a, b)"),
+            ("QUOTE", 2, "\"q\""),
+            ("TABBED", 3, "a\tb\nc"),
+        ] {
+            table
+                .rows
+                .push(vec![Cell::from(name), Cell::from(line), Cell::from(value)]);
+        }
+        table
+    }
+
+    #[test]
+    fn csv_quotes_delimiters_quotes_and_line_breaks() {
+        assert_eq!(
+            csv(&[table()]),
+            "name,line,value\n\
+             MAX,1,\"max(a, b)\"\n\
+             QUOTE,2,\"\"\"q\"\"\"\n\
+             TABBED,3,\"a\tb\nc\"\n"
+        );
+    }
+
+    #[test]
+    fn tsv_turns_tabs_and_line_breaks_into_spaces() {
+        assert_eq!(
+            tsv(&[table()]),
+            "name\tline\tvalue\n\
+             MAX\t1\tmax(a, b)\n\
+             QUOTE\t2\t\"q\"\n\
+             TABBED\t3\ta b c\n"
+        );
+    }
+
+    #[test]
+    fn tables_are_apart_by_a_blank_line() {
+        let totals = Table::new("totals", &["what", "count"]);
+        assert_eq!(
+            csv(&[totals, Table::new("files", &["file"])]),
+            "what,count\n\nfile\n"
+        );
+    }
+}
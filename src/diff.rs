@@ -0,0 +1,286 @@
+use crate::cscope::{Cscope, Symbol};
+use crate::render::{pad, width, RenderOptions};
+use crate::table::{Cell, Table};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Change {
+    Added,
+    Removed,
+    // In another file, or with `lines` on another line.
+    Relocated,
+}
+
+impl Change {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Relocated => "relocated",
+        }
+    }
+}
+
+// A definition that changed, with where it was and where it is now.
+struct Entry<'a> {
+    change: Change,
+    old: Option<&'a Symbol>,
+    new: Option<&'a Symbol>,
+}
+
+impl<'a> Entry<'a> {
+    // The definition as it is now, or as it was when it is gone.
+    fn symbol(&self) -> &'a Symbol {
+        self.new
+            .or(self.old)
+            .expect("a definition on one side at least")
+    }
+}
+
+// The definitions added, removed and relocated from one database to another.
+// Definitions are told apart by their kind and name, those of the same kind
+// and name (statics in several files) are matched up by file first.
+pub struct Diff<'a> {
+    entries: Vec<Entry<'a>>,
+    options: RenderOptions,
+}
+
+impl<'a> Diff<'a> {
+    // With `lines`, definitions that stay in their file but move to another
+    // line are relocated too, rather than only those moving file.
+    pub fn new(old: &'a Cscope, new: &'a Cscope, lines: bool, options: RenderOptions) -> Diff<'a> {
+        type Sides<'a> = (Vec<&'a Symbol>, Vec<&'a Symbol>);
+        let mut by_name: BTreeMap<(&str, &str), Sides> = BTreeMap::new();
+        for sym in old
+            .symbols()
+            .iter()
+            .filter(|s| s.mark().is_outer_definition())
+        {
+            let key = (sym.mark().name(), sym.display_name());
+            by_name.entry(key).or_default().0.push(sym);
+        }
+        for sym in new
+            .symbols()
+            .iter()
+            .filter(|s| s.mark().is_outer_definition())
+        {
+            let key = (sym.mark().name(), sym.display_name());
+            by_name.entry(key).or_default().1.push(sym);
+        }
+
+        let mut entries = vec![];
+        for (_, (mut was, mut is)) in by_name.into_iter() {
+            // Those where they were, then those still in the same file.
+            let same_line = |a: &Symbol, b: &Symbol| {
+                a.filename() == b.filename() && a.line_number() == b.line_number()
+            };
+            let same_file = |a: &Symbol, b: &Symbol| a.filename() == b.filename();
+            for same in [&same_line as &dyn Fn(&Symbol, &Symbol) -> bool, &same_file] {
+                was.retain(|a| match is.iter().position(|b| same(a, b)) {
+                    Some(idx) => {
+                        let b = is.remove(idx);
+                        if lines && !same_line(a, b) {
+                            entries.push(Entry {
+                                change: Change::Relocated,
+                                old: Some(a),
+                                new: Some(b),
+                            });
+                        }
+                        false
+                    }
+                    None => true,
+                });
+            }
+            let moved = was.len().min(is.len());
+            for (a, b) in was.drain(..moved).zip(is.drain(..moved)) {
+                entries.push(Entry {
+                    change: Change::Relocated,
+                    old: Some(a),
+                    new: Some(b),
+                });
+            }
+            entries.extend(was.into_iter().map(|a| Entry {
+                change: Change::Removed,
+                old: Some(a),
+                new: None,
+            }));
+            entries.extend(is.into_iter().map(|b| Entry {
+                change: Change::Added,
+                old: None,
+                new: Some(b),
+            }));
+        }
+        entries.sort_by(|a, b| {
+            let (x, y) = (a.symbol(), b.symbol());
+            a.change
+                .cmp(&b.change)
+                .then(x.filename().cmp(y.filename()))
+                .then(x.line_number().cmp(&y.line_number()))
+        });
+        Diff { entries, options }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new("diff", &["change", "kind", "name", "file", "line", "was"]);
+        for entry in self.entries.iter() {
+            let sym = entry.symbol();
+            let was = match (entry.change, entry.old) {
+                (Change::Relocated, Some(old)) => {
+                    format!("{}:{}", old.filename(), old.line_number())
+                }
+                _ => String::new(),
+            };
+            table.rows.push(vec![
+                Cell::from(entry.change.name()),
+                Cell::from(sym.mark().name()),
+                Cell::from(sym.display_name()),
+                Cell::from(sym.filename()),
+                Cell::from(sym.line_number()),
+                Cell::from(was),
+            ]);
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for Diff<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let len = |column: fn(&Symbol) -> &str| {
+            self.entries
+                .iter()
+                .map(|e| width(column(e.symbol())))
+                .max()
+                .unwrap_or(0)
+        };
+        let (name_len, kind_len) = (len(|s| s.display_name()), len(|s| s.mark().name()));
+        let at = |sym: &Symbol| {
+            format!(
+                "{}:{}",
+                self.options.file(sym.filename()),
+                sym.line_number()
+            )
+        };
+        let mut heading: Option<Change> = None;
+        for entry in self.entries.iter() {
+            if heading != Some(entry.change) {
+                heading = Some(entry.change);
+                let count = self
+                    .entries
+                    .iter()
+                    .filter(|e| e.change == entry.change)
+                    .count();
+                write!(f, "•{}: {}\n╰─╮\n", entry.change.name(), count)?;
+            }
+            let sym = entry.symbol();
+            let place = match (entry.old, entry.new) {
+                (Some(old), Some(new)) => format!("{} → {}", at(old), at(new)),
+                _ => at(sym),
+            };
+            writeln!(
+                f,
+                "  ├ {} {} {}",
+                pad(sym.mark().name(), kind_len),
+                pad(sym.display_name(), name_len),
+                place
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    // Functions defined at the given lines of their files.
+    fn defining(functions: &[(&str, &str, u64)]) -> Cscope {
+        let mut body = String::new();
+        let mut file = "";
+        for (name, in_file, line) in functions.iter() {
+            if *in_file != file {
+                file = in_file;
+                body.push_str(&format!("\t@{}\n\n", file));
+            }
+            body.push_str(&format!(
+                "{} int \n\t${}\n(void) {{\n\n{} }}\n\n\t}}\n\n",
+                line,
+                name,
+                line + 1
+            ));
+        }
+        body.push_str("\t@\n");
+        parse_buffer(&database(&body)).unwrap()
+    }
+
+    // The change, name and former place of each entry.
+    fn changes(diff: &Diff) -> Vec<[String; 3]> {
+        diff.table()
+            .rows
+            .iter()
+            .map(|r| [r[0].to_string(), r[2].to_string(), r[5].to_string()])
+            .collect()
+    }
+
+    fn change(change: &str, name: &str, was: &str) -> [String; 3] {
+        [change.to_string(), name.to_string(), was.to_string()]
+    }
+
+    #[test]
+    fn definitions_are_added_removed_and_moved_to_another_file() {
+        let old = defining(&[
+            ("main", "src/main.c", 1),
+            ("gone", "src/main.c", 3),
+            ("util", "src/main.c", 5),
+        ]);
+        let new = defining(&[
+            ("main", "src/main.c", 2),
+            ("fresh", "src/main.c", 4),
+            ("util", "src/util.c", 1),
+        ]);
+        let diff = Diff::new(&old, &new, false, RenderOptions::default());
+        // main only moved down its file.
+        assert_eq!(
+            changes(&diff),
+            vec![
+                change("added", "fresh", ""),
+                change("removed", "gone", ""),
+                change("relocated", "util", "src/main.c:5"),
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "•added: 1\n╰─╮\n  ├ function fresh src/main.c:4\n\
+             •removed: 1\n╰─╮\n  ├ function gone  src/main.c:3\n\
+             •relocated: 1\n╰─╮\n  ├ function util  src/main.c:5 → src/util.c:1\n"
+        );
+    }
+
+    #[test]
+    fn lines_relocates_definitions_moving_within_their_file() {
+        let old = defining(&[("main", "src/main.c", 1), ("util", "src/main.c", 5)]);
+        let new = defining(&[("main", "src/main.c", 2), ("util", "src/main.c", 5)]);
+        let diff = Diff::new(&old, &new, true, RenderOptions::default());
+        assert_eq!(
+            changes(&diff),
+            vec![change("relocated", "main", "src/main.c:1")]
+        );
+        assert!(Diff::new(&old, &old, true, RenderOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn statics_of_the_same_name_are_matched_by_file() {
+        let old = defining(&[("init", "src/a.c", 1), ("init", "src/b.c", 1)]);
+        let new = defining(&[("init", "src/b.c", 1), ("init", "src/c.c", 1)]);
+        let diff = Diff::new(&old, &new, false, RenderOptions::default());
+        assert_eq!(
+            changes(&diff),
+            vec![change("relocated", "init", "src/a.c:1")]
+        );
+    }
+}
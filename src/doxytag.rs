@@ -0,0 +1,206 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::macros;
+use crate::outline;
+use std::collections::BTreeMap;
+
+// The characters of a name Doxygen replaces to make an HTML file name of it.
+fn doxygen_file_name(name: &str) -> String {
+    let mut escaped = String::new();
+    for c in name.chars() {
+        match c {
+            '_' => escaped.push_str("__"),
+            '.' => escaped.push_str("_8"),
+            ':' => escaped.push_str("_1"),
+            '/' => escaped.push_str("_2"),
+            '<' => escaped.push_str("_3"),
+            '>' => escaped.push_str("_4"),
+            '*' => escaped.push_str("_5"),
+            '&' => escaped.push_str("_6"),
+            ' ' => escaped.push_str("_01"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// A function's signature split around its name: the return type and the
+// parameter list.
+fn split_signature(sym: &Symbol) -> (&str, &str) {
+    let sig = sym.signature().unwrap_or("");
+    let name = sym.name();
+    // The name is the last whole word before the parameter list, the return
+    // type may hold it as part of a longer word, as size_t holds size.
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let params = sig.find('(').unwrap_or(sig.len());
+    let found = sig[..params]
+        .rmatch_indices(name)
+        .map(|(idx, _)| idx)
+        .find(|idx| {
+            !is_word(sig[..*idx].chars().next_back())
+                && !is_word(sig[idx + name.len()..].chars().next())
+        });
+    match found {
+        Some(idx) => (sig[..idx].trim(), sig[idx + name.len()..].trim()),
+        None => ("", sig),
+    }
+}
+
+// One <member> of a compound.  The anchors point at the lines of the source
+// browser pages, which is where Doxygen puts "l<line>" anchors.
+struct Member<'a> {
+    kind: &'static str,
+    sym: &'a Symbol,
+    type_: String,
+    arglist: String,
+}
+
+impl<'a> Member<'a> {
+    fn write(&self, out: &mut String) {
+        let source = format!(
+            "{}_source.html",
+            doxygen_file_name(file_name(self.sym.filename()))
+        );
+        out.push_str(&format!("    <member kind=\"{}\">\n", self.kind));
+        out.push_str(&format!("      <type>{}</type>\n", xml_escape(&self.type_)));
+        out.push_str(&format!(
+            "      <name>{}</name>\n",
+            xml_escape(self.sym.display_name())
+        ));
+        out.push_str(&format!("      <anchorfile>{}</anchorfile>\n", source));
+        out.push_str(&format!(
+            "      <anchor>l{:05}</anchor>\n",
+            self.sym.line_number()
+        ));
+        out.push_str(&format!(
+            "      <arglist>{}</arglist>\n",
+            xml_escape(&self.arglist)
+        ));
+        out.push_str("    </member>\n");
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn member_of(sym: &Symbol) -> Option<Member<'_>> {
+    let kind = match sym.mark() {
+        FileMark::FunctionDefinition => "function",
+        FileMark::OtherGlobalDefinition | FileMark::EnumStructUnionMemberGlobalDefinition => {
+            "variable"
+        }
+        FileMark::TypedefDefinition => "typedef",
+        FileMark::EnumDefinition => "enumeration",
+        _ => return None,
+    };
+    let (type_, arglist) = match sym.mark() {
+        FileMark::FunctionDefinition => split_signature(sym),
+        _ => (sym.text_before().trim(), ""),
+    };
+    Some(Member {
+        kind,
+        sym,
+        type_: type_.to_string(),
+        arglist: arglist.to_string(),
+    })
+}
+
+// A Doxygen tagfile of the definitions: a file compound for each source with
+// its functions, globals, typedefs and macros, and a compound for each
+// struct, union and class with its members.
+pub fn tagfile(cscope: &Cscope) -> String {
+    let mut files: BTreeMap<&str, Vec<Member>> = BTreeMap::new();
+    for sym in cscope.symbols().iter() {
+        if sym.mark() == FileMark::EnumStructUnionMemberGlobalDefinition {
+            continue;
+        }
+        if let Some(member) = member_of(sym) {
+            files.entry(sym.filename()).or_default().push(member);
+        }
+    }
+    for m in macros::macros(cscope) {
+        files
+            .entry(m.definition.filename())
+            .or_default()
+            .push(Member {
+                kind: "define",
+                sym: m.definition,
+                type_: String::new(),
+                arglist: m.parameters.clone().unwrap_or_default(),
+            });
+    }
+
+    let mut out = String::from("<?xml version='1.0' encoding='UTF-8' standalone='yes' ?>\n");
+    out.push_str("<tagfile>\n");
+    for (path, members) in files.iter_mut() {
+        members.sort_by_key(|m| m.sym.line_number());
+        let name = file_name(path);
+        let dir = &path[..path.len() - name.len()];
+        out.push_str("  <compound kind=\"file\">\n");
+        out.push_str(&format!("    <name>{}</name>\n", xml_escape(name)));
+        out.push_str(&format!("    <path>{}</path>\n", xml_escape(dir)));
+        out.push_str(&format!(
+            "    <filename>{}.html</filename>\n",
+            doxygen_file_name(name)
+        ));
+        for member in members.iter() {
+            member.write(&mut out);
+        }
+        out.push_str("  </compound>\n");
+    }
+
+    let is_type = |m: FileMark| {
+        matches!(
+            m,
+            FileMark::StructDefinition | FileMark::UnionDefinition | FileMark::ClassDefinition
+        )
+    };
+    for scope in outline::scopes(cscope, is_type) {
+        let kind = match scope.parent.mark() {
+            FileMark::UnionDefinition => "union",
+            FileMark::ClassDefinition => "class",
+            _ => "struct",
+        };
+        let name = scope.parent.display_name();
+        out.push_str(&format!("  <compound kind=\"{}\">\n", kind));
+        out.push_str(&format!("    <name>{}</name>\n", xml_escape(name)));
+        out.push_str(&format!(
+            "    <filename>{}{}.html</filename>\n",
+            kind,
+            doxygen_file_name(name)
+        ));
+        for member in scope.members.iter().filter_map(|m| member_of(m)) {
+            member.write(&mut out);
+        }
+        out.push_str("  </compound>\n");
+    }
+    out.push_str("</tagfile>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    #[test]
+    fn tagfile_splits_the_signature_at_the_name() {
+        let db = database(
+            "\t@src/main.c\n\n\
+             1 size_t \n\t$size\n(void) {\n\n\
+             2 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let tagfile = tagfile(&parse_buffer(&db).unwrap());
+        assert!(tagfile.contains("<type>size_t</type>"), "{}", tagfile);
+        assert!(tagfile.contains("<arglist>(void)</arglist>"), "{}", tagfile);
+    }
+}
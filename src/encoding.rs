@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+// How the bytes of names and source text in a database are read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Latin1,
+    // UTF-8 where it is valid, otherwise Latin-1.
+    Auto,
+}
+
+impl Encoding {
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        match name {
+            "utf8" => Some(Encoding::Utf8),
+            "latin1" => Some(Encoding::Latin1),
+            "auto" => Some(Encoding::Auto),
+            _ => None,
+        }
+    }
+
+    // None when the bytes are not valid UTF-8 and only UTF-8 is accepted.
+    pub fn decode(&self, bytes: &[u8]) -> Option<String> {
+        match (self, std::str::from_utf8(bytes)) {
+            (Encoding::Latin1, _) => Some(latin1(bytes)),
+            (_, Ok(s)) => Some(s.to_string()),
+            (Encoding::Auto, Err(_)) => Some(latin1(bytes)),
+            (Encoding::Utf8, Err(_)) => None,
+        }
+    }
+
+    // Like decode, but with replacement characters for invalid UTF-8.
+    pub fn decode_lossy(&self, bytes: &[u8]) -> String {
+        self.decode(bytes)
+            .unwrap_or_else(|| String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+// Every Latin-1 byte is the Unicode code point of the same value.
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::{parse_buffer_with, ParseOptions};
+    use crate::testdb::database_of;
+    use crate::warnings::WarningKind;
+
+    #[test]
+    fn latin1_bytes_are_their_code_points() {
+        let bytes = b"caf\xe9 \xb5s";
+        assert_eq!(Encoding::Latin1.decode(bytes), Some("café µs".to_string()));
+        assert_eq!(Encoding::Auto.decode(bytes), Some("café µs".to_string()));
+        assert_eq!(Encoding::Utf8.decode(bytes), None);
+        assert_eq!(Encoding::Utf8.decode_lossy(bytes), "caf\u{fffd} \u{fffd}s");
+        // Valid UTF-8 is only read as UTF-8 when Latin-1 is not asked for.
+        let utf8 = "café".as_bytes();
+        assert_eq!(Encoding::Auto.decode(utf8), Some("café".to_string()));
+        assert_eq!(Encoding::Latin1.decode(utf8), Some("cafÃ©".to_string()));
+    }
+
+    #[test]
+    fn names_and_text_are_read_in_the_encoding() {
+        let db = database_of(
+            "/nonexistent",
+            b"\t@src/main.c\n\n\
+              1 int \n\t$caf\xe9\n(void) { /* na\xefve */\n\n\
+              2 }\n\n\t}\n\n\
+              \t@\n",
+        );
+        let parse = |encoding| {
+            let options = ParseOptions {
+                encoding,
+                ..ParseOptions::default()
+            };
+            parse_buffer_with(&db, options).unwrap()
+        };
+        for encoding in [Encoding::Latin1, Encoding::Auto] {
+            let cscope = parse(encoding);
+            let sym = &cscope.symbols()[0];
+            assert_eq!(sym.display_name(), "café");
+            assert_eq!(sym.line_text(), "int café(void) { /* naïve */");
+            assert!(cscope.warnings().is_empty());
+        }
+        let cscope = parse(Encoding::Utf8);
+        assert_eq!(cscope.symbols()[0].display_name(), "<invalid utf8>");
+        let kinds: Vec<WarningKind> = cscope.warnings().iter().map(|w| w.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![WarningKind::InvalidUtf8, WarningKind::InvalidUtf8]
+        );
+    }
+}
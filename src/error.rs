@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+// At most this many of the bytes found where parsing failed are kept.
+const SNIPPET_LEN: usize = 24;
+
+// Where in a database parsing failed: the byte offset, the line of the
+// database that is on and the bytes found there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub offset: u64,
+    pub line: usize,
+    pub found: Vec<u8>,
+}
+
+impl Location {
+    pub(crate) fn new(db: &[u8], offset: u64) -> Location {
+        let at = (offset as usize).min(db.len());
+        let line = db[..at].iter().filter(|b| **b == b'\n').count() + 1;
+        let end = (at + SNIPPET_LEN).min(db.len());
+        Location {
+            offset,
+            line,
+            found: db[at..end].to_vec(),
+        }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "offset {} (line {}): \"{}\"",
+            self.offset,
+            self.line,
+            self.found.escape_ascii()
+        )?;
+        if self.found.len() == SNIPPET_LEN {
+            write!(f, "...")?;
+        }
+        Ok(())
+    }
+}
+
+// Why a database could not be parsed.
+#[derive(Debug)]
+pub enum CscopeError {
+    // The database could not be read.
+    Io(std::io::Error),
+    // What a rule of the grammar expected is not there, or nothing matched.
+    Syntax {
+        at: Location,
+        expected: Option<&'static str>,
+    },
+    // A part that matched but makes no sense, e.g. a file path that is not
+    // in the encoding, or a trailer offset inside the header.
+    Invalid {
+        at: Location,
+        what: &'static str,
+    },
+    // A mark character the parser does not know, when those are errors.
+    UnknownMark {
+        at: Location,
+        mark: u8,
+    },
+    // The records stop before the trailer does.
+    Truncated {
+        offset: u64,
+    },
+    // The header points past the end of the database for the trailer.
+    TrailerPastEnd {
+        trailer_offset: u64,
+    },
+    // An inverted index (cscope -q) was given instead of its database.
+    InvertedIndex {
+        index: PathBuf,
+        database: PathBuf,
+    },
+}
+
+impl CscopeError {
+    // Whether a lenient parse can skip the record and carry on.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            CscopeError::Syntax { .. } | CscopeError::Invalid { .. }
+        )
+    }
+
+    // The byte offset in the database the error is at, if any.
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            CscopeError::Syntax { at, .. }
+            | CscopeError::Invalid { at, .. }
+            | CscopeError::UnknownMark { at, .. } => Some(at.offset),
+            CscopeError::Truncated { offset } => Some(*offset),
+            CscopeError::TrailerPastEnd { trailer_offset } => Some(*trailer_offset),
+            CscopeError::Io(_) | CscopeError::InvertedIndex { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for CscopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CscopeError::Io(e) => write!(f, "{}", e),
+            CscopeError::Syntax {
+                at,
+                expected: Some(expected),
+            } => write!(f, "Expected {} at {}", expected, at),
+            CscopeError::Syntax { at, expected: None } => write!(f, "Invalid record at {}", at),
+            CscopeError::Invalid { at, what } => write!(f, "Invalid {} at {}", what, at),
+            CscopeError::UnknownMark { at, mark } => {
+                write!(f, "Unknown mark {:?} at {}", *mark as char, at)
+            }
+            CscopeError::Truncated { offset } => {
+                write!(f, "The records end unexpectedly at offset {}.", offset)
+            }
+            CscopeError::TrailerPastEnd { trailer_offset } => write!(
+                f,
+                "Truncated database, the trailer offset {} is past its end.",
+                trailer_offset
+            ),
+            CscopeError::InvertedIndex { index, database } => write!(
+                f,
+                "{} is an inverted index (cscope -q), read its database {} instead.",
+                index.display(),
+                database.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CscopeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CscopeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CscopeError {
+    fn from(e: std::io::Error) -> CscopeError {
+        CscopeError::Io(e)
+    }
+}
+
+// For callers working in std::io errors.
+impl From<CscopeError> for std::io::Error {
+    fn from(e: CscopeError) -> std::io::Error {
+        use std::io::ErrorKind;
+        let kind = match e {
+            CscopeError::Io(e) => return e,
+            CscopeError::Truncated { .. } | CscopeError::TrailerPastEnd { .. } => {
+                ErrorKind::UnexpectedEof
+            }
+            CscopeError::UnknownMark { .. } => ErrorKind::Unsupported,
+            _ => ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
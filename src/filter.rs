@@ -0,0 +1,235 @@
+use crate::glob;
+use std::io::Error;
+use std::path::Path;
+
+// Paths where system and toolchain headers are installed.
+const SYSTEM_PREFIXES: &[&str] = &[
+    "/usr/include",
+    "/usr/local/include",
+    "/usr/lib/gcc",
+    "/usr/lib/clang",
+    "/usr/lib/llvm",
+    "/opt/homebrew/include",
+    "/Library/Developer/CommandLineTools",
+    "/Applications/Xcode.app",
+];
+
+// The ignore file read from the directory holding the database.
+pub const IGNORE_FILE: &str = ".code-lsignore";
+
+// A line of an ignore file (gitignore syntax).
+struct IgnorePattern {
+    glob: String,
+    negated: bool,
+    dir_only: bool,
+    // Patterns containing a '/' match from the root, the others match the
+    // last component of the path at any depth.
+    anchored: bool,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str) -> Option<IgnorePattern> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        Some(IgnorePattern {
+            glob: line.trim_start_matches('/').to_string(),
+            negated,
+            dir_only,
+            anchored: line.contains('/'),
+        })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            glob::is_match(&self.glob, path)
+        } else {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            glob::is_match(&self.glob, name)
+        }
+    }
+}
+
+// The patterns of an ignore file, matched against paths relative to the
+// directory the file is in.
+struct IgnoreFile {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreFile {
+    fn is_ignored(&self, path: &str) -> bool {
+        // Ignoring a directory ignores everything beneath it.
+        let path = path.trim_start_matches("./");
+        let mut ends: Vec<usize> = path.match_indices('/').map(|(i, _)| i).collect();
+        ends.push(path.len());
+        ends.iter().any(|end| {
+            let is_dir = *end != path.len();
+            let mut ignored = false;
+            for pattern in self.patterns.iter() {
+                if pattern.matches(&path[..*end], is_dir) {
+                    ignored = !pattern.negated;
+                }
+            }
+            ignored
+        })
+    }
+}
+
+// Decides which source files are left out of the output.
+#[derive(Default)]
+pub struct PathFilter {
+    excluded_prefixes: Vec<String>,
+    ignore_files: Vec<IgnoreFile>,
+    // When there are any, the paths matching none of these are left out.
+    included_globs: Vec<String>,
+}
+
+impl PathFilter {
+    // Leave out the system headers, along with any extra system prefixes.
+    pub fn exclude_system(&mut self, extra_prefixes: &[&str]) {
+        let prefixes = SYSTEM_PREFIXES.iter().chain(extra_prefixes.iter());
+        self.excluded_prefixes
+            .extend(prefixes.map(|p| p.trim_end_matches('/').to_string()));
+    }
+
+    // Leave out the paths matched by any of the gitignore style patterns.
+    pub fn exclude_patterns(&mut self, patterns: &[&str]) {
+        let patterns = patterns.iter().filter_map(|p| IgnorePattern::parse(p));
+        self.ignore_files.push(IgnoreFile {
+            patterns: patterns.collect(),
+        });
+    }
+
+    // Leave out the paths matched by an ignore file, if there is one.
+    pub fn add_ignore_file(&mut self, path: &Path) -> Result<(), Error> {
+        if !path.is_file() {
+            return Ok(());
+        }
+        let text = std::fs::read_to_string(path)?;
+        let patterns = text.lines().filter_map(IgnorePattern::parse).collect();
+        self.ignore_files.push(IgnoreFile { patterns });
+        Ok(())
+    }
+
+    // Only keep the paths matched by one of the globs, or beneath a
+    // directory matched by one.
+    pub fn include_only(&mut self, globs: &[&str]) {
+        self.included_globs
+            .extend(globs.iter().map(|g| g.trim_start_matches("./").to_string()));
+    }
+
+    fn is_included(&self, path: &str) -> bool {
+        let path = path.trim_start_matches("./");
+        let mut ends: Vec<usize> = path.match_indices('/').map(|(i, _)| i).collect();
+        ends.push(path.len());
+        self.included_globs.is_empty()
+            || self
+                .included_globs
+                .iter()
+                .any(|glob| ends.iter().any(|end| glob::is_match(glob, &path[..*end])))
+    }
+
+    pub fn is_excluded(&self, path: &str) -> bool {
+        if !self.is_included(path) {
+            return true;
+        }
+        let system = self.excluded_prefixes.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        });
+        // Ignore files only apply beneath their own directory.
+        system || (!path.starts_with('/') && self.ignore_files.iter().any(|i| i.is_ignored(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_as_gitignore_does() {
+        // (patterns, path, left out)
+        let cases: &[(&[&str], &str, bool)] = &[
+            // Without a '/' a pattern matches a name at any depth.
+            (&["*.gen.c"], "src/net/a.gen.c", true),
+            (&["build"], "src/build/a.c", true),
+            // With one it is anchored to the directory of the ignore file.
+            (&["/main.c"], "main.c", true),
+            (&["/main.c"], "src/main.c", false),
+            (&["src/gen"], "src/gen/a.c", true),
+            (&["src/gen"], "lib/src/gen/a.c", false),
+            // A trailing '/' only matches directories.
+            (&["gen/"], "src/gen/a.c", true),
+            (&["gen/"], "src/gen", false),
+            (&["a.c/"], "a.c", false),
+            // The last pattern matching decides, negated or not.
+            (&["*.c", "!keep.c"], "src/keep.c", false),
+            (&["!keep.c", "*.c"], "src/keep.c", true),
+            (&["*.c", "!keep.c", "keep.c"], "keep.c", true),
+            // A file beneath an ignored directory cannot be put back.
+            (&["gen/", "!gen/keep.c"], "gen/keep.c", true),
+            (&["gen/*", "!gen/keep.c"], "gen/keep.c", false),
+            // "**/" matches any directory, none included.
+            (&["**/fixtures"], "fixtures/a.c", true),
+            (&["**/fixtures"], "test/unit/fixtures/a.c", true),
+            (&["test/**/a.c"], "test/a.c", true),
+            (&["test/**/a.c"], "test/x/y/a.c", true),
+            (&["test/**"], "test/x/a.c", true),
+            (&["test/**"], "test", false),
+            // Character classes and escapes.
+            (&["[a-c].c"], "src/b.c", true),
+            (&["[!a-c].c"], "src/b.c", false),
+            (&["\\#hash.c"], "#hash.c", true),
+            (&["\\!bang.c"], "!bang.c", true),
+            (&["\\*.c"], "a.c", false),
+            // Comments and blank lines are not patterns.
+            (&["# a.c", ""], "a.c", false),
+            (&["#a.c"], "#a.c", false),
+            // Absolute paths are never beneath the ignore file.
+            (&["*.h"], "/usr/include/stdio.h", false),
+        ];
+        for (patterns, path, expected) in cases.iter() {
+            let mut filter = PathFilter::default();
+            filter.exclude_patterns(patterns);
+            assert_eq!(
+                filter.is_excluded(path),
+                *expected,
+                "{:?} {}",
+                patterns,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_only_included_paths() {
+        let mut filter = PathFilter::default();
+        filter.include_only(&["src", "./lib/*.c"]);
+        assert!(!filter.is_excluded("src/net/a.c"));
+        assert!(!filter.is_excluded("lib/a.c"));
+        assert!(filter.is_excluded("lib/b/a.c"));
+        assert!(filter.is_excluded("test/a.c"));
+    }
+
+    #[test]
+    fn leaves_out_system_headers() {
+        let mut filter = PathFilter::default();
+        filter.exclude_system(&["/opt/tc/"]);
+        assert!(filter.is_excluded("/usr/include/stdio.h"));
+        assert!(filter.is_excluded("/opt/tc/include/tc.h"));
+        assert!(!filter.is_excluded("/usr/includes/a.h"));
+        assert!(!filter.is_excluded("usr/include/a.h"));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::cscope::FileMark;
+use crate::writer::{WriteOptions, Writer};
+
+// The shape of a synthetic database.
+pub struct FixtureOptions {
+    pub files: usize,
+    pub symbols_per_file: usize,
+    pub write: WriteOptions,
+    // Cut the database off halfway through its records.
+    pub truncated: bool,
+}
+
+// A database of made up C sources, each file cycling through a macro, a
+// struct, a global, and a function calling the one defined before it.
+pub fn generate(options: &FixtureOptions) -> Vec<u8> {
+    let mut writer = Writer::new(options.write);
+    for file in 0..options.files {
+        writer.file(&format!("src/file{}.c", file));
+        let mut line_number = 0;
+        let mut next_line = || {
+            line_number += 1;
+            line_number
+        };
+        let mut in_function = false;
+        for n in 0..options.symbols_per_file {
+            let name = |prefix: &str| format!("{}{}_{}", prefix, file, n);
+            match n % 5 {
+                0 => {
+                    let name = name("MACRO");
+                    writer.line(
+                        next_line(),
+                        "#define ",
+                        &[(Some(FileMark::Define), &name, &format!(" {}", n))],
+                    );
+                    writer.end_mark(FileMark::DefineEnd);
+                }
+                1 => {
+                    let name = name("s");
+                    writer.line(
+                        next_line(),
+                        "struct ",
+                        &[(Some(FileMark::StructDefinition), &name, " {")],
+                    );
+                    writer.line(next_line(), "};", &[]);
+                }
+                2 => {
+                    let name = name("g");
+                    writer.line(
+                        next_line(),
+                        "int ",
+                        &[(Some(FileMark::OtherGlobalDefinition), &name, ";")],
+                    );
+                }
+                3 => {
+                    let name = name("f");
+                    writer.line(
+                        next_line(),
+                        "void ",
+                        &[(Some(FileMark::FunctionDefinition), &name, "(void) {")],
+                    );
+                    in_function = true;
+                }
+                _ => {
+                    // The first function has none before it, it recurses.
+                    let callee = format!("f{}_{}", file, if n < 9 { n - 1 } else { n - 6 });
+                    writer.line(
+                        next_line(),
+                        " ",
+                        &[(Some(FileMark::FunctionCall), &callee, "();")],
+                    );
+                    writer.line(next_line(), "}", &[]);
+                    writer.end_mark(FileMark::FunctionEnd);
+                    in_function = false;
+                }
+            }
+        }
+        if in_function {
+            writer.line(next_line(), "}", &[]);
+            writer.end_mark(FileMark::FunctionEnd);
+        }
+    }
+
+    let mut db = writer.finish(".");
+    if options.truncated {
+        db.truncate(db.len() / 2);
+    }
+    db
+}
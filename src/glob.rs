@@ -0,0 +1,107 @@
+// Shell style wildcard matching of paths, as used by gitignore:
+//   *     any run of characters other than '/'
+//   ?     any one character other than '/'
+//   [...] any one character of the class, [!...] or [^...] to negate it
+//   **    any run of characters, "**/" also matching no directory at all
+//   \     makes the following character literal
+pub fn is_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
+
+fn matches(p: &[char], t: &[char]) -> bool {
+    match p.first() {
+        None => t.is_empty(),
+        Some('*') if p.get(1) == Some(&'*') => {
+            let rest = &p[2..];
+            match rest.split_first() {
+                // <dir>/**/<rest>: <rest> starts at the beginning of any path component.
+                Some(('/', rest)) => {
+                    (0..=t.len()).any(|i| (i == 0 || t[i - 1] == '/') && matches(rest, &t[i..]))
+                }
+                _ => (0..=t.len()).any(|i| matches(rest, &t[i..])),
+            }
+        }
+        Some('*') => {
+            let component = t.iter().position(|c| *c == '/').unwrap_or(t.len());
+            (0..=component).any(|i| matches(&p[1..], &t[i..]))
+        }
+        Some('?') => t.first().is_some_and(|c| *c != '/') && matches(&p[1..], &t[1..]),
+        Some('[') => match (t.first(), class(&p[1..])) {
+            (Some(c), Some((members, negated, len))) if *c != '/' => {
+                members.contains(c) != negated && matches(&p[len + 1..], &t[1..])
+            }
+            // An unterminated class is taken literally.
+            (Some('['), None) => matches(&p[1..], &t[1..]),
+            _ => false,
+        },
+        Some('\\') if p.len() > 1 => t.first() == Some(&p[1]) && matches(&p[2..], &t[1..]),
+        Some(c) => t.first() == Some(c) && matches(&p[1..], &t[1..]),
+    }
+}
+
+// Parse a character class following its '[', returning its members, whether
+// it is negated and how many pattern characters it spans (including the ']').
+fn class(p: &[char]) -> Option<(Vec<char>, bool, usize)> {
+    let negated = matches!(p.first(), Some('!') | Some('^'));
+    let start = if negated { 1 } else { 0 };
+    let mut members: Vec<char> = vec![];
+    let mut idx = start;
+    while idx < p.len() {
+        match p[idx] {
+            // A ']' right after the '[' is a member rather than the end.
+            ']' if idx > start => return Some((members, negated, idx + 1)),
+            c if p.get(idx + 1) == Some(&'-') && p.get(idx + 2).is_some_and(|e| *e != ']') => {
+                members.extend(c..=p[idx + 2]);
+                idx += 3;
+                continue;
+            }
+            c => members.push(c),
+        }
+        idx += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_as_gitignore_does() {
+        // (pattern, path, matches)
+        let cases = [
+            ("*.o", "main.o", true),
+            ("*.o", "src/main.o", false),
+            ("src/*.c", "src/main.c", true),
+            ("src/*.c", "src/net/main.c", false),
+            ("?.c", "a.c", true),
+            ("?.c", "ab.c", false),
+            ("a?b", "a/b", false),
+            ("**/gen", "gen", true),
+            ("**/gen", "src/gen", true),
+            ("**/gen", "src/ungen", false),
+            ("src/**/gen.c", "src/gen.c", true),
+            ("src/**/gen.c", "src/a/b/gen.c", true),
+            ("src/**", "src/a/b.c", true),
+            ("[abc].c", "b.c", true),
+            ("[abc].c", "d.c", false),
+            ("[a-c].c", "c.c", true),
+            ("[!a-c].c", "c.c", false),
+            ("[^a-c].c", "d.c", true),
+            ("[]].c", "].c", true),
+            ("[a-].c", "-.c", true),
+            ("[/].c", "/.c", false),
+            ("[ab", "[ab", true),
+            ("\\*.c", "*.c", true),
+            ("\\*.c", "a.c", false),
+            ("\\[a].c", "[a].c", true),
+            ("caf\u{e9}.c", "caf\u{e9}.c", true),
+            ("caf?.c", "caf\u{e9}.c", true),
+        ];
+        for (pattern, path, expected) in cases.iter() {
+            assert_eq!(is_match(pattern, path), *expected, "{} {}", pattern, path);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// The layout of a cscope database as nom parsers over its bytes.  These only
+// pick the database apart, cscope.rs turns what they find into symbols.
+// The comments in angle brackets are from the cscope man page, see cscope.rs.
+use nom::bytes::complete::{tag, take_till, take_till1};
+use nom::character::complete::{char, digit1};
+use nom::combinator::{cut, map, map_res, not, opt, rest};
+use nom::error::{context, ContextError, ErrorKind, FromExternalError, ParseError};
+use nom::multi::{length_count, many0, many0_count, many1_count, many_till};
+use nom::number::complete::u8 as byte;
+use nom::sequence::{preceded, terminated, tuple};
+use nom::IResult;
+
+// Where a rule failed, and what it expected to find there.
+#[derive(Debug, PartialEq)]
+pub struct RuleError<'a> {
+    pub input: &'a [u8],
+    pub expected: Option<&'static str>,
+}
+
+impl<'a> ParseError<&'a [u8]> for RuleError<'a> {
+    fn from_error_kind(input: &'a [u8], _: ErrorKind) -> Self {
+        RuleError {
+            input,
+            expected: None,
+        }
+    }
+
+    fn append(_: &'a [u8], _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+// The innermost rule knows best what was expected.
+impl<'a> ContextError<&'a [u8]> for RuleError<'a> {
+    fn add_context(_: &'a [u8], expected: &'static str, other: Self) -> Self {
+        RuleError {
+            expected: other.expected.or(Some(expected)),
+            ..other
+        }
+    }
+}
+
+impl<'a, E> FromExternalError<&'a [u8], E> for RuleError<'a> {
+    fn from_external_error(input: &'a [u8], kind: ErrorKind, _: E) -> Self {
+        RuleError::from_error_kind(input, kind)
+    }
+}
+
+type Rule<'a, T> = IResult<&'a [u8], T, RuleError<'a>>;
+
+// cscope <version> <current dir> [-c] [-q <terms>] [-T] <trailer offset>
+pub struct Header<'a> {
+    pub version: u32,
+    pub current_dir: &'a [u8],
+    pub trailer_offset: u64,
+    // The digits of the trailer offset.
+    pub offset: &'a [u8],
+    // The whole line, for the flags.
+    pub line: &'a [u8],
+}
+
+// A symbol: where its record starts, its mark (none for a reference), its
+// name and the non-symbol text following it.
+pub struct RawSymbol<'a> {
+    pub at: &'a [u8],
+    pub mark: Option<u8>,
+    pub name: &'a [u8],
+    pub text: &'a [u8],
+}
+
+pub enum Record<'a> {
+    // <mark> <empty line>
+    // A function or #define end mark, closing the definition above.
+    End(RawSymbol<'a>),
+    // <line number> <blank> <non-symbol text>
+    // <optional mark> <symbol>
+    // <non-symbol text>
+    // repeat above 2 lines as necessary
+    // <empty line>
+    Line {
+        number: u64,
+        text: &'a [u8],
+        symbols: Vec<RawSymbol<'a>>,
+    },
+}
+
+fn number<T: std::str::FromStr>(digits: &[u8]) -> Result<T, T::Err> {
+    String::from_utf8_lossy(digits).parse()
+}
+
+fn is_blank_or_newline(b: u8) -> bool {
+    b == b' ' || b == b'\n'
+}
+
+// The rest of a line, without the newline ending it.
+fn line(i: &[u8]) -> Rule<'_, &[u8]> {
+    context(
+        "a newline",
+        terminated(take_till(|b| b == b'\n'), char('\n')),
+    )(i)
+}
+
+// <empty line>
+pub fn empty_line(i: &[u8]) -> Rule<'_, ()> {
+    context("an empty line", map(char('\n'), |_| ()))(i)
+}
+
+// <tab> <mark>
+fn mark(i: &[u8]) -> Rule<'_, u8> {
+    context("a mark", preceded(char('\t'), byte))(i)
+}
+
+pub fn header(i: &[u8]) -> Rule<'_, Header<'_>> {
+    let word = || take_till1(is_blank_or_newline);
+    let (rest, (_, version, _, current_dir, mut words, _)) = tuple((
+        context("a cscope header", tag("cscope ")),
+        context("a version", map_res(digit1, number::<u32>)),
+        char(' '),
+        word(),
+        many0(preceded(many1_count(char(' ')), word())),
+        context("a newline", preceded(many0_count(char(' ')), char('\n'))),
+    ))(i)?;
+    let offset = words.pop().unwrap_or(&[]);
+    let trailer_offset = number(offset).map_err(|_| {
+        nom::Err::Error(RuleError {
+            input: offset,
+            expected: Some("a trailer offset"),
+        })
+    })?;
+    let header = Header {
+        version,
+        current_dir,
+        trailer_offset,
+        offset,
+        line: &i[..i.len() - rest.len()],
+    };
+    Ok((rest, header))
+}
+
+// <file mark> <file path>
+// A file mark without a path follows the records of the last file.
+pub fn file_line(i: &[u8]) -> Rule<'_, &[u8]> {
+    context("a file mark", preceded(tag("\t@"), line))(i)
+}
+
+pub fn record(i: &[u8]) -> Rule<'_, Record<'_>> {
+    if i.first() == Some(&b'\t') {
+        map(end_mark, Record::End)(i)
+    } else {
+        source_line(i)
+    }
+}
+
+fn end_mark(i: &[u8]) -> Rule<'_, RawSymbol<'_>> {
+    let (rest, (mark, name, _)) = tuple((mark, line, empty_line))(i)?;
+    let sym = RawSymbol {
+        at: i,
+        mark: Some(mark),
+        name,
+        text: &[],
+    };
+    Ok((rest, sym))
+}
+
+fn source_line(i: &[u8]) -> Rule<'_, Record<'_>> {
+    let line_number = context(
+        "a line number",
+        terminated(map_res(digit1, number::<u64>), char(' ')),
+    );
+    let (rest, (number, text, symbols, _)) =
+        tuple((line_number, line, many0(symbol), empty_line))(i)?;
+    Ok((
+        rest,
+        Record::Line {
+            number,
+            text,
+            symbols,
+        },
+    ))
+}
+
+// <optional mark> <symbol>
+// <non-symbol text>
+// Anything but the empty line ending the record starts a symbol.
+fn symbol(i: &[u8]) -> Rule<'_, RawSymbol<'_>> {
+    let (rest, (mark, name, text)) = preceded(
+        not(char('\n')),
+        cut(tuple((opt(mark), line, context("non-symbol text", line)))),
+    )(i)?;
+    let sym = RawSymbol {
+        at: i,
+        mark,
+        name,
+        text,
+    };
+    Ok((rest, sym))
+}
+
+// Past the empty line ending a malformed record, to the start of the next.
+pub fn skip_record(i: &[u8]) -> Rule<'_, ()> {
+    let text_line = terminated(take_till1(|b| b == b'\n'), char('\n'));
+    map(many_till(text_line, empty_line), |_| ())(i)
+}
+
+// <source directories> <include directories>, each a count followed by
+// that many lines, and what follows them: the source files.
+pub struct RawTrailer<'a> {
+    pub source_dirs: Vec<&'a [u8]>,
+    pub include_dirs: Vec<&'a [u8]>,
+    pub rest: &'a [u8],
+}
+
+pub fn trailer(i: &[u8]) -> Rule<'_, RawTrailer<'_>> {
+    let count = || {
+        context(
+            "a count",
+            terminated(map_res(digit1, number::<usize>), char('\n')),
+        )
+    };
+    let (i, (source_dirs, include_dirs, rest)) = tuple((
+        length_count(count(), line),
+        length_count(count(), line),
+        rest,
+    ))(i)?;
+    let trailer = RawTrailer {
+        source_dirs,
+        include_dirs,
+        rest,
+    };
+    Ok((i, trailer))
+}
@@ -0,0 +1,1130 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width, RenderOptions};
+use crate::search::NameMatch;
+use crate::table::{Cell, Table};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// A call from the body of a function, the call record being where the call
+// is made.
+#[derive(Clone, Copy, Debug)]
+pub struct CallEdge<'a> {
+    pub caller: &'a Symbol,
+    pub call: &'a Symbol,
+}
+
+impl<'a> CallEdge<'a> {
+    pub fn callee(&self) -> &'a str {
+        self.call.display_name()
+    }
+}
+
+// Each function definition with the calls made from its body.
+pub struct CallGraph<'a> {
+    functions: Vec<(&'a Symbol, Vec<&'a Symbol>)>,
+    // The names of every function defined in the database.
+    defined: HashSet<&'a str>,
+    // The calls of each function, and those made from the bodies of each.
+    callers: HashMap<&'a str, Vec<CallEdge<'a>>>,
+    callees: HashMap<&'a str, Vec<CallEdge<'a>>>,
+    options: RenderOptions,
+}
+
+type Index<'a> = HashMap<&'a str, Vec<CallEdge<'a>>>;
+
+// The calls of each function and those made from the body of each, in the
+// order they are in the database.
+fn index<'a>(functions: &[(&'a Symbol, Vec<&'a Symbol>)]) -> (Index<'a>, Index<'a>) {
+    let mut callers: Index = HashMap::new();
+    let mut callees: Index = HashMap::new();
+    for (caller, calls) in functions.iter() {
+        for call in calls.iter() {
+            let edge = CallEdge { caller, call };
+            callers.entry(edge.callee()).or_default().push(edge);
+            callees.entry(caller.display_name()).or_default().push(edge);
+        }
+    }
+    (callers, callees)
+}
+
+impl<'a> CallGraph<'a> {
+    pub fn new(cscope: &'a Cscope, options: RenderOptions) -> CallGraph<'a> {
+        let mut functions: Vec<(&Symbol, Vec<&Symbol>)> = vec![];
+        for (sym, function) in cscope.with_enclosing_functions() {
+            if sym.mark() == FileMark::FunctionDefinition {
+                functions.push((sym, vec![]));
+            } else if sym.mark() == FileMark::FunctionCall && function.is_some() {
+                // The enclosing function is always the one defined last.
+                if let Some((_, calls)) = functions.last_mut() {
+                    calls.push(sym);
+                }
+            }
+        }
+        let defined = functions.iter().map(|(f, _)| f.display_name()).collect();
+        let (callers, callees) = index(&functions);
+        CallGraph {
+            functions,
+            defined,
+            callers,
+            callees,
+            options,
+        }
+    }
+
+    // Only the definitions of the function `name`, for what it calls.
+    pub fn of_callers(mut self, name: &str, matcher: NameMatch) -> CallGraph<'a> {
+        self.functions
+            .retain(|(f, _)| matcher.is_match(f.display_name(), name));
+        (self.callers, self.callees) = index(&self.functions);
+        self
+    }
+
+    // Every call from a function body to another function, defined in the
+    // database or not.
+    pub fn edges(&self) -> impl Iterator<Item = CallEdge<'a>> + '_ {
+        self.functions
+            .iter()
+            .flat_map(|(caller, calls)| calls.iter().map(move |call| CallEdge { caller, call }))
+    }
+
+    // The calls of the function `name`.
+    pub fn callers_of(&self, name: &str) -> &[CallEdge<'a>] {
+        self.callers.get(name).map_or(&[], |edges| edges.as_slice())
+    }
+
+    // The calls made from the bodies of the functions named `name`.
+    pub fn callees_of(&self, name: &str) -> &[CallEdge<'a>] {
+        self.callees.get(name).map_or(&[], |edges| edges.as_slice())
+    }
+
+    // How many calls away from the functions `from` each function defined
+    // in the database is, `from` being `first` away, as far as `depth`.
+    fn distances(
+        &self,
+        from: Vec<&'a str>,
+        first: usize,
+        depth: Option<usize>,
+    ) -> HashMap<&'a str, usize> {
+        let mut distance: HashMap<&str, usize> = HashMap::new();
+        let mut queue: VecDeque<&str> = VecDeque::new();
+        for name in from.into_iter().filter(|n| self.defined.contains(n)) {
+            if distance.insert(name, first).is_none() {
+                queue.push_back(name);
+            }
+        }
+        while let Some(name) = queue.pop_front() {
+            let next = distance[name] + 1;
+            if depth.is_some_and(|depth| next > depth) {
+                continue;
+            }
+            for edge in self.callees_of(name) {
+                let callee = edge.callee();
+                if self.defined.contains(callee) && !distance.contains_key(callee) {
+                    distance.insert(callee, next);
+                    queue.push_back(callee);
+                }
+            }
+        }
+        distance
+    }
+
+    // The definitions of the functions reached by following one call or
+    // more from the function `name`, nearest first.  `name` itself is one
+    // of them only when it is recursive.
+    pub fn reachable_from(&self, name: &str) -> Vec<&'a Symbol> {
+        let callees = self.callees_of(name).iter().map(|e| e.callee()).collect();
+        let distance = self.distances(callees, 1, None);
+        let mut reached: Vec<&Symbol> = self
+            .functions
+            .iter()
+            .map(|(f, _)| *f)
+            .filter(|f| distance.contains_key(f.display_name()))
+            .collect();
+        reached.sort_by_key(|f| distance[f.display_name()]);
+        reached
+    }
+
+    // The strongly connected components of the calls between the functions
+    // defined in the database that hold a cycle: a function calling itself,
+    // or functions calling each other round, those of a component in the
+    // order they are defined.
+    pub fn cycles(&self) -> Vec<Vec<&'a Symbol>> {
+        // One node per name, defined where it is first.
+        let mut nodes: Vec<&Symbol> = vec![];
+        let mut node_of: HashMap<&str, usize> = HashMap::new();
+        for (f, _) in self.functions.iter() {
+            node_of.entry(f.display_name()).or_insert_with(|| {
+                nodes.push(f);
+                nodes.len() - 1
+            });
+        }
+        let mut succ: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+        for edge in self.edges() {
+            if let Some(callee) = node_of.get(edge.callee()) {
+                let caller = &mut succ[node_of[edge.caller.display_name()]];
+                if !caller.contains(callee) {
+                    caller.push(*callee);
+                }
+            }
+        }
+
+        // Tarjan's algorithm, with a stack of its own rather than recursion
+        // as deep as the longest chain of calls.
+        let mut index: Vec<Option<usize>> = vec![None; nodes.len()];
+        let mut low: Vec<usize> = vec![0; nodes.len()];
+        let mut on_stack: Vec<bool> = vec![false; nodes.len()];
+        let mut stack: Vec<usize> = vec![];
+        let mut next = 0;
+        let mut components: Vec<Vec<usize>> = vec![];
+        for start in 0..nodes.len() {
+            if index[start].is_some() {
+                continue;
+            }
+            // Each node being visited with how many of its callees it has.
+            let mut visiting: Vec<(usize, usize)> = vec![(start, 0)];
+            while let Some((node, done)) = visiting.pop() {
+                if done == 0 {
+                    index[node] = Some(next);
+                    low[node] = next;
+                    next += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                }
+                if let Some(&callee) = succ[node].get(done) {
+                    visiting.push((node, done + 1));
+                    match index[callee] {
+                        None => visiting.push((callee, 0)),
+                        Some(i) if on_stack[callee] => low[node] = low[node].min(i),
+                        Some(_) => (),
+                    }
+                    continue;
+                }
+                if Some(low[node]) == index[node] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+                if let Some((caller, _)) = visiting.last() {
+                    low[*caller] = low[*caller].min(low[node]);
+                }
+            }
+        }
+
+        let mut cycles: Vec<Vec<&Symbol>> = components
+            .into_iter()
+            .filter(|c| c.len() > 1 || succ[c[0]].contains(&c[0]))
+            .map(|mut c| {
+                c.sort_unstable();
+                c.into_iter().map(|n| nodes[n]).collect()
+            })
+            .collect();
+        cycles.sort_by_key(|c| node_of[c[0].display_name()]);
+        cycles
+    }
+
+    // Only the functions reached by following calls from `root`, at most
+    // `depth` calls away.
+    pub fn rooted_at(mut self, root: &str, depth: Option<usize>) -> CallGraph<'a> {
+        let root = self
+            .functions
+            .iter()
+            .map(|(f, _)| f.display_name())
+            .find(|f| *f == root);
+        let distance = self.distances(root.into_iter().collect(), 0, depth);
+        self.functions
+            .retain(|(f, _)| distance.contains_key(f.display_name()));
+        // The calls out of the functions at the limit go further than asked.
+        for (f, calls) in self.functions.iter_mut() {
+            if depth.is_some_and(|depth| distance[f.display_name()] == depth) {
+                calls.clear();
+            }
+        }
+        self
+    }
+
+    // A Graphviz digraph of the calls between the functions defined in the
+    // database, for `dot -Tsvg`.
+    pub fn dot(&self) -> String {
+        let mut nodes: Vec<&str> = vec![];
+        let mut edges: Vec<(&str, &str)> = vec![];
+        for (function, calls) in self.functions.iter() {
+            nodes.push(function.display_name());
+            for call in calls
+                .iter()
+                .filter(|c| self.defined.contains(c.display_name()))
+            {
+                nodes.push(call.display_name());
+                edges.push((function.display_name(), call.display_name()));
+            }
+        }
+        let mut seen: HashSet<&str> = HashSet::new();
+        nodes.retain(|n| seen.insert(n));
+        let mut seen: HashSet<(&str, &str)> = HashSet::new();
+        edges.retain(|e| seen.insert(*e));
+
+        let mut out = String::from("digraph calls {\n");
+        for node in nodes.iter() {
+            out.push_str(&format!("    {};\n", dot_id(node)));
+        }
+        for (from, to) in edges.iter() {
+            out.push_str(&format!("    {} -> {};\n", dot_id(from), dot_id(to)));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn is_defined(&self) -> bool {
+        !self.functions.is_empty()
+    }
+
+    pub fn has_calls(&self) -> bool {
+        self.functions.iter().any(|(_, calls)| !calls.is_empty())
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "calls",
+            &[
+                "caller",
+                "caller_demangled",
+                "callee",
+                "callee_demangled",
+                "file",
+                "line",
+            ],
+        );
+        for (function, calls) in self.functions.iter() {
+            for call in calls.iter() {
+                table.rows.push(vec![
+                    Cell::from(function.name()),
+                    Cell::from(function.demangled().unwrap_or("")),
+                    Cell::from(call.name()),
+                    Cell::from(call.demangled().unwrap_or("")),
+                    Cell::from(call.filename()),
+                    Cell::from(call.line_number()),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+// A quoted DOT identifier.
+pub(crate) fn dot_id(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Each calling function with the functions it calls beneath it.
+impl<'a> std::fmt::Display for CallGraph<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let max_len: usize = self
+            .functions
+            .iter()
+            .flat_map(|(_, calls)| calls.iter())
+            .map(|s| width(s.display_name()))
+            .max()
+            .unwrap_or(0);
+        for (function, calls) in self.functions.iter() {
+            if calls.is_empty() {
+                continue;
+            }
+            write!(
+                f,
+                "•{} ({}:{}):\n╰─╮\n",
+                function.display_name(),
+                self.options.file(function.filename()),
+                function.line_number()
+            )?;
+            for call in calls.iter() {
+                self.options.write_row(
+                    f,
+                    "  ├",
+                    &pad(call.display_name(), max_len),
+                    call.line_text(),
+                    call,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The recursive functions and the cycles of mutually recursive ones.
+pub struct Cycles<'a> {
+    cycles: Vec<Vec<&'a Symbol>>,
+    options: RenderOptions,
+}
+
+impl<'a> Cycles<'a> {
+    pub fn new(graph: &CallGraph<'a>) -> Cycles<'a> {
+        Cycles {
+            cycles: graph.cycles(),
+            options: graph.options.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cycles.is_empty()
+    }
+
+    // What a cycle is called: the recursive functions all go under one.
+    fn names(&self) -> Vec<String> {
+        let mut n = 0;
+        self.cycles
+            .iter()
+            .map(|c| match c.len() {
+                1 => "recursive".to_string(),
+                _ => {
+                    n += 1;
+                    format!("cycle {}", n)
+                }
+            })
+            .collect()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new("cycles", &["cycle", "function", "file", "line"]);
+        for (name, cycle) in self.names().into_iter().zip(self.cycles.iter()) {
+            for function in cycle.iter() {
+                table.rows.push(vec![
+                    Cell::from(name.as_str()),
+                    Cell::from(function.display_name()),
+                    Cell::from(function.filename()),
+                    Cell::from(function.line_number()),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for Cycles<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut write = |heading: &str, functions: &[&Symbol]| -> std::fmt::Result {
+            write!(f, "•{}:\n╰─╮\n", heading)?;
+            let max_len = functions
+                .iter()
+                .map(|s| width(s.display_name()))
+                .max()
+                .unwrap_or(0);
+            for function in functions.iter() {
+                writeln!(
+                    f,
+                    "  ├ {} {}, {}",
+                    pad(function.display_name(), max_len),
+                    self.options.file(function.filename()),
+                    self.options.location(function)
+                )?;
+            }
+            Ok(())
+        };
+        let recursive: Vec<&Symbol> = self
+            .cycles
+            .iter()
+            .filter(|c| c.len() == 1)
+            .map(|c| c[0])
+            .collect();
+        if !recursive.is_empty() {
+            write("recursive", &recursive)?;
+        }
+        let names = self.names();
+        for (name, cycle) in names.iter().zip(self.cycles.iter()) {
+            if cycle.len() > 1 {
+                write(&format!("{}, {} functions", name, cycle.len()), cycle)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// A function calling the one above it in the tree of callers, from the
+// call sites in `calls`.
+struct Caller<'a> {
+    depth: usize,
+    function: &'a Symbol,
+    calls: Vec<&'a Symbol>,
+    // The function is already further up this path, which goes round.
+    recursive: bool,
+    // The callers of the function are beneath an earlier row of it.
+    repeated: bool,
+}
+
+// The transitive callers of a function: those calling it, those calling
+// them and so on, up to the functions nothing calls or `depth` calls away.
+pub struct WhoCalls<'a> {
+    name: String,
+    callers: Vec<Caller<'a>>,
+    options: RenderOptions,
+}
+
+impl<'a> WhoCalls<'a> {
+    // Depth first, so each caller is followed by those calling it.  The
+    // callers of a function are only followed the first time it is met,
+    // for the paths up from shared callers grow exponentially.
+    pub fn new(graph: &CallGraph<'a>, name: &str, depth: Option<usize>) -> WhoCalls<'a> {
+        let mut callers = vec![];
+        let mut followed: HashSet<&str> = HashSet::new();
+        // The callers left to visit, the next last, with None where the
+        // callers of the last function on `path` end.
+        let mut stack: Vec<Option<Caller<'a>>> = vec![];
+        let mut path: Vec<&'a str> = vec![];
+        stack.extend(
+            callers_of(graph, name, name, &path)
+                .into_iter()
+                .rev()
+                .map(Some),
+        );
+        while let Some(entry) = stack.pop() {
+            let mut caller = match entry {
+                Some(caller) => caller,
+                None => {
+                    path.pop();
+                    continue;
+                }
+            };
+            let function = caller.function.display_name();
+            caller.repeated = !caller.recursive && followed.contains(function);
+            let follow = !caller.recursive
+                && !caller.repeated
+                && depth.is_none_or(|depth| caller.depth < depth);
+            callers.push(caller);
+            if follow {
+                followed.insert(function);
+                path.push(function);
+                stack.push(None);
+                let found = callers_of(graph, name, function, &path);
+                stack.extend(found.into_iter().rev().map(Some));
+            }
+        }
+        WhoCalls {
+            name: name.to_string(),
+            callers,
+            options: graph.options.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.callers.is_empty()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            &self.name,
+            &[
+                "depth",
+                "caller",
+                "callee",
+                "file",
+                "line",
+                "recursive",
+                "repeated",
+            ],
+        );
+        let mut callees: Vec<&str> = vec![&self.name];
+        for caller in self.callers.iter() {
+            callees.truncate(caller.depth);
+            let callee = callees.last().copied().unwrap_or("");
+            for call in caller.calls.iter() {
+                table.rows.push(vec![
+                    Cell::from(caller.depth as u64),
+                    Cell::from(caller.function.display_name()),
+                    Cell::from(callee),
+                    Cell::from(call.filename()),
+                    Cell::from(call.line_number()),
+                    Cell::from(if caller.recursive { "yes" } else { "no" }),
+                    Cell::from(if caller.repeated { "yes" } else { "no" }),
+                ]);
+            }
+            callees.push(caller.function.display_name());
+        }
+        table
+    }
+}
+
+// The functions calling `callee`, each with its calls of it.  `path` holds
+// the callers between `name` and `callee`.
+fn callers_of<'a>(
+    graph: &CallGraph<'a>,
+    name: &str,
+    callee: &str,
+    path: &[&'a str],
+) -> Vec<Caller<'a>> {
+    let mut found: Vec<Caller<'a>> = vec![];
+    for edge in graph.callers_of(callee) {
+        match found
+            .iter_mut()
+            .find(|c| std::ptr::eq(c.function, edge.caller))
+        {
+            Some(caller) => caller.calls.push(edge.call),
+            None => found.push(Caller {
+                depth: path.len() + 1,
+                function: edge.caller,
+                calls: vec![edge.call],
+                recursive: {
+                    let caller = edge.caller.display_name();
+                    caller == name || path.contains(&caller)
+                },
+                repeated: false,
+            }),
+        }
+    }
+    found
+}
+
+// Each caller beneath the function it calls, with the lines of its calls.
+impl<'a> std::fmt::Display for WhoCalls<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "•{}:\n╰─╮\n", self.name)?;
+        for caller in self.callers.iter() {
+            let lines: Vec<u64> = caller.calls.iter().map(|c| c.line_number()).collect();
+            writeln!(
+                f,
+                "  {}├ {} {}, {}{}",
+                "│ ".repeat(caller.depth - 1),
+                caller.function.display_name(),
+                self.options.file(caller.calls[0].filename()),
+                self.options.lines(&lines),
+                if caller.recursive {
+                    " (recursive)"
+                } else if caller.repeated {
+                    " (see above)"
+                } else {
+                    ""
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// How many steps the search for call paths takes at most, the number of
+// paths growing exponentially with the depth of the call graph.
+const MAX_PATH_STEPS: usize = 1_000_000;
+
+// The call paths from the entry points of the program down to a function,
+// the deepest first, for an estimate of how deep its stack can get.  Each
+// path is the calls along it from the entry point, `name` being called last.
+pub struct CallPaths<'a> {
+    name: String,
+    paths: Vec<Vec<CallEdge<'a>>>,
+    // The search stopped at MAX_PATH_STEPS, deeper paths may be missing.
+    truncated: bool,
+    options: RenderOptions,
+}
+
+impl<'a> CallPaths<'a> {
+    // The entry points are the functions in `entries`, or when there are
+    // none the functions nothing calls, such as main and interrupt handlers.
+    pub fn new(graph: &CallGraph<'a>, name: &str, entries: &[&str]) -> CallPaths<'a> {
+        let called: HashSet<&str> = graph.edges().map(|e| e.callee()).collect();
+        let is_entry = |f: &str| match entries {
+            [] => !called.contains(f),
+            _ => entries.contains(&f),
+        };
+        let mut search = PathSearch {
+            graph,
+            name,
+            is_entry: &is_entry,
+            path: vec![],
+            paths: vec![],
+            steps: 0,
+        };
+        search.up_from(name);
+        let truncated = search.steps >= MAX_PATH_STEPS;
+        let mut paths = search.paths;
+        paths.sort_by_key(|p| Reverse(p.len()));
+        CallPaths {
+            name: name.to_string(),
+            paths,
+            truncated,
+            options: graph.options.clone(),
+        }
+    }
+
+    // Only the `n` deepest paths.
+    pub fn top(mut self, n: usize) -> CallPaths<'a> {
+        self.paths.truncate(n);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            &self.name,
+            &["path", "depth", "caller", "callee", "file", "line"],
+        );
+        for (n, path) in self.paths.iter().enumerate() {
+            for (depth, edge) in path.iter().enumerate() {
+                table.rows.push(vec![
+                    Cell::from(n as u64 + 1),
+                    Cell::from(depth as u64 + 1),
+                    Cell::from(edge.caller.display_name()),
+                    Cell::from(edge.callee()),
+                    Cell::from(edge.call.filename()),
+                    Cell::from(edge.call.line_number()),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+// The call paths being found, up from the function called last.
+struct PathSearch<'g, 'a> {
+    graph: &'g CallGraph<'a>,
+    // The function called last.
+    name: &'g str,
+    is_entry: &'g dyn Fn(&str) -> bool,
+    // The calls from the function being looked at down to the one called
+    // last, innermost last.
+    path: Vec<CallEdge<'a>>,
+    paths: Vec<Vec<CallEdge<'a>>>,
+    steps: usize,
+}
+
+impl<'g, 'a> PathSearch<'g, 'a> {
+    fn up_from(&mut self, callee: &str) {
+        let mut seen: Vec<&Symbol> = vec![];
+        for edge in self.graph.callers_of(callee) {
+            // A caller calling more than once makes the same paths.
+            if seen.iter().any(|s| std::ptr::eq(*s, edge.caller)) {
+                continue;
+            }
+            seen.push(edge.caller);
+            let caller = edge.caller.display_name();
+            // Recursion, which graph --cycles lists, is left out of paths.
+            if caller == self.name || self.path.iter().any(|e| e.callee() == caller) {
+                continue;
+            }
+            self.steps += 1;
+            if self.steps >= MAX_PATH_STEPS {
+                return;
+            }
+            self.path.push(*edge);
+            if (self.is_entry)(caller) {
+                self.paths.push(self.path.iter().rev().copied().collect());
+            }
+            self.up_from(caller);
+            self.path.pop();
+        }
+    }
+}
+
+// Each path as the calls down it, from the entry point to the function.
+impl<'a> std::fmt::Display for CallPaths<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for path in self.paths.iter() {
+            write!(
+                f,
+                "•{} → {}, {} calls deep:\n╰─╮\n",
+                path[0].caller.display_name(),
+                self.name,
+                path.len()
+            )?;
+            let max_len = path
+                .iter()
+                .map(|e| width(e.caller.display_name()))
+                .max()
+                .unwrap_or(0);
+            for edge in path.iter() {
+                writeln!(
+                    f,
+                    "  ├ {} → {} {}, {}",
+                    pad(edge.caller.display_name(), max_len),
+                    edge.callee(),
+                    self.options.file(edge.call.filename()),
+                    self.options.location(edge.call)
+                )?;
+            }
+        }
+        if self.truncated {
+            writeln!(
+                f,
+                "Stopped after {} steps, deeper paths may be missing.",
+                MAX_PATH_STEPS
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// What the fan-in and fan-out of functions are ordered by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FanOrder {
+    FanIn,
+    FanOut,
+    Name,
+}
+
+// Each function defined in the database with its fan-in, the number of
+// distinct functions calling it, and its fan-out, the number of distinct
+// functions it calls, defined in the database or not.
+pub struct FanMetrics<'a> {
+    functions: Vec<(&'a Symbol, u64, u64)>,
+    options: RenderOptions,
+}
+
+impl<'a> FanMetrics<'a> {
+    pub fn new(graph: &CallGraph<'a>, order: FanOrder) -> FanMetrics<'a> {
+        let mut callers: HashMap<&str, HashSet<&str>> = HashMap::new();
+        let mut callees: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for edge in graph.edges() {
+            let caller = edge.caller.display_name();
+            callers.entry(edge.callee()).or_default().insert(caller);
+            callees.entry(caller).or_default().insert(edge.callee());
+        }
+        let count = |sets: &HashMap<&str, HashSet<&str>>, name: &str| {
+            sets.get(name).map_or(0, |s| s.len() as u64)
+        };
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut functions: Vec<(&Symbol, u64, u64)> = graph
+            .functions
+            .iter()
+            .map(|(f, _)| *f)
+            .filter(|f| seen.insert(f.display_name()))
+            .map(|f| {
+                let name = f.display_name();
+                (f, count(&callers, name), count(&callees, name))
+            })
+            .collect();
+        functions.sort_by_key(|(f, _, _)| f.display_name());
+        match order {
+            FanOrder::FanIn => functions.sort_by_key(|(_, fan_in, _)| Reverse(*fan_in)),
+            FanOrder::FanOut => functions.sort_by_key(|(_, _, fan_out)| Reverse(*fan_out)),
+            FanOrder::Name => (),
+        }
+        FanMetrics {
+            functions,
+            options: graph.options.clone(),
+        }
+    }
+
+    // Only the first `n` functions.
+    pub fn top(mut self, n: usize) -> FanMetrics<'a> {
+        self.functions.truncate(n);
+        self
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "metrics",
+            &["function", "fan-in", "fan-out", "file", "line"],
+        );
+        for (function, fan_in, fan_out) in self.functions.iter() {
+            table.rows.push(vec![
+                Cell::from(function.display_name()),
+                Cell::from(*fan_in),
+                Cell::from(*fan_out),
+                Cell::from(function.filename()),
+                Cell::from(function.line_number()),
+            ]);
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for FanMetrics<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let max_len = self
+            .functions
+            .iter()
+            .map(|(s, _, _)| width(s.display_name()))
+            .max()
+            .unwrap_or(0);
+        writeln!(f, "fan-in fan-out function")?;
+        for (function, fan_in, fan_out) in self.functions.iter() {
+            writeln!(
+                f,
+                "{:>6} {:>7} {} {}, {}",
+                fan_in,
+                fan_out,
+                pad(function.display_name(), max_len),
+                self.options.file(function.filename()),
+                self.options.location(function)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The call sites of a function, under the file they are in.
+pub struct Callers<'a> {
+    name: &'a str,
+    // Each call with the function whose body it is in.
+    calls: Vec<(&'a Symbol, Option<&'a Symbol>)>,
+    options: RenderOptions,
+}
+
+impl<'a> Callers<'a> {
+    pub fn new(
+        cscope: &'a Cscope,
+        name: &'a str,
+        matcher: NameMatch,
+        options: RenderOptions,
+    ) -> Callers<'a> {
+        let calls = cscope
+            .with_enclosing_functions()
+            .into_iter()
+            .filter(|(s, _)| {
+                s.mark() == FileMark::FunctionCall && matcher.is_match(s.display_name(), name)
+            })
+            .collect();
+        Callers {
+            name,
+            calls,
+            options,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            self.name,
+            &["file", "function", "demangled", "line", "text"],
+        );
+        for (call, function) in self.calls.iter() {
+            table.rows.push(vec![
+                Cell::from(call.filename()),
+                Cell::from(function.map_or("", |f| f.name())),
+                Cell::from(function.and_then(|f| f.demangled()).unwrap_or("")),
+                Cell::from(call.line_number()),
+                Cell::from(call.line_text()),
+            ]);
+        }
+        table
+    }
+}
+
+// Calls outside of any function body, e.g. in an initializer, show as "-".
+impl<'a> std::fmt::Display for Callers<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let caller = |function: &Option<&'a Symbol>| function.map_or("-", |f| f.display_name());
+        let max_len: usize = self
+            .calls
+            .iter()
+            .map(|(_, function)| width(caller(function)))
+            .max()
+            .unwrap_or(0);
+        let mut fname: &str = "";
+        for (call, function) in self.calls.iter() {
+            if call.filename() != fname {
+                fname = call.filename();
+                write!(f, "•{}:\n╰─╮\n", self.options.file(fname))?;
+            }
+            self.options.write_row(
+                f,
+                "  ├",
+                &pad(caller(function), max_len),
+                call.line_text(),
+                call,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::{calls, sample};
+
+    #[test]
+    fn lookups_follow_the_functions_kept() {
+        let db = calls(&[("main", &["a", "b"]), ("a", &["b"]), ("b", &[])]);
+        let cscope = parse_buffer(&db).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        let callers: Vec<&str> = graph
+            .callers_of("b")
+            .iter()
+            .map(|e| e.caller.name())
+            .collect();
+        assert_eq!(callers, vec!["main", "a"]);
+        assert_eq!(graph.callees_of("main").len(), 2);
+        assert!(graph.callers_of("main").is_empty());
+        let graph = graph.of_callers("a", NameMatch::default());
+        assert_eq!(graph.callers_of("b").len(), 1);
+        assert!(graph.callees_of("main").is_empty());
+    }
+
+    #[test]
+    fn whocalls_follows_a_shared_caller_once() {
+        // main calls a and b, both of which call leaf.
+        let db = calls(&[
+            ("main", &["a", "b"]),
+            ("a", &["leaf"]),
+            ("b", &["leaf"]),
+            ("leaf", &[]),
+        ]);
+        let cscope = parse_buffer(&db).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        let who = WhoCalls::new(&graph, "leaf", None).to_string();
+        assert_eq!(
+            who,
+            "•leaf:\n╰─╮\n  \
+             ├ a src/main.c, line:6\n  \
+             │ ├ main src/main.c, line:2\n  \
+             ├ b src/main.c, line:9\n  \
+             │ ├ main src/main.c, line:3 (see above)\n"
+        );
+    }
+
+    // `n` diamonds down from j0 to leaf: each join calls two functions, both
+    // calling the next join, so there are 2^n paths from j0.
+    fn diamonds(n: usize) -> Vec<u8> {
+        let names: Vec<(String, String, String)> = (0..n)
+            .map(|n| (format!("j{}", n), format!("a{}", n), format!("b{}", n)))
+            .collect();
+        let mut functions: Vec<(&str, Vec<&str>)> = vec![];
+        for (n, (join, a, b)) in names.iter().enumerate() {
+            functions.push((join, vec![a, b]));
+            let next = names.get(n + 1).map_or("leaf", |(j, _, _)| j.as_str());
+            functions.push((a, vec![next]));
+            functions.push((b, vec![next]));
+        }
+        let functions: Vec<(&str, &[&str])> =
+            functions.iter().map(|(f, c)| (*f, c.as_slice())).collect();
+        calls(&functions)
+    }
+
+    #[test]
+    fn whocalls_stays_linear_on_a_chain_of_diamonds() {
+        let cscope = parse_buffer(&diamonds(40)).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        let who = WhoCalls::new(&graph, "leaf", None);
+        // One row per call.
+        assert_eq!(who.table().rows.len(), 40 * 4);
+        assert_eq!(who.to_string().matches("(see above)").count(), 40);
+    }
+
+    #[test]
+    fn call_graph_has_the_call_sites() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        let callers = graph.callers_of("puts");
+        assert_eq!(callers.len(), 1);
+        assert_eq!(callers[0].caller.name(), "main");
+        assert_eq!(callers[0].call.line_number(), 4);
+        assert_eq!(graph.callees_of("main").len(), 1);
+        // puts is not defined in the database, so nothing is reached.
+        assert!(graph.reachable_from("main").is_empty());
+    }
+
+    #[test]
+    fn metrics_lines_have_no_trailing_blanks() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        let metrics = FanMetrics::new(&graph, FanOrder::FanIn).to_string();
+        assert!(
+            metrics.starts_with("fan-in fan-out function\n"),
+            "{}",
+            metrics
+        );
+        assert!(metrics.lines().all(|l| l == l.trim_end()), "{:?}", metrics);
+    }
+
+    #[test]
+    fn cycles_are_the_recursive_functions_and_the_rings_of_calls() {
+        let db = calls(&[
+            ("main", &["walk", "even"]),
+            ("walk", &["walk"]),
+            ("even", &["odd"]),
+            ("odd", &["even", "leaf"]),
+            ("leaf", &[]),
+        ]);
+        let cscope = parse_buffer(&db).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        let cycles = Cycles::new(&graph);
+        assert_eq!(
+            cycles.to_string(),
+            "•recursive:\n╰─╮\n  \
+             ├ walk src/main.c, line:5\n\
+             •cycle 1, 2 functions:\n╰─╮\n  \
+             ├ even src/main.c, line:8\n  \
+             ├ odd  src/main.c, line:11\n"
+        );
+        let rows: Vec<(String, String)> = cycles
+            .table()
+            .rows
+            .iter()
+            .map(|r| (r[0].to_string(), r[1].to_string()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("recursive".to_string(), "walk".to_string()),
+                ("cycle 1".to_string(), "even".to_string()),
+                ("cycle 1".to_string(), "odd".to_string()),
+            ]
+        );
+        let acyclic = calls(&[("main", &["leaf"]), ("leaf", &[])]);
+        let cscope = parse_buffer(&acyclic).unwrap();
+        assert!(Cycles::new(&CallGraph::new(&cscope, RenderOptions::default())).is_empty());
+    }
+
+    #[test]
+    fn callpaths_are_the_deepest_first_from_the_entry_points() {
+        // main reaches leaf directly and through a and b, isr through b.
+        let db = calls(&[
+            ("main", &["a", "leaf"]),
+            ("a", &["b"]),
+            ("b", &["leaf"]),
+            ("isr", &["b"]),
+            ("leaf", &[]),
+        ]);
+        let cscope = parse_buffer(&db).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        let route = |paths: &CallPaths| -> Vec<Vec<String>> {
+            paths
+                .paths
+                .iter()
+                .map(|p| p.iter().map(|e| e.caller.name().to_string()).collect())
+                .collect()
+        };
+        let paths = CallPaths::new(&graph, "leaf", &[]);
+        assert_eq!(
+            route(&paths),
+            vec![vec!["main", "a", "b"], vec!["isr", "b"], vec!["main"]]
+        );
+        assert_eq!(
+            paths.to_string().lines().next(),
+            Some("•main → leaf, 3 calls deep:")
+        );
+        assert_eq!(route(&paths.top(1)).len(), 1);
+        let from_main = CallPaths::new(&graph, "leaf", &["main"]);
+        assert_eq!(
+            route(&from_main),
+            vec![vec!["main", "a", "b"], vec!["main"]]
+        );
+        assert!(!from_main.to_string().contains("Stopped"));
+    }
+
+    #[test]
+    fn callpaths_stop_after_the_most_steps() {
+        let cscope = parse_buffer(&diamonds(24)).unwrap();
+        let graph = CallGraph::new(&cscope, RenderOptions::default());
+        // No entry point is on the way, so the search takes its steps
+        // without keeping a path for each.
+        let paths = CallPaths::new(&graph, "leaf", &["nowhere"]);
+        assert!(paths.truncated);
+        assert_eq!(
+            paths.to_string(),
+            "Stopped after 1000000 steps, deeper paths may be missing.\n"
+        );
+        let shallow = parse_buffer(&diamonds(4)).unwrap();
+        let graph = CallGraph::new(&shallow, RenderOptions::default());
+        assert!(!CallPaths::new(&graph, "leaf", &["nowhere"]).truncated);
+    }
+}
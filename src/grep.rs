@@ -0,0 +1,86 @@
+use crate::table::Table;
+
+// The columns naming the symbol of a row, the first a table has does, each
+// with the kind of its rows when the table has no kind column.  `None` takes
+// the name of the table as the kind, e.g. globals.
+const SYMBOL_COLUMNS: &[(&str, Option<&str>)] = &[
+    ("name", None),
+    ("member", None),
+    ("callee", Some("call")),
+    ("global", Some("write")),
+    ("macro", Some("use")),
+    ("header", Some("include")),
+    ("function", Some("function")),
+];
+
+// "<file>:<line>: <symbol> (<kind>)" for each row of the tables that has a
+// place in the sources, as grep -n prints matches, for Vim's quickfix list
+// (`:cfile`) and the tools reading grep output.  The rows of tables without
+// a symbol column, e.g. a text search, and those with it empty are
+// "<file>:<line>: <text>".
+pub fn lines(tables: &[Table]) -> String {
+    let mut out = String::new();
+    for table in tables.iter() {
+        let column = |name: &str| table.header.iter().position(|h| h == name);
+        let (file, line) = match (column("file"), column("line")) {
+            (Some(file), Some(line)) => (file, line),
+            _ => continue,
+        };
+        let symbol = SYMBOL_COLUMNS
+            .iter()
+            .find_map(|(name, kind)| column(name).map(|idx| (idx, *kind)));
+        let kind = column("kind");
+        let text = column("text");
+        for row in table.rows.iter() {
+            let what = match symbol {
+                // A call outside of any function has no caller.
+                Some((idx, fixed)) if !row[idx].to_string().is_empty() => {
+                    let kind = match (kind, fixed) {
+                        (Some(kind), _) => row[kind].to_string(),
+                        (None, Some(fixed)) => fixed.to_string(),
+                        (None, None) => table.name.clone(),
+                    };
+                    format!("{} ({})", row[idx], kind)
+                }
+                _ => match text {
+                    Some(text) => row[text].to_string(),
+                    None => continue,
+                },
+            };
+            out.push_str(&format!("{}:{}: {}\n", row[file], row[line], what));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::Cell;
+
+    #[test]
+    fn grep_lines_name_the_symbol_of_each_table() {
+        let mut callers = Table::new("puts", &["file", "function", "demangled", "line", "text"]);
+        for function in ["main", ""].iter() {
+            callers.rows.push(vec![
+                Cell::from("src/main.c"),
+                Cell::from(*function),
+                Cell::from(""),
+                Cell::from(4),
+                Cell::from("puts(\"hi\");"),
+            ]);
+        }
+        let mut text = Table::new("text", &["file", "line", "text"]);
+        text.rows.push(vec![
+            Cell::from("src/main.c"),
+            Cell::from(4),
+            Cell::from("hi"),
+        ]);
+        assert_eq!(
+            lines(&[callers, text]),
+            "src/main.c:4: main (function)\n\
+             src/main.c:4: puts(\"hi\");\n\
+             src/main.c:4: hi\n"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }\n\
+    table { border-collapse: collapse; }\n\
+    th, td { border: 1px solid #ccc; padding: 0.2em 0.6em; text-align: left; }\n\
+    td.code { font-family: monospace; white-space: pre; }\n\
+    tr:target { background: #ffd; }\n";
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// A name made of the characters safe in file names and fragment ids.
+fn slug(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+// The id of a record on the page of its file.
+fn anchor(sym: &Symbol) -> String {
+    if sym.mark() == FileMark::FunctionCall {
+        format!("c{}", sym.offset())
+    } else {
+        format!("L{}-{}", sym.line_number(), slug(sym.display_name()))
+    }
+}
+
+// The records of a file the report shows, and the page they are on.
+struct FilePage<'a> {
+    name: &'a str,
+    page: String,
+    definitions: Vec<&'a Symbol>,
+    // Each call with the function it is made from.
+    calls: Vec<(&'a Symbol, Option<&'a Symbol>)>,
+}
+
+// A static HTML report of the database: an index of the files, and a page
+// per file with its definitions and the calls made in it.  Calls link to the
+// definition of what they call, function definitions to where they are
+// called from.  Each page comes with its path within the report.
+pub fn report(cscope: &Cscope) -> Vec<(PathBuf, String)> {
+    let mut files: Vec<FilePage> = vec![];
+    let mut pages: HashSet<String> = HashSet::new();
+    for (sym, function) in cscope.with_enclosing_functions() {
+        if files.last().map(|f| f.name) != Some(sym.filename()) {
+            // Paths differing only in their punctuation get a number.
+            let mut page = format!("{}.html", slug(sym.filename()));
+            let mut n = 1;
+            while !pages.insert(page.clone()) {
+                n += 1;
+                page = format!("{}-{}.html", slug(sym.filename()), n);
+            }
+            files.push(FilePage {
+                name: sym.filename(),
+                page,
+                definitions: vec![],
+                calls: vec![],
+            });
+        }
+        let file = match files.last_mut() {
+            Some(file) => file,
+            None => continue,
+        };
+        if sym.mark().is_outer_definition() {
+            file.definitions.push(sym);
+        } else if sym.mark() == FileMark::FunctionCall {
+            file.calls.push((sym, function));
+        }
+    }
+
+    // Where each function and macro is defined, and called from.
+    let page_of: HashMap<&str, &str> = files.iter().map(|f| (f.name, f.page.as_str())).collect();
+    let mut defined: HashMap<&str, Vec<&Symbol>> = HashMap::new();
+    let mut callers: HashMap<&str, Vec<&Symbol>> = HashMap::new();
+    for file in files.iter() {
+        for sym in file.definitions.iter() {
+            if matches!(sym.mark(), FileMark::FunctionDefinition | FileMark::Define) {
+                defined.entry(sym.display_name()).or_default().push(sym);
+            }
+        }
+        for (call, _) in file.calls.iter() {
+            callers.entry(call.display_name()).or_default().push(call);
+        }
+    }
+    let link = |from: &str, to: &Symbol, text: &str| {
+        let href = if from == to.filename() {
+            String::new()
+        } else {
+            page_of[to.filename()].to_string()
+        };
+        format!(
+            "<a href=\"{}#{}\">{}</a>",
+            escape(&href),
+            anchor(to),
+            escape(text)
+        )
+    };
+
+    let mut out = vec![];
+    let mut index = String::from("<h1>Source files</h1>\n<table>\n");
+    index.push_str("<tr><th>file</th><th>definitions</th><th>calls</th></tr>\n");
+    for file in files.iter() {
+        index.push_str(&format!(
+            "<tr><td><a href=\"files/{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            escape(&file.page),
+            escape(file.name),
+            file.definitions.len(),
+            file.calls.len()
+        ));
+
+        let mut body = format!(
+            "<p><a href=\"../index.html\">All files</a></p>\n<h1>{}</h1>\n",
+            escape(file.name)
+        );
+        body.push_str("<h2>Definitions</h2>\n<table>\n");
+        body.push_str(
+            "<tr><th>line</th><th>kind</th><th>name</th><th>text</th><th>called from</th></tr>\n",
+        );
+        for sym in file.definitions.iter() {
+            let called_from: Vec<String> = match sym.mark() {
+                FileMark::FunctionDefinition | FileMark::Define => callers
+                    .get(sym.display_name())
+                    .into_iter()
+                    .flatten()
+                    .map(|c| {
+                        link(
+                            file.name,
+                            c,
+                            &format!("{}:{}", c.filename(), c.line_number()),
+                        )
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+            body.push_str(&format!(
+                "<tr id=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td class=\"code\">{}</td><td>{}</td></tr>\n",
+                anchor(sym),
+                sym.line_number(),
+                sym.mark().name(),
+                escape(sym.display_name()),
+                escape(sym.signature().unwrap_or(sym.line_text())),
+                called_from.join(", ")
+            ));
+        }
+        body.push_str("</table>\n<h2>Calls</h2>\n<table>\n");
+        body.push_str("<tr><th>line</th><th>in</th><th>calls</th><th>text</th></tr>\n");
+        for (call, function) in file.calls.iter() {
+            // The definition in the same file first, as for a static function.
+            let definitions = defined.get(call.display_name());
+            let target = definitions.and_then(|d| {
+                d.iter()
+                    .find(|d| d.filename() == file.name)
+                    .or_else(|| d.first())
+            });
+            let callee = match target {
+                Some(target) => link(file.name, target, call.display_name()),
+                None => escape(call.display_name()),
+            };
+            let caller = match function {
+                Some(function) => link(file.name, function, function.display_name()),
+                None => String::new(),
+            };
+            body.push_str(&format!(
+                "<tr id=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td class=\"code\">{}</td></tr>\n",
+                anchor(call),
+                call.line_number(),
+                caller,
+                callee,
+                escape(call.line_text())
+            ));
+        }
+        body.push_str("</table>\n");
+        out.push((
+            PathBuf::from("files").join(&file.page),
+            page(file.name, &body),
+        ));
+    }
+    index.push_str("</table>\n");
+    out.insert(
+        0,
+        (PathBuf::from("index.html"), page("Source files", &index)),
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    fn pages() -> Vec<(PathBuf, String)> {
+        let db = database(
+            "\t@src/a.c\n\n\
+             1 int \n\t$main\n(void) {\n\n\
+             2 \n\t`helper\n(a < b);\n\n\
+             3 }\n\n\t}\n\n\
+             \t@src/a_c\n\n\
+             1 void \n\t$helper\n(int x) {\n\n\
+             2 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        report(&parse_buffer(&db).unwrap())
+    }
+
+    #[test]
+    fn pages_are_the_index_and_one_per_file() {
+        let paths: Vec<PathBuf> = pages().into_iter().map(|(p, _)| p).collect();
+        // Both paths slug to src_a_c.
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("index.html"),
+                PathBuf::from("files/src_a_c.html"),
+                PathBuf::from("files/src_a_c-2.html"),
+            ]
+        );
+        let index = &pages()[0].1;
+        assert!(index.contains(
+            "<tr><td><a href=\"files/src_a_c.html\">src/a.c</a></td><td>1</td><td>1</td></tr>"
+        ));
+    }
+
+    #[test]
+    fn calls_and_definitions_link_to_each_other() {
+        let pages = pages();
+        let (caller, callee) = (&pages[1].1, &pages[2].1);
+        // The call links to the definition on the other page, and to the
+        // function it is in on its own.
+        assert!(
+            caller.contains("<a href=\"src_a_c-2.html#L1-helper\">helper</a>"),
+            "{}",
+            caller
+        );
+        assert!(
+            caller.contains("<a href=\"#L1-main\">main</a>"),
+            "{}",
+            caller
+        );
+        assert!(
+            caller.contains("<td class=\"code\">helper(a &lt; b);</td>"),
+            "{}",
+            caller
+        );
+        // The definition's callers link back to the row of the call.
+        let href = "<a href=\"src_a_c.html#";
+        let fragment = &callee[callee.find(href).unwrap() + href.len()..];
+        let fragment = &fragment[..fragment.find('"').unwrap()];
+        assert!(caller.contains(&format!("<tr id=\"{}\"><td>2</td>", fragment)));
+        assert!(callee.contains(">src/a.c:2</a>"), "{}", callee);
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            escape("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+        assert_eq!(slug("src/net/if.c"), "src_net_if_c");
+    }
+}
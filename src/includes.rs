@@ -0,0 +1,241 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::graph::dot_id;
+use crate::render::{pad, width, RenderOptions};
+use crate::table::{Cell, Table};
+use std::collections::{HashMap, HashSet};
+
+// An include directive: the file it is in, the header as written and the
+// database file that header is, when there is one.
+struct Include<'a> {
+    directive: &'a Symbol,
+    header: &'a str,
+    resolved: Option<&'a str>,
+}
+
+impl<'a> Include<'a> {
+    // The header with both delimiters, <stdio.h> or "util.h".
+    fn written(&self) -> String {
+        match self.directive.name().chars().next() {
+            Some('<') => format!("<{}>", self.header),
+            Some('"') => format!("\"{}\"", self.header),
+            _ => self.header.to_string(),
+        }
+    }
+
+    // The database file when the header is one, otherwise its name.
+    fn target(&self) -> &'a str {
+        self.resolved.unwrap_or(self.header)
+    }
+}
+
+// The headers each file includes, or with `reverse` the files including
+// each header.
+pub struct Includes<'a> {
+    includes: Vec<Include<'a>>,
+    reverse: bool,
+    options: RenderOptions,
+}
+
+impl<'a> Includes<'a> {
+    pub fn new(cscope: &'a Cscope, options: RenderOptions) -> Includes<'a> {
+        // The database files by their path and by each trailing part of it,
+        // "src/util.h" is also "util.h".  A name more than one file ends in
+        // is left unresolved.
+        let mut by_suffix: HashMap<&str, Option<&str>> = HashMap::new();
+        let files: HashSet<&str> = cscope.symbols().iter().map(|s| s.filename()).collect();
+        for file in files.iter() {
+            let mut suffix: &str = file;
+            loop {
+                by_suffix
+                    .entry(suffix)
+                    .and_modify(|f| {
+                        if *f != Some(*file) {
+                            *f = None
+                        }
+                    })
+                    .or_insert(Some(file));
+                match suffix.split_once('/') {
+                    Some((_, rest)) => suffix = rest,
+                    None => break,
+                }
+            }
+        }
+
+        let includes = cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.mark() == FileMark::IncludeDirective)
+            .map(|s| {
+                let header = s.name().trim_start_matches(&['<', '"'][..]);
+                let resolved = by_suffix
+                    .get(header.trim_start_matches("./"))
+                    .copied()
+                    .flatten();
+                Include {
+                    directive: s,
+                    header,
+                    resolved,
+                }
+            })
+            .collect();
+        Includes {
+            includes,
+            reverse: false,
+            options,
+        }
+    }
+
+    // Group by the header included rather than the file including it.
+    pub fn reverse(mut self) -> Includes<'a> {
+        self.includes.sort_by(|a, b| a.target().cmp(b.target()));
+        self.reverse = true;
+        self
+    }
+
+    // Only the includes of `file`, or of the header `file` when reversed,
+    // given by its path or the end of it.
+    pub fn only(mut self, file: &str) -> Includes<'a> {
+        let matches = |path: &str| path == file || path.ends_with(&format!("/{}", file));
+        let reverse = self.reverse;
+        self.includes.retain(|i| {
+            if reverse {
+                matches(i.target()) || i.header == file
+            } else {
+                matches(i.directive.filename())
+            }
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.includes.is_empty()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new("includes", &["file", "header", "resolved", "line"]);
+        for include in self.includes.iter() {
+            table.rows.push(vec![
+                Cell::from(include.directive.filename()),
+                Cell::from(include.header),
+                Cell::from(include.resolved.unwrap_or("")),
+                Cell::from(include.directive.line_number()),
+            ]);
+        }
+        table
+    }
+
+    // A Graphviz digraph with an edge from each file to the headers it
+    // includes, for `dot -Tsvg`.
+    pub fn dot(&self) -> String {
+        let mut seen: HashSet<(&str, &str)> = HashSet::new();
+        let mut out = String::from("digraph includes {\n");
+        for include in self.includes.iter() {
+            let edge = (include.directive.filename(), include.target());
+            if seen.insert(edge) {
+                out.push_str(&format!("    {} -> {};\n", dot_id(edge.0), dot_id(edge.1)));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl<'a> std::fmt::Display for Includes<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let column = |i: &Include| {
+            if self.reverse {
+                i.directive.filename().to_string()
+            } else {
+                i.written()
+            }
+        };
+        let max_len: usize = self
+            .includes
+            .iter()
+            .map(|i| width(&column(i)))
+            .max()
+            .unwrap_or(0);
+        let mut heading: &str = "";
+        for include in self.includes.iter() {
+            let group = if self.reverse {
+                include.target()
+            } else {
+                include.directive.filename()
+            };
+            if group != heading {
+                heading = group;
+                write!(f, "•{}:\n╰─╮\n", self.options.file(heading))?;
+            }
+            // The file a header is, when it is named differently.
+            let resolved = match include.resolved {
+                Some(path) if !self.reverse && path != include.header => path,
+                _ => "",
+            };
+            let name = pad(&column(include), max_len);
+            let location = self.options.location(include.directive);
+            if resolved.is_empty() {
+                writeln!(f, "  ├ {}, {}", name, location)?;
+            } else {
+                writeln!(f, "  ├ {} {}, {}", name, resolved, location)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    // src/main.c and src/util.c both include util.h, which is src/util.h of
+    // the database, main.c also includes <stdio.h>.
+    fn includes() -> Cscope {
+        parse_buffer(&database(
+            "\t@src/main.c\n\n\
+             1 #include \n\t~<stdio.h\n>\n\n\
+             2 #include \n\t~\"util.h\n\"\n\n\
+             \t@src/util.c\n\n\
+             1 #include \n\t~\"util.h\n\"\n\n\
+             \t@src/util.h\n\n\
+             1 struct \n\tspoint\n {\n\n\
+             \t@\n",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn includes_show_each_file_and_who_includes_each_header() {
+        let cscope = includes();
+        let options = RenderOptions::default;
+        assert_eq!(
+            Includes::new(&cscope, options()).to_string(),
+            "•src/main.c:\n╰─╮\n  \
+             ├ <stdio.h>, line:1\n  \
+             ├ \"util.h\"  src/util.h, line:2\n\
+             •src/util.c:\n╰─╮\n  \
+             ├ \"util.h\"  src/util.h, line:1\n"
+        );
+        // Headers that are not database files go by their name.
+        assert_eq!(
+            Includes::new(&cscope, options()).reverse().to_string(),
+            "•src/util.h:\n╰─╮\n  \
+             ├ src/main.c, line:2\n  \
+             ├ src/util.c, line:1\n\
+             •stdio.h:\n╰─╮\n  \
+             ├ src/main.c, line:1\n"
+        );
+        let util = Includes::new(&cscope, options()).reverse().only("util.h");
+        assert_eq!(util.table().rows.len(), 2);
+        assert!(Includes::new(&cscope, options()).only("util.h").is_empty());
+        assert_eq!(
+            Includes::new(&cscope, options()).dot(),
+            "digraph includes {\n    \
+             \"src/main.c\" -> \"stdio.h\";\n    \
+             \"src/main.c\" -> \"src/util.h\";\n    \
+             \"src/util.c\" -> \"src/util.h\";\n\
+             }\n"
+        );
+    }
+}
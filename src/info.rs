@@ -0,0 +1,179 @@
+use crate::cscope::{parse_header_and_trailer, Cscope, Trailer};
+use crate::error::CscopeError;
+use crate::table::{Cell, Table};
+
+// What the header and trailer of a database say, and whether the trailer is
+// where the header says it is.
+pub struct Info {
+    // The size of the database, once decompressed when kept gzip or zstd
+    // compressed.
+    size: u64,
+    cscope: Cscope,
+    trailer: Result<Trailer, CscopeError>,
+    // The records before the trailer end with the empty file mark.
+    records_end: bool,
+}
+
+impl Info {
+    pub fn new(db: &[u8]) -> Result<Info, CscopeError> {
+        let (cscope, trailer) = parse_header_and_trailer(db)?;
+        let records = db.get(..cscope.trailer_offset() as usize).unwrap_or(db);
+        Ok(Info {
+            size: db.len() as u64,
+            records_end: records.ends_with(b"\t@\n"),
+            cscope,
+            trailer,
+        })
+    }
+
+    // The options cscope was run with, those between the directory and the
+    // trailer offset in the header.
+    fn options(&self) -> String {
+        let words: Vec<&str> = self.cscope.header().split_whitespace().collect();
+        match words.len() {
+            n if n > 4 => words[3..n - 1].join(" "),
+            _ => String::new(),
+        }
+    }
+
+    // Whether the trailer offset holds up, and what is wrong with it when not.
+    fn trailer_check(&self) -> String {
+        match &self.trailer {
+            Err(CscopeError::TrailerPastEnd { .. }) => {
+                format!("past the end of the {} bytes", self.size)
+            }
+            Err(e) => format!("not at a trailer: {}", e),
+            Ok(_) if !self.records_end => "not right after the records".to_string(),
+            Ok(_) => "ok".to_string(),
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, String)> {
+        let yes_no = |b: bool| if b { "yes" } else { "no" }.to_string();
+        let options = self.options();
+        vec![
+            ("version", self.cscope.version().to_string()),
+            ("directory", self.cscope.current_dir().display().to_string()),
+            ("options", options.clone()),
+            ("compressed", yes_no(self.cscope.is_compressed())),
+            (
+                "inverted index",
+                match self.cscope.inverted_index_terms() {
+                    Some(terms) => format!("{} terms", terms),
+                    None => yes_no(false),
+                },
+            ),
+            (
+                "truncated names",
+                yes_no(options.split_whitespace().any(|o| o == "-T")),
+            ),
+            ("trailer offset", self.cscope.trailer_offset().to_string()),
+            ("size", self.size.to_string()),
+            ("trailer", self.trailer_check()),
+        ]
+    }
+
+    // The lists of the trailer, by name, when it could be read.
+    fn lists(&self) -> Vec<(&'static str, &[String])> {
+        match &self.trailer {
+            Ok(trailer) => vec![
+                ("source directories", &trailer.source_dirs),
+                ("include directories", &trailer.include_dirs),
+                ("source files", &trailer.source_files),
+            ],
+            Err(_) => vec![],
+        }
+    }
+
+    pub fn tables(&self) -> Vec<Table> {
+        let mut header = Table::new("header", &["field", "value"]);
+        for (name, value) in self.fields() {
+            header.rows.push(vec![Cell::from(name), Cell::from(value)]);
+        }
+        let mut trailer = Table::new("trailer", &["list", "name"]);
+        for (list, names) in self.lists() {
+            for name in names.iter() {
+                trailer
+                    .rows
+                    .push(vec![Cell::from(list), Cell::from(name.as_str())]);
+            }
+        }
+        vec![header, trailer]
+    }
+}
+
+impl std::fmt::Display for Info {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (name, value) in self.fields() {
+            writeln!(f, "{:<16} {}", name, value)?;
+        }
+        for (list, names) in self.lists() {
+            writeln!(f, "\n{}: {}", list, names.len())?;
+            for name in names.iter() {
+                writeln!(f, "  {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdb::{database, sample};
+
+    // The sample with its trailer offset moved by `by` bytes.
+    fn moved(by: i64) -> Vec<u8> {
+        let db = sample();
+        let header_end = db.iter().position(|&b| b == b'\n').unwrap();
+        let header = std::str::from_utf8(&db[..header_end]).unwrap();
+        let (start, offset) = header.rsplit_once(' ').unwrap();
+        let offset: i64 = offset.parse().unwrap();
+        let mut out = format!("{} {:010}", start, offset + by).into_bytes();
+        out.extend_from_slice(&db[header_end..]);
+        out
+    }
+
+    fn trailer_check(db: &[u8]) -> String {
+        let info = Info::new(db).unwrap();
+        let (_, value) = info.fields().pop().unwrap();
+        value
+    }
+
+    #[test]
+    fn info_prints_the_header_and_trailer() {
+        assert_eq!(
+            Info::new(&sample()).unwrap().to_string(),
+            "version          15\n\
+             directory        /nonexistent\n\
+             options          -c\n\
+             compressed       no\n\
+             inverted index   no\n\
+             truncated names  no\n\
+             trailer offset   128\n\
+             size             152\n\
+             trailer          ok\n\
+             \n\
+             source directories: 1\n  \
+             .\n\
+             \n\
+             include directories: 0\n\
+             \n\
+             source files: 1\n  \
+             src/main.c\n"
+        );
+    }
+
+    #[test]
+    fn trailer_offsets_are_checked_against_the_file() {
+        assert_eq!(trailer_check(&moved(1000)), "past the end of the 152 bytes");
+        assert!(trailer_check(&moved(1))
+            .starts_with("not at a trailer: Expected a count at offset 129"));
+        // A trailer that reads, but not after the end of the records.
+        let db = database("\t@src/main.c\n\n1 int \n\t$main\n(void) {\n\n");
+        assert_eq!(trailer_check(&db), "not right after the records");
+        // Nothing but a trailer to list when it cannot be read.
+        assert_eq!(Info::new(&moved(1)).unwrap().tables()[1].rows.len(), 0);
+        assert_eq!(Info::new(&sample()).unwrap().tables()[1].rows.len(), 2);
+    }
+}
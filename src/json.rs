@@ -0,0 +1,33 @@
+use crate::table::{Cell, Table};
+use serde_json::{Map, Value};
+
+fn rows(table: &Table) -> Value {
+    let rows = table.rows.iter().map(|row| {
+        let object: Map<String, Value> = table
+            .header
+            .iter()
+            .zip(row.iter())
+            .map(|(heading, cell)| {
+                let value = match cell {
+                    Cell::Text(text) => Value::from(text.as_str()),
+                    Cell::Number(n) => Value::from(*n),
+                };
+                (heading.clone(), value)
+            })
+            .collect();
+        Value::Object(object)
+    });
+    Value::Array(rows.collect())
+}
+
+// A table as an array of objects keyed by its header, several tables as an
+// object holding each under its name.
+pub fn tables(tables: &[Table]) -> String {
+    let value = match tables {
+        [table] => rows(table),
+        _ => Value::Object(tables.iter().map(|t| (t.name.clone(), rows(t))).collect()),
+    };
+    let mut text = serde_json::to_string_pretty(&value).unwrap_or_default();
+    text.push('\n');
+    text
+}
@@ -0,0 +1,340 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// Names a node of the Kythe graph.
+#[derive(Clone, Serialize)]
+struct VName {
+    corpus: String,
+    path: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    language: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    signature: String,
+}
+
+// One fact of a node, or an edge between two nodes, in the JSON form of the
+// Kythe entry stream.  Fact values are bytes, which JSON holds as base64.
+#[derive(Serialize)]
+struct Entry<'a> {
+    source: &'a VName,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edge_kind: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a VName>,
+    fact_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fact_value: Option<String>,
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+// The node kind, and subkind, of what a definition defines.
+fn node_kind(mark: FileMark) -> Option<(&'static str, &'static str)> {
+    match mark {
+        FileMark::FunctionDefinition => Some(("function", "")),
+        FileMark::Define => Some(("macro", "")),
+        FileMark::ClassDefinition => Some(("record", "class")),
+        FileMark::StructDefinition => Some(("record", "struct")),
+        FileMark::UnionDefinition => Some(("record", "union")),
+        FileMark::EnumDefinition => Some(("sum", "enum")),
+        FileMark::TypedefDefinition => Some(("talias", "")),
+        FileMark::OtherGlobalDefinition => Some(("variable", "")),
+        FileMark::EnumStructUnionMemberGlobalDefinition => Some(("variable", "field")),
+        FileMark::FunctionBlockLocalDefinition => Some(("variable", "local")),
+        FileMark::FunctionParameterDefinition => Some(("variable", "local/parameter")),
+        _ => None,
+    }
+}
+
+// Writes the entries out as JSON lines.
+struct Stream {
+    out: String,
+}
+
+impl Stream {
+    fn fact(&mut self, source: &VName, name: &str, value: &[u8]) {
+        self.push(&Entry {
+            source,
+            edge_kind: None,
+            target: None,
+            fact_name: name,
+            fact_value: Some(base64(value)),
+        });
+    }
+
+    fn edge(&mut self, source: &VName, kind: &str, target: &VName) {
+        self.push(&Entry {
+            source,
+            edge_kind: Some(kind),
+            target: Some(target),
+            fact_name: "/",
+            fact_value: None,
+        });
+    }
+
+    fn push(&mut self, entry: &Entry) {
+        if let Ok(line) = serde_json::to_string(entry) {
+            self.out.push_str(&line);
+            self.out.push('\n');
+        }
+    }
+}
+
+// Where the name of a record starts in its source line: past as many
+// occurrences of the name as the text before it in the database holds.
+fn name_column(sym: &Symbol, line: &str) -> Option<usize> {
+    let skip = sym.text_before().matches(sym.name()).count();
+    line.match_indices(sym.name()).nth(skip).map(|(idx, _)| idx)
+}
+
+// Kythe entries for the database: a node for each definition, and anchors
+// in the source files which define, reference or call them.  An anchor needs
+// the byte offset of the name in its file, so the files themselves are read
+// from where cscope was run; records of files which are not there only add
+// their definitions' nodes.
+pub fn entries(cscope: &Cscope, corpus: &str) -> String {
+    let vname = |path: &str, language: &str, signature: String| VName {
+        corpus: corpus.to_string(),
+        path: path.to_string(),
+        language: language.to_string(),
+        signature,
+    };
+
+    // Locals and parameters are only seen from their file, everything else
+    // is linked across files by name.
+    let node = |sym: &Symbol| -> VName {
+        match sym.mark() {
+            FileMark::FunctionBlockLocalDefinition | FileMark::FunctionParameterDefinition => {
+                vname(
+                    "",
+                    "c",
+                    format!("{}:{}:{}", sym.filename(), sym.line_number(), sym.name()),
+                )
+            }
+            _ => vname("", "c", format!("c:{}", sym.name())),
+        }
+    };
+
+    let mut stream = Stream { out: String::new() };
+    let enclosing: HashMap<*const Symbol, *const Symbol> = cscope
+        .with_enclosing_functions()
+        .into_iter()
+        .filter_map(|(sym, function)| Some((sym as *const Symbol, function? as *const Symbol)))
+        .collect();
+    let function_of = |sym: &Symbol| enclosing.get(&(sym as *const Symbol)).copied();
+    let mut locals: HashMap<(*const Symbol, &str), VName> = HashMap::new();
+    let mut defined: HashMap<&str, VName> = HashMap::new();
+    let mut nodes: HashSet<String> = HashSet::new();
+    for sym in cscope.symbols().iter() {
+        if let Some((kind, subkind)) = node_kind(sym.mark()) {
+            let target = node(sym);
+            if nodes.insert(target.signature.clone()) {
+                stream.fact(&target, "/kythe/node/kind", kind.as_bytes());
+                if !subkind.is_empty() {
+                    stream.fact(&target, "/kythe/subkind", subkind.as_bytes());
+                }
+            }
+            if !matches!(
+                sym.mark(),
+                FileMark::FunctionBlockLocalDefinition | FileMark::FunctionParameterDefinition
+            ) {
+                defined.entry(sym.name()).or_insert(target);
+            } else if let Some(function) = function_of(sym) {
+                locals.entry((function, sym.name())).or_insert(target);
+            }
+        }
+    }
+
+    let mut start = 0;
+    let symbols = cscope.symbols();
+    while start < symbols.len() {
+        let fname = symbols[start].filename();
+        let end = symbols[start..]
+            .iter()
+            .position(|s| s.filename() != fname)
+            .map_or(symbols.len(), |n| start + n);
+        let text = match std::fs::read(cscope.current_dir().join(Path::new(fname))) {
+            Ok(text) => text,
+            Err(_) => {
+                start = end;
+                continue;
+            }
+        };
+        let file = vname(fname, "", String::new());
+        stream.fact(&file, "/kythe/node/kind", b"file");
+        stream.fact(&file, "/kythe/text", &text);
+
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            text.iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .map(|(idx, _)| idx + 1),
+        );
+        for sym in symbols[start..end].iter().filter(|s| !s.is_end_mark()) {
+            let edge_kind = match sym.mark() {
+                m if node_kind(m).is_some() => "/kythe/edge/defines/binding",
+                FileMark::FunctionCall => "/kythe/edge/ref/call",
+                FileMark::Reference | FileMark::DirectAssingmentIncDec => "/kythe/edge/ref",
+                _ => continue,
+            };
+            let target = match node_kind(sym.mark()) {
+                Some(_) => node(sym),
+                // The locals of the enclosing function hide the globals.
+                None => {
+                    let local = function_of(sym).and_then(|f| locals.get(&(f, sym.name())));
+                    match local.or_else(|| defined.get(sym.name())) {
+                        Some(target) => target.clone(),
+                        None => continue,
+                    }
+                }
+            };
+            let line_start = match line_starts.get(sym.line_number().saturating_sub(1) as usize) {
+                Some(idx) => *idx,
+                None => continue,
+            };
+            let line_end = line_starts
+                .get(sym.line_number() as usize)
+                .map_or(text.len(), |idx| *idx);
+            let line = String::from_utf8_lossy(&text[line_start..line_end]);
+            let column = match name_column(sym, &line) {
+                Some(column) => column,
+                None => continue,
+            };
+            let (from, to) = (line_start + column, line_start + column + sym.name().len());
+            let anchor = vname(fname, "c", format!("@{}:{}", from, to));
+            stream.fact(&anchor, "/kythe/node/kind", b"anchor");
+            stream.fact(&anchor, "/kythe/loc/start", from.to_string().as_bytes());
+            stream.fact(&anchor, "/kythe/loc/end", to.to_string().as_bytes());
+            stream.edge(&anchor, "/kythe/edge/childof", &file);
+            stream.edge(&anchor, edge_kind, &target);
+        }
+        start = end;
+    }
+    stream.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database_in;
+    use serde_json::Value;
+
+    #[test]
+    fn base64_pads_the_last_group() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b""), "");
+    }
+
+    #[test]
+    fn anchors_bind_and_reference_the_nodes() {
+        let dir = std::env::temp_dir().join(format!("code-ls-kythe-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let source = "int count;\nint main(void) {\n  return count;\n}\n";
+        std::fs::write(dir.join("src/main.c"), source).unwrap();
+        let db = database_in(
+            &dir.to_string_lossy(),
+            "\t@src/main.c\n\n\
+             1 int \n\tgcount\n;\n\n\
+             2 int \n\t$main\n(void) {\n\n\
+             3 return \ncount\n;\n\n\
+             4 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let cscope = parse_buffer(&db).unwrap();
+        let out = entries(&cscope, "project");
+        std::fs::remove_dir_all(&dir).unwrap();
+        let entries: Vec<Value> = out
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let fact = |signature: &str, name: &str| -> Option<String> {
+            entries
+                .iter()
+                .find(|e| e["source"]["signature"] == signature && e["fact_name"] == name)
+                .map(|e| e["fact_value"].as_str().unwrap().to_string())
+        };
+        let edge = |signature: &str| -> Vec<(String, String)> {
+            entries
+                .iter()
+                .filter(|e| e["source"]["signature"] == signature && e["edge_kind"].is_string())
+                .map(|e| {
+                    // A file is named by its path, other nodes by signature.
+                    let target = match e["target"]["signature"].as_str() {
+                        Some(signature) => signature,
+                        None => e["target"]["path"].as_str().unwrap(),
+                    };
+                    (
+                        e["edge_kind"].as_str().unwrap().to_string(),
+                        target.to_string(),
+                    )
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            fact("c:count", "/kythe/node/kind"),
+            Some(base64(b"variable"))
+        );
+        assert_eq!(
+            fact("c:main", "/kythe/node/kind"),
+            Some(base64(b"function"))
+        );
+        assert!(entries.iter().all(|e| e["source"]["corpus"] == "project"));
+
+        // count is defined at bytes 4 to 9 and used at 37 to 42.
+        assert_eq!(
+            edge("@4:9"),
+            vec![
+                ("/kythe/edge/childof".to_string(), "src/main.c".to_string()),
+                (
+                    "/kythe/edge/defines/binding".to_string(),
+                    "c:count".to_string()
+                ),
+            ]
+        );
+        assert_eq!(
+            edge("@37:42"),
+            vec![
+                ("/kythe/edge/childof".to_string(), "src/main.c".to_string()),
+                ("/kythe/edge/ref".to_string(), "c:count".to_string()),
+            ]
+        );
+        assert_eq!(fact("@37:42", "/kythe/loc/start"), Some(base64(b"37")));
+    }
+
+    #[test]
+    fn records_of_missing_files_only_add_their_nodes() {
+        let db = database_in(
+            "/nonexistent",
+            "\t@src/main.c\n\n2 int \n\t$main\n(void) {\n\n4 }\n\n\t}\n\n\t@\n",
+        );
+        let out = entries(&parse_buffer(&db).unwrap(), "project");
+        assert_eq!(out.lines().count(), 1, "{}", out);
+        assert!(out.contains("\"signature\":\"c:main\""));
+    }
+}
@@ -0,0 +1,52 @@
+// The cscope database parser and the views of it, the code-ls command line
+// tool in main.rs is built on top of these.
+pub mod annotate;
+pub mod badge;
+pub mod collisions;
+pub mod config;
+pub mod cscope;
+pub mod delimited;
+pub mod diff;
+pub mod doxytag;
+pub mod encoding;
+pub mod error;
+pub mod filter;
+pub mod fixture;
+pub mod glob;
+mod grammar;
+pub mod graph;
+pub mod grep;
+pub mod html;
+pub mod includes;
+pub mod info;
+pub mod json;
+pub mod kythe;
+pub mod linemode;
+pub mod listing;
+pub mod macros;
+pub mod markup;
+pub mod outline;
+pub mod rename;
+pub mod render;
+pub mod report;
+pub mod sarif;
+pub mod search;
+pub mod selfcheck;
+pub mod sidecar;
+pub mod sort;
+pub mod sqlite;
+pub mod stats;
+pub mod table;
+pub mod tags;
+#[cfg(test)]
+mod testdb;
+pub mod warnings;
+pub mod writer;
+pub mod xlsx;
+
+pub use cscope::{
+    parse_buffer, parse_buffer_with, parse_database, parse_database_streaming, parse_database_with,
+    Cscope, FileMark, ParseOptions, Symbol,
+};
+pub use error::CscopeError;
+pub use graph::{CallEdge, CallGraph};
@@ -0,0 +1,283 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::RenderOptions;
+use crate::search::TextSearch;
+use regex::{Regex, RegexBuilder};
+use std::path::Path;
+
+// The queries of cscope's line-oriented interface, by the number of the
+// field (`cscope -L -1 NAME`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Symbol,
+    GlobalDefinition,
+    CalledBy,
+    Calling,
+    Text,
+    // cscope's "Change this text string", a text search outside its menus.
+    Change,
+    Egrep,
+    File,
+    Including,
+    Assignments,
+}
+
+impl Field {
+    pub fn from_number(n: u8) -> Option<Field> {
+        Some(match n {
+            0 => Field::Symbol,
+            1 => Field::GlobalDefinition,
+            2 => Field::CalledBy,
+            3 => Field::Calling,
+            4 => Field::Text,
+            5 => Field::Change,
+            6 => Field::Egrep,
+            7 => Field::File,
+            8 => Field::Including,
+            9 => Field::Assignments,
+            _ => return None,
+        })
+    }
+}
+
+// The enclosing function of a record outside any function.
+const GLOBAL: &str = "<global>";
+// The function of a text or file match, which cscope does not look up.
+const UNKNOWN: &str = "<unknown>";
+
+fn in_function(function: Option<&Symbol>) -> &str {
+    function.map_or(GLOBAL, |f| f.display_name())
+}
+
+// A line for a record, in the context of `function`.
+fn line<'a>(sym: &'a Symbol, function: &str) -> (&'a str, String, u64, String) {
+    (
+        sym.filename(),
+        function.to_string(),
+        sym.line_number(),
+        sym.line_text().to_string(),
+    )
+}
+
+// The lines cscope -L prints for a query, "<file> <function> <line> <text>"
+// each, so the editor plugins that run cscope can run code-ls instead.  As
+// with cscope the pattern is a regular expression matching the whole name,
+// or part of a line of text for the text and egrep queries.
+pub struct LineQuery<'a> {
+    lines: Vec<(&'a str, String, u64, String)>,
+}
+
+impl<'a> LineQuery<'a> {
+    // `dir` is where the text searches read the sources from, and `keep`
+    // says which of the files the trailer lists the file query may find.
+    pub fn new(
+        cscope: &'a Cscope,
+        field: Field,
+        pattern: &str,
+        ignore_case: bool,
+        dir: &Path,
+        keep: impl Fn(&str) -> bool,
+    ) -> Result<LineQuery<'a>, regex::Error> {
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(ignore_case)
+                .build()
+        };
+        let whole: Regex = build(&format!("^(?:{})$", pattern))?;
+        let mut lines = vec![];
+        let records = cscope.with_enclosing_functions();
+        match field {
+            Field::Symbol => {
+                for (sym, function) in records {
+                    if sym.is_end_mark()
+                        || sym.mark() == FileMark::File
+                        || !whole.is_match(sym.display_name())
+                    {
+                        continue;
+                    }
+                    // A definition is in the context of its own name.
+                    let context = match sym.mark() {
+                        FileMark::FunctionDefinition | FileMark::Define => sym.display_name(),
+                        _ => in_function(function),
+                    };
+                    lines.push(line(sym, context));
+                }
+            }
+            Field::GlobalDefinition => {
+                for (sym, _) in records {
+                    if sym.mark().is_outer_definition()
+                        && sym.mark() != FileMark::EnumStructUnionMemberGlobalDefinition
+                        && whole.is_match(sym.display_name())
+                    {
+                        lines.push(line(sym, sym.display_name()));
+                    }
+                }
+            }
+            Field::CalledBy => {
+                for (sym, function) in records {
+                    if sym.mark() == FileMark::FunctionCall
+                        && function.is_some_and(|f| whole.is_match(f.display_name()))
+                    {
+                        lines.push(line(sym, sym.display_name()));
+                    }
+                }
+            }
+            Field::Calling => {
+                for (sym, function) in records {
+                    if sym.mark() == FileMark::FunctionCall && whole.is_match(sym.display_name()) {
+                        lines.push(line(sym, in_function(function)));
+                    }
+                }
+            }
+            Field::Text | Field::Change | Field::Egrep => {
+                let pattern = match field {
+                    Field::Egrep => build(pattern)?,
+                    _ => build(&regex::escape(pattern))?,
+                };
+                let search =
+                    TextSearch::in_sources(cscope, &pattern, dir, RenderOptions::default());
+                for (file, n, text) in search.lines() {
+                    lines.push((file, UNKNOWN.to_string(), *n, text.clone()));
+                }
+            }
+            Field::File => {
+                // The trailer lists the files without records too.
+                let pattern = build(pattern)?;
+                for file in cscope.trailer().source_files.iter() {
+                    if keep(file) && pattern.is_match(file) {
+                        lines.push((file.as_str(), UNKNOWN.to_string(), 1, UNKNOWN.to_string()));
+                    }
+                }
+            }
+            Field::Including => {
+                for (sym, function) in records {
+                    let header = sym.name().trim_start_matches(&['<', '"'][..]);
+                    let base = header.rsplit('/').next().unwrap_or(header);
+                    if sym.mark() == FileMark::IncludeDirective
+                        && (whole.is_match(header) || whole.is_match(base))
+                    {
+                        lines.push(line(sym, in_function(function)));
+                    }
+                }
+            }
+            Field::Assignments => {
+                for (sym, function) in records {
+                    if sym.mark() == FileMark::DirectAssingmentIncDec
+                        && whole.is_match(sym.display_name())
+                    {
+                        lines.push(line(sym, in_function(function)));
+                    }
+                }
+            }
+        }
+        Ok(LineQuery { lines })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+impl<'a> std::fmt::Display for LineQuery<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (file, function, line, text) in self.lines.iter() {
+            writeln!(f, "{} {} {} {}", file, function, line, text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::{database, sample};
+
+    fn query(db: &[u8], field: u8, pattern: &str) -> String {
+        let cscope = parse_buffer(db).unwrap();
+        let field = Field::from_number(field).unwrap();
+        let query = LineQuery::new(&cscope, field, pattern, false, Path::new("."), |_| true);
+        query.unwrap().to_string()
+    }
+
+    // main calling draw, which draw.c defines.
+    fn program() -> Vec<u8> {
+        database(
+            "\t@src/main.c\n\n\
+             1 #include \n\t~\"draw.h\n\"\n\n\
+             2 int \n\tgcount\n;\n\n\
+             3 int \n\t$main\n(void) {\n\n\
+             4 \n\t`draw\n(\ncount\n);\n\n\
+             5 }\n\n\t}\n\n\
+             \t@src/draw.c\n\n\
+             1 void \n\t$draw\n(int \nn\n) { }\n\n\t}\n\n\
+             \t@\n",
+        )
+    }
+
+    #[test]
+    fn symbol_query_finds_definitions_and_uses() {
+        assert_eq!(
+            query(&sample(), 0, "main"),
+            "src/main.c main 3 int main(void) {\n"
+        );
+        assert_eq!(
+            query(&program(), 0, "count|draw"),
+            "src/main.c <global> 2 int count;\n\
+             src/main.c main 4 draw(count);\n\
+             src/main.c main 4 draw(count);\n\
+             src/draw.c draw 1 void draw(int n) { }\n"
+        );
+    }
+
+    #[test]
+    fn global_definition_query_leaves_out_the_uses() {
+        assert_eq!(
+            query(&sample(), 1, "point"),
+            "src/main.c point 1 struct point {\n"
+        );
+        assert_eq!(
+            query(&program(), 1, "draw|count"),
+            "src/main.c count 2 int count;\n\
+             src/draw.c draw 1 void draw(int n) { }\n"
+        );
+    }
+
+    #[test]
+    fn calling_query_names_the_caller() {
+        assert_eq!(
+            query(&sample(), 3, "puts"),
+            "src/main.c main 4 puts(\"hi\");\n"
+        );
+        assert_eq!(
+            query(&program(), 3, "draw"),
+            "src/main.c main 4 draw(count);\n"
+        );
+        assert_eq!(query(&program(), 3, "main"), "");
+    }
+
+    #[test]
+    fn file_query_lists_the_files_of_the_trailer() {
+        assert_eq!(
+            query(&sample(), 7, "main"),
+            "src/main.c <unknown> 1 <unknown>\n"
+        );
+        // A file without records is still one of the database's.
+        let mut db = database("\t@src/main.c\n\n3 int \n\t$main\n(void) {\n\n5 }\n\n\t}\n\n\t@\n");
+        let trailer = db.len() - b"1\n11\nsrc/main.c\n".len();
+        db.truncate(trailer);
+        db.extend_from_slice(b"2\n22\nsrc/main.c\nsrc/io.h\n");
+        assert_eq!(
+            query(&db, 7, "\\.[ch]$"),
+            "src/main.c <unknown> 1 <unknown>\n\
+             src/io.h <unknown> 1 <unknown>\n"
+        );
+        let cscope = parse_buffer(&db).unwrap();
+        let query = LineQuery::new(&cscope, Field::File, "", false, Path::new("."), |f| {
+            f != "src/io.h"
+        });
+        assert_eq!(
+            query.unwrap().to_string(),
+            "src/main.c <unknown> 1 <unknown>\n"
+        );
+    }
+}
@@ -0,0 +1,652 @@
+use crate::cscope::{Cscope, FileMark, Symbol, Trailer};
+use crate::render::{self, pad, width, RenderOptions};
+use crate::search::NameMatch;
+use crate::sort::{sort_within_files, WithinFile};
+use crate::table::{Cell, Table};
+use std::collections::HashMap;
+
+// Renders records in the tree style, grouped under the file they are in.
+pub struct Listing<'a> {
+    symbols: Vec<&'a Symbol>,
+    options: RenderOptions,
+}
+
+impl<'a> Listing<'a> {
+    pub fn new(symbols: Vec<&'a Symbol>, options: RenderOptions) -> Listing<'a> {
+        Listing { symbols, options }
+    }
+
+    pub fn table(&self, name: &str) -> Table {
+        let mut table = Table::new(name, &["file", "name", "demangled", "text", "line"]);
+        for sym in self.symbols.iter() {
+            table.rows.push(vec![
+                Cell::from(sym.filename()),
+                Cell::from(sym.name()),
+                Cell::from(sym.demangled().unwrap_or("")),
+                Cell::from(sym.line_text()),
+                Cell::from(sym.line_number()),
+            ]);
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for Listing<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut fname: &str = "";
+        let max_len: usize = self
+            .symbols
+            .iter()
+            .map(|s| width(s.display_name()))
+            .max()
+            .unwrap_or(0);
+        for sym in self.symbols.iter() {
+            if sym.filename() != fname {
+                fname = sym.filename();
+                write!(f, "•{}:\n╰─╮\n", self.options.file(fname))?;
+            }
+            self.options.write_row(
+                f,
+                "  ├",
+                &pad(sym.display_name(), max_len),
+                sym.line_text(),
+                sym,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// A file's records, grouped by their kind.
+type KindGroups<'a> = Vec<(FileMark, Vec<&'a Symbol>)>;
+
+// The records of some kinds, under their file and then their kind.
+pub struct KindListing<'a> {
+    // Each file with the records of each kind, in the order asked for.
+    files: Vec<(&'a str, KindGroups<'a>)>,
+    options: RenderOptions,
+}
+
+impl<'a> KindListing<'a> {
+    pub fn new(cscope: &'a Cscope, kinds: &[FileMark], options: RenderOptions) -> KindListing<'a> {
+        let mut files: Vec<(&str, KindGroups)> = vec![];
+        for sym in cscope.symbols().iter() {
+            if !kinds.contains(&sym.mark()) {
+                continue;
+            }
+            if files.last().map(|f| f.0) != Some(sym.filename()) {
+                let groups = kinds.iter().map(|k| (*k, vec![])).collect();
+                files.push((sym.filename(), groups));
+            }
+            if let Some((_, groups)) = files.last_mut() {
+                if let Some(group) = groups.iter_mut().find(|g| g.0 == sym.mark()) {
+                    group.1.push(sym);
+                }
+            }
+        }
+        for (_, groups) in files.iter_mut() {
+            groups.retain(|g| !g.1.is_empty());
+        }
+        KindListing { files, options }
+    }
+
+    // List the records of each kind in `order` rather than as recorded.
+    pub fn sorted(mut self, order: WithinFile, reverse: bool) -> KindListing<'a> {
+        for (_, groups) in self.files.iter_mut() {
+            for (_, syms) in groups.iter_mut() {
+                sort_within_files(syms, |s| s, order, reverse);
+            }
+        }
+        self
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "records",
+            &["file", "kind", "name", "demangled", "text", "line"],
+        );
+        for (fname, groups) in self.files.iter() {
+            for (kind, syms) in groups.iter() {
+                for sym in syms.iter() {
+                    table.rows.push(vec![
+                        Cell::from(*fname),
+                        Cell::from(kind.name()),
+                        Cell::from(sym.name()),
+                        Cell::from(sym.demangled().unwrap_or("")),
+                        Cell::from(sym.line_text()),
+                        Cell::from(sym.line_number()),
+                    ]);
+                }
+            }
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for KindListing<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let max_len: usize = self
+            .files
+            .iter()
+            .flat_map(|(_, groups)| groups.iter().flat_map(|g| g.1.iter()))
+            .map(|s| width(s.display_name()))
+            .max()
+            .unwrap_or(0);
+        for (fname, groups) in self.files.iter() {
+            write!(f, "•{}:\n╰─╮\n", self.options.file(fname))?;
+            for (kind, syms) in groups.iter() {
+                writeln!(f, "  ├ {}:", kind.name())?;
+                for (i, sym) in syms.iter().enumerate() {
+                    let prefix = if i + 1 == syms.len() {
+                        "  │ ╰"
+                    } else {
+                        "  │ ├"
+                    };
+                    self.options.write_row(
+                        f,
+                        prefix,
+                        &pad(sym.display_name(), max_len),
+                        sym.line_text(),
+                        sym,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Where a name is defined and where it is referenced.
+pub struct Usage<'a> {
+    name: &'a str,
+    definitions: Vec<&'a Symbol>,
+    uses: Vec<&'a Symbol>,
+    options: RenderOptions,
+}
+
+impl<'a> Usage<'a> {
+    // Split the records named `name` into its definitions, those for which
+    // `is_definition` holds, and its uses.
+    pub fn new(
+        name: &'a str,
+        matcher: NameMatch,
+        symbols: &'a [Symbol],
+        is_definition: impl Fn(&Symbol) -> bool,
+        options: RenderOptions,
+    ) -> Usage<'a> {
+        let (definitions, uses) = symbols
+            .iter()
+            .filter(|s| matcher.is_match(s.display_name(), name) && !s.is_end_mark())
+            .partition(|s| is_definition(s));
+        Usage {
+            name,
+            definitions,
+            uses,
+            options,
+        }
+    }
+
+    pub fn is_defined(&self) -> bool {
+        !self.definitions.is_empty()
+    }
+
+    pub fn is_unused(&self) -> bool {
+        self.uses.is_empty()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(self.name, &["use", "file", "line", "text"]);
+        for (heading, syms) in [("defined", &self.definitions), ("used", &self.uses)].iter() {
+            for sym in syms.iter() {
+                table.rows.push(vec![
+                    Cell::from(*heading),
+                    Cell::from(sym.filename()),
+                    Cell::from(sym.line_number()),
+                    Cell::from(sym.line_text()),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for Usage<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "•{}:", self.name)?;
+        for (heading, syms) in [("defined", &self.definitions), ("used", &self.uses)].iter() {
+            if syms.is_empty() {
+                continue;
+            }
+            writeln!(f, "╰─╮ {}", heading)?;
+            for sym in syms.iter() {
+                writeln!(
+                    f,
+                    "  ├ {}:{}: {}{}",
+                    sym.filename(),
+                    sym.line_number(),
+                    sym.line_text(),
+                    self.options.offset(sym)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The writes of each global by name, with the function each is made in.
+type Writes<'a> = HashMap<&'a str, Vec<(&'a Symbol, Option<&'a Symbol>)>>;
+
+// The global variables of each file, with where each is written to when
+// asked for, to track down shared mutable state.
+pub struct Globals<'a> {
+    globals: Vec<&'a Symbol>,
+    // The assignments, increments and decrements of each global.
+    writes: Option<Writes<'a>>,
+    options: RenderOptions,
+}
+
+impl<'a> Globals<'a> {
+    pub fn new(cscope: &'a Cscope, options: RenderOptions) -> Globals<'a> {
+        let globals = cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.mark() == FileMark::OtherGlobalDefinition)
+            .collect();
+        Globals {
+            globals,
+            writes: None,
+            options,
+        }
+    }
+
+    // Along with the places each global is written to.  An assignment
+    // within a function with a local or parameter of the same name is to
+    // that instead.
+    pub fn with_writes(mut self, cscope: &'a Cscope) -> Globals<'a> {
+        let mut writes: Writes = self
+            .globals
+            .iter()
+            .map(|g| (g.display_name(), vec![]))
+            .collect();
+        let mut shadowing: HashMap<*const Symbol, Vec<&str>> = HashMap::new();
+        for (sym, function) in cscope.with_enclosing_functions() {
+            let key = function.map_or(std::ptr::null(), |f| f as *const Symbol);
+            match sym.mark() {
+                FileMark::FunctionBlockLocalDefinition | FileMark::FunctionParameterDefinition => {
+                    shadowing.entry(key).or_default().push(sym.display_name())
+                }
+                FileMark::DirectAssingmentIncDec => {
+                    let shadowed = shadowing
+                        .get(&key)
+                        .is_some_and(|names| names.contains(&sym.display_name()));
+                    if let Some(sites) = writes.get_mut(sym.display_name()).filter(|_| !shadowed) {
+                        sites.push((sym, function));
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.writes = Some(writes);
+        self
+    }
+
+    fn writes_of(&self, global: &Symbol) -> &[(&'a Symbol, Option<&'a Symbol>)] {
+        self.writes
+            .as_ref()
+            .and_then(|writes| writes.get(global.display_name()))
+            .map_or(&[], |sites| sites.as_slice())
+    }
+
+    pub fn tables(&self) -> Vec<Table> {
+        let mut globals = Table::new("globals", &["file", "name", "text", "line"]);
+        for sym in self.globals.iter() {
+            globals.rows.push(vec![
+                Cell::from(sym.filename()),
+                Cell::from(sym.display_name()),
+                Cell::from(sym.line_text()),
+                Cell::from(sym.line_number()),
+            ]);
+        }
+        let mut tables = vec![globals];
+        if self.writes.is_some() {
+            let mut writes = Table::new("writes", &["global", "file", "line", "function", "text"]);
+            for global in self.globals.iter() {
+                for (sym, function) in self.writes_of(global).iter() {
+                    writes.rows.push(vec![
+                        Cell::from(global.display_name()),
+                        Cell::from(sym.filename()),
+                        Cell::from(sym.line_number()),
+                        Cell::from(function.map_or("", |f| f.display_name())),
+                        Cell::from(sym.line_text()),
+                    ]);
+                }
+            }
+            tables.push(writes);
+        }
+        tables
+    }
+}
+
+impl<'a> std::fmt::Display for Globals<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut fname: &str = "";
+        let max_len: usize = self
+            .globals
+            .iter()
+            .map(|s| width(s.display_name()))
+            .max()
+            .unwrap_or(0);
+        for sym in self.globals.iter() {
+            if sym.filename() != fname {
+                fname = sym.filename();
+                write!(f, "•{}:\n╰─╮\n", self.options.file(fname))?;
+            }
+            self.options.write_row(
+                f,
+                "  ├",
+                &pad(sym.display_name(), max_len),
+                sym.line_text(),
+                sym,
+            )?;
+            if self.writes.is_some() {
+                let writes = self.writes_of(sym);
+                writeln!(f, "  │ ╰ writes: {}", writes.len())?;
+                for (site, function) in writes.iter() {
+                    writeln!(
+                        f,
+                        "  │   ├ {}:{}: {} ({}){}",
+                        self.options.file(site.filename()),
+                        site.line_number(),
+                        site.line_text(),
+                        function.map_or("-", |f| f.display_name()),
+                        self.options.offset(site)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Where a name is defined, one "<file>:<line>: <kind> <text>" line each for
+// scripts to pick apart.
+pub struct Definitions<'a> {
+    definitions: Vec<&'a Symbol>,
+}
+
+impl<'a> Definitions<'a> {
+    pub fn new(cscope: &'a Cscope, name: &str, matcher: NameMatch) -> Definitions<'a> {
+        let definitions = cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.mark().is_outer_definition() && matcher.is_match(s.display_name(), name))
+            .collect();
+        Definitions { definitions }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.definitions.is_empty()
+    }
+
+    pub fn table(&self, name: &str) -> Table {
+        let mut table = Table::new(name, &["file", "line", "kind", "name", "demangled", "text"]);
+        for sym in self.definitions.iter() {
+            table.rows.push(vec![
+                Cell::from(sym.filename()),
+                Cell::from(sym.line_number()),
+                Cell::from(sym.mark().name()),
+                Cell::from(sym.name()),
+                Cell::from(sym.demangled().unwrap_or("")),
+                Cell::from(sym.line_text()),
+            ]);
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for Definitions<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for sym in self.definitions.iter() {
+            writeln!(
+                f,
+                "{}:{}: {} {}",
+                sym.filename(),
+                sym.line_number(),
+                sym.mark().name(),
+                sym.line_text()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// One row per symbol with every field the parser captured, in aligned
+// columns: kind, file, line, extent, offset, enclosing function, name and
+// the signature (or the source line when there is none).  End marks carry
+// no symbol, the extent of the definition they close stands for them.
+pub struct LongListing<'a> {
+    records: Vec<(&'a Symbol, Option<&'a Symbol>)>,
+}
+
+impl<'a> LongListing<'a> {
+    pub fn new(cscope: &'a Cscope) -> LongListing<'a> {
+        LongListing::sorted(cscope, None, WithinFile::Database, false)
+    }
+
+    // The records of each file in `order` rather than as recorded, only
+    // those of `kinds` when given.
+    pub fn sorted(
+        cscope: &'a Cscope,
+        kinds: Option<&[FileMark]>,
+        order: WithinFile,
+        reverse: bool,
+    ) -> LongListing<'a> {
+        let mut records = cscope.with_enclosing_functions();
+        records
+            .retain(|(sym, _)| !sym.is_end_mark() && kinds.is_none_or(|k| k.contains(&sym.mark())));
+        sort_within_files(&mut records, |r| r.0, order, reverse);
+        LongListing { records }
+    }
+
+    // The same records with the recorded and the demangled name apart and
+    // the text around the name.
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "symbols",
+            &[
+                "kind",
+                "file",
+                "line",
+                "extent",
+                "offset",
+                "function",
+                "name",
+                "demangled",
+                "signature",
+                "text_before",
+                "text_after",
+                "line_text",
+            ],
+        );
+        for (sym, function) in self.records.iter() {
+            table.rows.push(vec![
+                Cell::from(sym.mark().name()),
+                Cell::from(sym.filename()),
+                Cell::from(sym.line_number()),
+                Cell::from(
+                    sym.extent()
+                        .map_or(String::new(), |(start, end)| format!("{}-{}", start, end)),
+                ),
+                Cell::from(sym.offset()),
+                Cell::from(function.map_or("", |f| f.display_name())),
+                Cell::from(sym.name()),
+                Cell::from(sym.demangled().unwrap_or("")),
+                Cell::from(sym.signature().unwrap_or(sym.line_text())),
+                Cell::from(sym.text_before()),
+                Cell::from(sym.text_after()),
+                Cell::from(sym.line_text()),
+            ]);
+        }
+        table
+    }
+}
+
+// The table of the records of `kinds`, or of every record, as listed.
+pub fn symbols_table(cscope: &Cscope, kinds: Option<&[FileMark]>) -> Table {
+    LongListing::sorted(cscope, kinds, WithinFile::Database, false).table()
+}
+
+impl<'a> std::fmt::Display for LongListing<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let header = [
+            "KIND",
+            "FILE",
+            "LINE",
+            "EXTENT",
+            "OFFSET",
+            "FUNCTION",
+            "NAME",
+            "SIGNATURE",
+        ];
+        let mut rows = vec![header.map(|h| h.to_string())];
+        for (sym, function) in self.records.iter() {
+            rows.push([
+                sym.mark().name().to_string(),
+                sym.filename().to_string(),
+                sym.line_number().to_string(),
+                sym.extent()
+                    .map_or("-".to_string(), |(start, end)| format!("{}-{}", start, end)),
+                sym.offset().to_string(),
+                function.map_or("-", |f| f.display_name()).to_string(),
+                sym.display_name().to_string(),
+                sym.signature().unwrap_or(sym.line_text()).to_string(),
+            ]);
+        }
+        let mut widths = [0; 8];
+        for row in rows.iter() {
+            for (width, field) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(render::width(field));
+            }
+        }
+        for row in rows.iter() {
+            let last = row.len() - 1;
+            for (idx, field) in row.iter().enumerate() {
+                if idx == last {
+                    writeln!(f, "{}", field)?;
+                } else if idx == 2 || idx == 4 {
+                    // Numeric columns are right aligned.
+                    write!(f, "{:>width$} ", field, width = widths[idx])?;
+                } else {
+                    write!(f, "{} ", render::pad(field, widths[idx]))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The directories and source files listed in the trailer of the database,
+// each file with the number of records it has.
+pub struct SourceFiles<'a> {
+    trailer: &'a Trailer,
+    files: Vec<(&'a str, usize)>,
+}
+
+impl<'a> SourceFiles<'a> {
+    pub fn new(cscope: &'a Cscope, keep: impl Fn(&str) -> bool) -> SourceFiles<'a> {
+        let mut records: HashMap<&str, usize> = HashMap::new();
+        for sym in cscope.symbols().iter() {
+            *records.entry(sym.filename()).or_insert(0) += 1;
+        }
+        let files = cscope
+            .trailer()
+            .source_files
+            .iter()
+            .filter(|f| keep(f))
+            .map(|f| (f.as_str(), records.get(f.as_str()).copied().unwrap_or(0)))
+            .collect();
+        SourceFiles {
+            trailer: cscope.trailer(),
+            files,
+        }
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new("files", &["file", "records"]);
+        for (file, records) in self.files.iter() {
+            table
+                .rows
+                .push(vec![Cell::from(*file), Cell::from(*records as u64)]);
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for SourceFiles<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "•files: ({} source files)", self.files.len())?;
+        for (heading, dirs) in [
+            ("source directories", &self.trailer.source_dirs),
+            ("include directories", &self.trailer.include_dirs),
+        ]
+        .iter()
+        {
+            if dirs.is_empty() {
+                continue;
+            }
+            writeln!(f, "╰─╮ {}", heading)?;
+            for dir in dirs.iter() {
+                writeln!(f, "  ├ {}", dir)?;
+            }
+        }
+        if self.files.is_empty() {
+            return Ok(());
+        }
+        let max_len: usize = self.files.iter().map(|(f, _)| width(f)).max().unwrap_or(0);
+        writeln!(f, "╰─╮ source files")?;
+        for (file, records) in self.files.iter() {
+            writeln!(f, "  ├ {} records:{}", pad(file, max_len), records)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::sample;
+
+    #[test]
+    fn long_listing_keeps_only_the_given_kinds() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        let kinds = [FileMark::FunctionCall];
+        let listing =
+            LongListing::sorted(&cscope, Some(&kinds), WithinFile::Database, false).to_string();
+        assert_eq!(listing.lines().count(), 2, "{}", listing);
+        assert!(listing.contains("puts"), "{}", listing);
+        assert_eq!(symbols_table(&cscope, Some(&kinds)).rows.len(), 1);
+    }
+
+    #[test]
+    fn long_listing_has_the_records_of_its_table() {
+        let cscope = parse_buffer(&sample()).unwrap();
+        let listing = LongListing::new(&cscope);
+        let text: Vec<String> = listing
+            .to_string()
+            .lines()
+            .skip(1)
+            .map(|l| l.split_whitespace().next().unwrap().to_string())
+            .collect();
+        let table: Vec<String> = listing
+            .table()
+            .rows
+            .iter()
+            .map(|r| r[0].to_string())
+            .collect();
+        // The function end mark is in neither.
+        assert_eq!(text, vec!["struct", "function", "call"]);
+        assert_eq!(table, text);
+        assert_eq!(symbols_table(&cscope, None).rows.len(), text.len());
+    }
+}
@@ -0,0 +1,567 @@
+use code_ls::cscope::{Cscope, FileMark, Symbol};
+use code_ls::search::fuzzy_quality;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+// At most this many symbols answer a workspace/symbol request.
+const MAX_SYMBOLS: usize = 1000;
+
+// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+// The LSP SymbolKind of a definition.
+fn symbol_kind(mark: FileMark) -> u8 {
+    match mark {
+        FileMark::FunctionDefinition => 12,
+        FileMark::Define => 14,
+        FileMark::ClassDefinition | FileMark::TypedefDefinition => 5,
+        FileMark::EnumDefinition => 10,
+        FileMark::StructDefinition | FileMark::UnionDefinition => 23,
+        FileMark::EnumStructUnionMemberGlobalDefinition => 8,
+        _ => 13,
+    }
+}
+
+// The position of a byte offset within a line, in UTF-16 code units as the
+// protocol counts them.
+fn utf16_column(line: &str, byte: usize) -> usize {
+    line[..char_boundary(line, byte)]
+        .chars()
+        .map(char::len_utf16)
+        .sum()
+}
+
+fn byte_column(line: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= utf16 {
+            return idx;
+        }
+        units += c.len_utf16();
+    }
+    line.len()
+}
+
+fn is_identifier(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// The start of the character a byte offset is in, for offsets computed from
+// another copy of the line.
+fn char_boundary(line: &str, byte: usize) -> usize {
+    let mut byte = byte.min(line.len());
+    while !line.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    byte
+}
+
+// The identifier at a byte offset of a line.
+fn word_at(line: &str, byte: usize) -> Option<&str> {
+    let start = line[..char_boundary(line, byte)]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !is_identifier(*c))
+        .map_or(0, |(idx, c)| idx + c.len_utf8());
+    let end = line[start..]
+        .find(|c: char| !is_identifier(c))
+        .map_or(line.len(), |idx| start + idx);
+    Some(&line[start..end]).filter(|w| !w.is_empty())
+}
+
+fn to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+fn from_uri(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut idx = 0;
+    while idx < path.len() {
+        let hex = path
+            .get(idx + 1..idx + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (path[idx], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                bytes.push(b);
+                idx += 3;
+            }
+            (b, _) => {
+                bytes.push(b);
+                idx += 1;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+// A minimal language server over the database, answering the symbols of a
+// document or of the whole workspace, and the definitions of and references
+// to the identifier under the cursor.  The text of open documents is kept as
+// the editor sends it, other files are read from `dir`.
+struct Server<'a> {
+    cscope: &'a Cscope,
+    dir: PathBuf,
+    // The lines of each file by its database name.
+    open: HashMap<String, Vec<String>>,
+    read: HashMap<String, Vec<String>>,
+}
+
+impl<'a> Server<'a> {
+    fn path(&self, fname: &str) -> PathBuf {
+        self.dir.join(fname)
+    }
+
+    // The name the database has for the file at `uri`.
+    fn fname(&self, uri: &str) -> Option<String> {
+        let path = from_uri(uri)?;
+        Some(match path.strip_prefix(&self.dir) {
+            Ok(rel) => rel.to_string_lossy().into_owned(),
+            Err(_) => path.to_string_lossy().into_owned(),
+        })
+    }
+
+    fn lines(&mut self, fname: &str) -> &[String] {
+        if let Some(lines) = self.open.get(fname) {
+            return lines;
+        }
+        let path = self.path(fname);
+        self.read.entry(fname.to_string()).or_insert_with(|| {
+            let bytes = std::fs::read(path).unwrap_or_default();
+            String::from_utf8_lossy(&bytes)
+                .lines()
+                .map(String::from)
+                .collect()
+        })
+    }
+
+    // Where a record's name is in its source line.  cscope squeezes the
+    // blanks of the line it records, so this finds the same occurrence of
+    // the name in the line as it is.
+    fn location(&mut self, sym: &Symbol) -> Value {
+        let recorded = sym.raw_line_text();
+        let nth = recorded[..char_boundary(recorded, sym.column())]
+            .matches(sym.name())
+            .count();
+        let line = sym.line_number().saturating_sub(1) as usize;
+        let text = self
+            .lines(sym.filename())
+            .get(line)
+            .cloned()
+            .unwrap_or_default();
+        let start = text
+            .match_indices(sym.name())
+            .nth(nth)
+            .map_or(0, |(idx, _)| idx);
+        let end = if start < text.len() {
+            start + sym.name().len()
+        } else {
+            start
+        };
+        json!({
+            "uri": to_uri(&self.path(sym.filename())),
+            "range": {
+                "start": {"line": line, "character": utf16_column(&text, start)},
+                "end": {"line": line, "character": utf16_column(&text, end)},
+            }
+        })
+    }
+
+    fn symbol_information(&mut self, sym: &Symbol) -> Value {
+        json!({
+            "name": sym.display_name(),
+            "kind": symbol_kind(sym.mark()),
+            "location": self.location(sym),
+        })
+    }
+
+    // The identifier at the position of a textDocument request, and the
+    // database name of the document.
+    fn word(&mut self, params: &Value) -> Option<(String, String)> {
+        let fname = self.fname(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        let text = self.lines(&fname).get(line)?;
+        let word = word_at(text, byte_column(text, character))?.to_string();
+        Some((word, fname))
+    }
+
+    fn document_symbol(&mut self, params: &Value) -> Value {
+        let fname = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.fname(uri));
+        let cscope = self.cscope;
+        let symbols: Vec<Value> = cscope
+            .symbols()
+            .iter()
+            .filter(|s| Some(s.filename()) == fname.as_deref() && s.mark().is_outer_definition())
+            .map(|s| self.symbol_information(s))
+            .collect();
+        Value::from(symbols)
+    }
+
+    fn workspace_symbol(&mut self, params: &Value) -> Value {
+        let query = params["query"].as_str().unwrap_or("");
+        let mut matches: Vec<(i64, &Symbol)> = self
+            .cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.mark().is_outer_definition())
+            // An empty query asks for every symbol.
+            .filter_map(|s| match query {
+                "" => Some((0, s)),
+                _ => fuzzy_quality(s.display_name(), query).map(|q| (q, s)),
+            })
+            .collect();
+        matches.sort_by_key(|(quality, _)| std::cmp::Reverse(*quality));
+        let symbols: Vec<Value> = matches
+            .into_iter()
+            .take(MAX_SYMBOLS)
+            .map(|(_, s)| self.symbol_information(s))
+            .collect();
+        Value::from(symbols)
+    }
+
+    // The outer definitions of the identifier, those in the same file first
+    // as for a static function.
+    fn definition(&mut self, params: &Value) -> Value {
+        let (word, fname) = match self.word(params) {
+            Some(found) => found,
+            None => return Value::Null,
+        };
+        let mut definitions: Vec<&Symbol> = self
+            .cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.mark().is_outer_definition() && s.display_name() == word)
+            .collect();
+        definitions.sort_by_key(|s| s.filename() != fname);
+        let locations: Vec<Value> = definitions.into_iter().map(|s| self.location(s)).collect();
+        Value::from(locations)
+    }
+
+    fn references(&mut self, params: &Value) -> Value {
+        let (word, _) = match self.word(params) {
+            Some(found) => found,
+            None => return Value::Null,
+        };
+        let declarations = params["context"]["includeDeclaration"]
+            .as_bool()
+            .unwrap_or(true);
+        let cscope = self.cscope;
+        let locations: Vec<Value> = cscope
+            .symbols()
+            .iter()
+            .filter(|s| !s.is_end_mark() && s.mark() != FileMark::File)
+            .filter(|s| s.display_name() == word)
+            .filter(|s| declarations || !s.mark().is_definition())
+            .map(|s| self.location(s))
+            .collect();
+        Value::from(locations)
+    }
+
+    // The whole text of a document, as opened or changed.
+    fn set_text(&mut self, params: &Value, text: Option<&str>) {
+        let fname = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.fname(uri));
+        if let (Some(fname), Some(text)) = (fname, text) {
+            let lines = text.lines().map(String::from).collect();
+            self.open.insert(fname, lines);
+        }
+    }
+
+    // The result of a request, None for the methods not served.
+    fn handle(&mut self, method: &str, params: &Value) -> Option<Value> {
+        Some(match method {
+            "initialize" => json!({
+                "capabilities": {
+                    // The whole document on each change.
+                    "textDocumentSync": 1,
+                    "documentSymbolProvider": true,
+                    "workspaceSymbolProvider": true,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                },
+                "serverInfo": {"name": "code-ls", "version": env!("CARGO_PKG_VERSION")},
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/documentSymbol" => self.document_symbol(params),
+            "workspace/symbol" => self.workspace_symbol(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            _ => return None,
+        })
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) {
+        match method {
+            "textDocument/didOpen" => {
+                self.set_text(params, params["textDocument"]["text"].as_str());
+            }
+            "textDocument/didChange" => {
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str());
+                self.set_text(params, text);
+            }
+            "textDocument/didClose" => {
+                if let Some(fname) = params["textDocument"]["uri"]
+                    .as_str()
+                    .and_then(|uri| self.fname(uri))
+                {
+                    self.open.remove(&fname);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// The body of the next message, None at the end of the input.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Vec<u8>>, Error> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let length = length
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "A message without Content-Length."))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(out: &mut impl Write, message: &Value) -> Result<(), Error> {
+    let body = message.to_string();
+    write!(out, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    out.flush()
+}
+
+// Serve the Language Server Protocol on stdin and stdout until the client
+// sends exit, `dir` being where the sources are.
+pub fn run(cscope: &Cscope, dir: &Path) -> Result<(), Error> {
+    let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    serve(
+        cscope,
+        dir,
+        &mut std::io::stdin().lock(),
+        &mut std::io::stdout().lock(),
+    )
+}
+
+fn serve(
+    cscope: &Cscope,
+    dir: PathBuf,
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let mut server = Server {
+        cscope,
+        dir,
+        open: HashMap::new(),
+        read: HashMap::new(),
+    };
+    while let Some(body) = read_message(input)? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = json!({"code": PARSE_ERROR, "message": e.to_string()});
+                write_message(out, &json!({"jsonrpc": "2.0", "id": null, "error": error}))?;
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        if method == "exit" {
+            break;
+        }
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => {
+                server.notify(method, params);
+                continue;
+            }
+        };
+        let reply = match server.handle(method, params) {
+            Some(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            None => {
+                let message = format!("{} is not served.", method);
+                let error = json!({"code": METHOD_NOT_FOUND, "message": message});
+                json!({"jsonrpc": "2.0", "id": id, "error": error})
+            }
+        };
+        write_message(out, &reply)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // src/main.c of the open document below: helper, and main calling it
+    // after a comment holding a multibyte character.
+    fn database() -> Vec<u8> {
+        let body = "\t@src/main.c\n\n\
+                    1 int \n\t$helper\n(void) {\n\n\
+                    2 }\n\n\t}\n\n\
+                    3 int \n\t$main\n(void) {\n\n\
+                    4  /*\u{2192}*/\n\t`helper\n();\n\n\
+                    5 }\n\n\t}\n\n\
+                    \t@\n";
+        let header_len = "cscope 15 /nonexistent -c 0000000000\n".len();
+        let header = format!(
+            "cscope 15 /nonexistent -c {:010}\n",
+            header_len + body.len()
+        );
+        let mut db = (header + body).into_bytes();
+        db.extend_from_slice(b"1\n.\n0\n0\n1\n11\nsrc/main.c\n");
+        db
+    }
+
+    const TEXT: &str = "int helper(void) {\n}\nint main(void) {\n    /*\u{2192}*/helper();\n}\n";
+    const URI: &str = "file:///nonexistent/src/main.c";
+
+    fn frame(message: &Value) -> Vec<u8> {
+        let mut out = vec![];
+        write_message(&mut out, message).unwrap();
+        out
+    }
+
+    // The replies of the server to `messages`, after opening src/main.c.
+    fn exchange(messages: &[Value]) -> Vec<Value> {
+        let cscope = code_ls::parse_buffer(&database()).unwrap();
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": URI, "text": TEXT}},
+        });
+        let mut input = frame(&open);
+        for message in messages.iter() {
+            input.extend(frame(message));
+        }
+        input.extend(frame(&json!({"jsonrpc": "2.0", "method": "exit"})));
+        let mut out = vec![];
+        serve(
+            &cscope,
+            PathBuf::from("/nonexistent"),
+            &mut Cursor::new(input),
+            &mut out,
+        )
+        .unwrap();
+        let mut out = Cursor::new(out);
+        let mut replies = vec![];
+        while let Some(body) = read_message(&mut out).unwrap() {
+            replies.push(serde_json::from_slice(&body).unwrap());
+        }
+        replies
+    }
+
+    fn at(id: u64, method: &str, line: u64, character: u64) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": {
+                "textDocument": {"uri": URI},
+                "position": {"line": line, "character": character},
+                "context": {"includeDeclaration": false},
+            },
+        })
+    }
+
+    fn range(line: u64, start: u64, end: u64) -> Value {
+        json!({
+            "start": {"line": line, "character": start},
+            "end": {"line": line, "character": end},
+        })
+    }
+
+    #[test]
+    fn frames_messages_by_content_length() {
+        let mut input = Cursor::new(
+            b"Content-Length: 2\r\n\r\n{}content-length: 4\r\nContent-Type: x\r\n\r\nnull".to_vec(),
+        );
+        assert_eq!(read_message(&mut input).unwrap(), Some(b"{}".to_vec()));
+        assert_eq!(read_message(&mut input).unwrap(), Some(b"null".to_vec()));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+        let mut input = Cursor::new(b"Content-Type: x\r\n\r\n{}".to_vec());
+        assert!(read_message(&mut input).is_err());
+        let framed = frame(&json!({"a": "\u{e9}"}));
+        assert!(
+            framed.starts_with(b"Content-Length: 10\r\n\r\n"),
+            "{:?}",
+            framed
+        );
+    }
+
+    #[test]
+    fn initialize_announces_the_capabilities() {
+        let replies = exchange(&[json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"})]);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0]["id"], 1);
+        let capabilities = &replies[0]["result"]["capabilities"];
+        assert_eq!(capabilities["definitionProvider"], true);
+        assert_eq!(capabilities["referencesProvider"], true);
+        let replies = exchange(&[json!({"jsonrpc": "2.0", "id": 2, "method": "hover"})]);
+        assert_eq!(replies[0]["error"]["code"], METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn definition_and_references_of_the_word_after_a_multibyte_character() {
+        // The cursor on the 'e' of helper in "    /*→*/helper();".
+        let replies = exchange(&[
+            at(1, "textDocument/definition", 3, 10),
+            at(2, "textDocument/references", 3, 10),
+            // Right after the arrow, which is no identifier.
+            at(3, "textDocument/definition", 3, 7),
+        ]);
+        let definitions = replies[0]["result"].as_array().unwrap();
+        assert_eq!(definitions.len(), 1);
+        assert_eq!(definitions[0]["uri"], URI);
+        assert_eq!(definitions[0]["range"], range(0, 4, 10));
+        let references = replies[1]["result"].as_array().unwrap();
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0]["range"], range(3, 9, 15));
+        assert_eq!(replies[2]["result"], Value::Null);
+    }
+
+    #[test]
+    fn words_are_found_around_multibyte_characters() {
+        let line = "a\u{a0}\u{e9}t\u{e9}\u{2192}b_1";
+        assert_eq!(word_at(line, 0), Some("a"));
+        // Inside the no-break space, and after it.
+        assert_eq!(word_at(line, 2), Some("a"));
+        assert_eq!(word_at(line, 3), Some("\u{e9}t\u{e9}"));
+        assert_eq!(word_at(line, 11), Some("b_1"));
+        assert_eq!(word_at(line, 100), Some("b_1"));
+        assert_eq!(word_at("\u{2192}", 1), None);
+        assert_eq!(utf16_column(line, 2), 1);
+    }
+}
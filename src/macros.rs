@@ -0,0 +1,194 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width, RenderOptions};
+use crate::table::{Cell, Table};
+use std::collections::HashMap;
+
+// A #define along with what could be recovered of its body.
+pub struct Macro<'a> {
+    pub definition: &'a Symbol,
+    // The parameter list of a function-like macro, e.g. "(a, b)".
+    pub parameters: Option<String>,
+    pub replacement: String,
+}
+
+impl<'a> Macro<'a> {
+    pub fn is_function_like(&self) -> bool {
+        self.parameters.is_some()
+    }
+}
+
+// Split "<name>(<params>) <replacement>" following the #define keyword.  A
+// macro is function-like when the parenthesis directly follows the name.
+fn split_definition(text: &str, name: &str) -> (Option<String>, String) {
+    let rest = match text.find("define") {
+        Some(idx) => text[idx + "define".len()..].trim_start(),
+        None => text,
+    };
+    let rest = rest.strip_prefix(name).unwrap_or(rest);
+    if rest.starts_with('(') {
+        if let Some(end) = rest.find(')') {
+            return (Some(rest[..=end].to_string()), rest[end + 1..].to_string());
+        }
+    }
+    (None, rest.to_string())
+}
+
+// Every #define in the database, in database order.
+pub fn macros(cscope: &Cscope) -> Vec<Macro<'_>> {
+    let symbols = cscope.symbols();
+    let mut macros: Vec<Macro> = vec![];
+    for (idx, sym) in symbols.iter().enumerate() {
+        if sym.mark() != FileMark::Define {
+            continue;
+        }
+        let (parameters, mut replacement) = split_definition(sym.line_text(), sym.display_name());
+
+        // <continuation lines> ... <#define end mark>
+        let mut line = sym.line_number();
+        for next in symbols[idx + 1..].iter() {
+            if next.filename() != sym.filename() || next.mark() == FileMark::DefineEnd {
+                break;
+            }
+            if next.line_number() != line {
+                line = next.line_number();
+                replacement = format!(
+                    "{} {}",
+                    replacement.trim_end_matches('\\'),
+                    next.line_text()
+                );
+            }
+        }
+        macros.push(Macro {
+            definition: sym,
+            parameters,
+            replacement: replacement
+                .trim_end_matches('\\')
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" "),
+        });
+    }
+    macros
+}
+
+// Renders macros in the tree style, grouped under the file they are in.
+pub struct MacroListing<'a> {
+    macros: Vec<Macro<'a>>,
+    // The records using each macro, by name, when asked for.
+    uses: Option<HashMap<&'a str, Vec<&'a Symbol>>>,
+    options: RenderOptions,
+}
+
+impl<'a> MacroListing<'a> {
+    pub fn new(macros: Vec<Macro<'a>>, options: RenderOptions) -> MacroListing<'a> {
+        MacroListing {
+            macros,
+            uses: None,
+            options,
+        }
+    }
+
+    // Along with every record using one of the macros: a call of a
+    // function-like macro, or a reference or assignment elsewhere.
+    pub fn with_uses(mut self, cscope: &'a Cscope) -> MacroListing<'a> {
+        let mut uses: HashMap<&str, Vec<&Symbol>> = self
+            .macros
+            .iter()
+            .map(|m| (m.definition.display_name(), vec![]))
+            .collect();
+        for sym in cscope.symbols().iter().filter(|s| {
+            matches!(
+                s.mark(),
+                FileMark::Reference | FileMark::FunctionCall | FileMark::DirectAssingmentIncDec
+            )
+        }) {
+            if let Some(records) = uses.get_mut(sym.display_name()) {
+                records.push(sym);
+            }
+        }
+        self.uses = Some(uses);
+        self
+    }
+
+    fn uses_of(&self, m: &Macro) -> &[&'a Symbol] {
+        self.uses
+            .as_ref()
+            .and_then(|uses| uses.get(m.definition.display_name()))
+            .map_or(&[], |records| records.as_slice())
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "macros",
+            &["file", "name", "parameters", "replacement", "line"],
+        );
+        for m in self.macros.iter() {
+            table.rows.push(vec![
+                Cell::from(m.definition.filename()),
+                Cell::from(m.definition.display_name()),
+                Cell::from(m.parameters.as_deref().unwrap_or("")),
+                Cell::from(m.replacement.as_str()),
+                Cell::from(m.definition.line_number()),
+            ]);
+        }
+        table
+    }
+
+    // The uses of each macro, when asked for.
+    pub fn tables(&self) -> Vec<Table> {
+        let mut tables = vec![self.table()];
+        if self.uses.is_some() {
+            let mut uses = Table::new("uses", &["macro", "file", "line", "text"]);
+            for m in self.macros.iter() {
+                for sym in self.uses_of(m).iter() {
+                    uses.rows.push(vec![
+                        Cell::from(m.definition.display_name()),
+                        Cell::from(sym.filename()),
+                        Cell::from(sym.line_number()),
+                        Cell::from(sym.line_text()),
+                    ]);
+                }
+            }
+            tables.push(uses);
+        }
+        tables
+    }
+}
+
+impl<'a> std::fmt::Display for MacroListing<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut fname: &str = "";
+        let heads: Vec<String> = self
+            .macros
+            .iter()
+            .map(|m| {
+                let name = m.definition.display_name();
+                format!("{}{}", name, m.parameters.as_deref().unwrap_or(""))
+            })
+            .collect();
+        let max_len: usize = heads.iter().map(|h| width(h)).max().unwrap_or(0);
+        for (m, head) in self.macros.iter().zip(heads.iter()) {
+            if m.definition.filename() != fname {
+                fname = m.definition.filename();
+                write!(f, "•{}:\n╰─╮\n", self.options.file(fname))?;
+            }
+            self.options
+                .write_row(f, "  ├", &pad(head, max_len), &m.replacement, m.definition)?;
+            if self.uses.is_some() {
+                let uses = self.uses_of(m);
+                writeln!(f, "  │ ╰ uses: {}", uses.len())?;
+                for sym in uses.iter() {
+                    writeln!(
+                        f,
+                        "  │   ├ {}:{}: {}{}",
+                        self.options.file(sym.filename()),
+                        sym.line_number(),
+                        sym.line_text(),
+                        self.options.offset(sym)
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
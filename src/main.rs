@@ -1,7 +1,21 @@
 extern crate clap;
-use clap::{App, Arg};
-use std::path::Path;
-mod cscope;
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use code_ls::cscope::FileMark;
+use code_ls::{
+    annotate, badge, collisions, config, cscope, delimited, diff, doxytag, encoding, filter,
+    fixture, graph, grep, html, includes, info, json, kythe, linemode, listing, macros, markup,
+    outline, rename, render, report, sarif, search, selfcheck, sidecar, sort, sqlite, stats, table,
+    tags, warnings, writer, xlsx,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+mod cache;
+mod lsp;
+mod open;
+mod tui;
+mod watch;
 
 fn main() {
     let args = App::new("code-ls")
@@ -9,17 +23,1724 @@ fn main() {
             Arg::with_name("file")
                 .value_name("FILE")
                 .short("f")
-                .help("cscope database file (it must be uncompressed).")
-                .required(true),
+                .help("cscope database file, compressed or built with -c, and as it is when kept gzip or zstd compressed. By default $CSCOPE_DB, or the cscope.out found in the working directory or above. Give it more than once, or a directory of databases, to browse them as one.")
+                .multiple(true)
+                .number_of_values(1)
+                .global(true),
         )
+        .arg(
+            Arg::with_name("demangle")
+                .long("demangle")
+                .global(true)
+                .help("Demangle C++ (Itanium ABI) symbol names."),
+        )
+        .arg(
+            Arg::with_name("no-system")
+                .long("no-system")
+                .global(true)
+                .help("Leave out symbols from system and toolchain headers (e.g. /usr/include)."),
+        )
+        .arg(
+            Arg::with_name("system-prefix")
+                .long("system-prefix")
+                .value_name("PREFIX")
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("An additional system header directory for --no-system."),
+        )
+        .arg(
+            Arg::with_name("gitignore")
+                .long("gitignore")
+                .global(true)
+                .help("Also leave out the files matched by the .gitignore next to the database."),
+        )
+        .arg(
+            Arg::with_name("path")
+                .long("path")
+                .value_name("GLOB")
+                .multiple(true)
+                .number_of_values(1)
+                .global(true)
+                .help("Only show symbols from source files matching GLOB (e.g. 'drivers/net/**'), may be repeated."),
+        )
+        .arg(
+            Arg::with_name("no-default-excludes")
+                .long("no-default-excludes")
+                .global(true)
+                .help("Keep generated sources (e.g. *.pb-c.c, *_generated.h, build/**)."),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .long("sort")
+                .value_name("ORDER")
+                .possible_values(&["name", "line", "file", "natural", "kind", "count"])
+                .global(true)
+                .help("Order the records of each file by name, line or kind, or the files by path, naturally (file2.c before file10.c) or by their number of records, rather than as in the database."),
+        )
+        .arg(
+            Arg::with_name("context")
+                .long("context")
+                .value_name("N")
+                .global(true)
+                .help("Show N lines of the source file around each symbol of the tree, read from where cscope was run."),
+        )
+        .arg(
+            Arg::with_name("offsets")
+                .long("offsets")
+                .global(true)
+                .help("Show the byte offset of each record within the database."),
+        )
+        .arg(
+            Arg::with_name("lenient")
+                .long("lenient")
+                .global(true)
+                .help("Skip malformed records and summarize what was skipped or odd at the end."),
+        )
+        .arg(
+            Arg::with_name("encoding")
+                .long("encoding")
+                .value_name("ENCODING")
+                .possible_values(&["utf8", "latin1", "auto"])
+                .global(true)
+                .help("How to read names and source text: as UTF-8, Latin-1, or UTF-8 falling back to Latin-1."),
+        )
+        .arg(
+            Arg::with_name("unknown-marks")
+                .long("unknown-marks")
+                .value_name("POLICY")
+                .possible_values(&["error", "warn", "ignore"])
+                .global(true)
+                .help("Fail on mark characters code-ls does not know, list them at the end (the default), or ignore them."),
+        )
+        .arg(
+            Arg::with_name("kinds")
+                .long("kinds")
+                .value_name("KINDS")
+                .global(true)
+                .help("Only read records of these comma separated kinds (e.g. function,struct)."),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .possible_values(&["text", "json", "xlsx", "rst", "asciidoc", "markdown", "csv", "tsv", "grep"])
+                .global(true)
+                .help("Write the output as text, JSON, an Excel workbook, reStructuredText or AsciiDoc tables, or file:line: lines like grep -n."),
+        )
+        .arg(
+            Arg::with_name("url-template")
+                .long("url-template")
+                .value_name("TEMPLATE")
+                .global(true)
+                .help("Add a url column to tables, filling in {path}, {line} and {rev} (e.g. https://host/repo/blob/{rev}/{path}#L{line})."),
+        )
+        .arg(
+            Arg::with_name("revision")
+                .long("revision")
+                .value_name("REV")
+                .global(true)
+                .help("The revision put in place of {rev} in the url template, HEAD by default."),
+        )
+        .arg(
+            Arg::with_name("no-cache")
+                .long("no-cache")
+                .global(true)
+                .help("Do not reuse, or save, the output of an earlier run of the same query, nor the parsed database kept beside a large one (cscope.out.cls)."),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .global(true)
+                .help("Keep running, printing the output again whenever the database, or one of those given with -f, is regenerated."),
+        )
+        .arg(
+            Arg::with_name("exec")
+                .long("exec")
+                .value_name("CMD")
+                .requires("watch")
+                .global(true)
+                .help("With --watch, run CMD through sh after each regeneration, {} standing for the database path (passed as \"$1\")."),
+        )
+        .arg(
+            Arg::with_name("wrap")
+                .long("wrap")
+                .global(true)
+                .help("Wrap long signatures onto continuation lines to fit the terminal."),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .possible_values(&["auto", "always", "never"])
+                .global(true)
+                .help("Color file names, symbol names and line numbers: always, never, or when writing text to a terminal (the default)."),
+        )
+        .args(&list_args())
+        .args(&line_mode_args())
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List the functions of each file, the same as giving no subcommand.")
+                .args(&list_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("files")
+                .about("List the source files the database was built from, from its trailer."),
+        )
+        .subcommand(
+            SubCommand::with_name("query")
+                .about("Print where SYMBOL is defined and where it is used.")
+                .arg(Arg::with_name("symbol").value_name("SYMBOL").required(true))
+                .args(&match_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("def")
+                .about("Print the file and line of each definition of NAME, exiting with 1 when there is none.")
+                .arg(Arg::with_name("name").value_name("NAME").required(true))
+                .args(&match_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("callers")
+                .about("List the call sites of the function NAME with the function each is in.")
+                .arg(Arg::with_name("name").value_name("NAME").required(true))
+                .args(&match_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("callees")
+                .about("List the calls made from the body of the function NAME.")
+                .arg(Arg::with_name("name").value_name("NAME").required(true))
+                .args(&match_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("whocalls")
+                .about("Print the tree of the functions calling NAME, those calling them and so on.")
+                .arg(Arg::with_name("name").value_name("NAME").required(true))
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .value_name("N")
+                        .help("Follow at most N calls up from NAME, rather than up to the functions nothing calls."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("callpaths")
+                .about("Print the deepest call paths from the entry points of the program down to the function NAME.")
+                .arg(Arg::with_name("name").value_name("NAME").required(true))
+                .arg(
+                    Arg::with_name("entry")
+                        .long("entry")
+                        .value_name("NAME")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("An entry point, e.g. main; repeatable. By default the functions nothing calls."),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .value_name("N")
+                        .default_value("5")
+                        .help("Only show the N deepest paths."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("graph")
+                .about("Print the call graph: each function with the calls made from its body.")
+                .arg(
+                    Arg::with_name("dot")
+                        .long("dot")
+                        .help("Write a Graphviz digraph of the calls between defined functions, for `dot -Tsvg`."),
+                )
+                .arg(
+                    Arg::with_name("cycles")
+                        .long("cycles")
+                        .conflicts_with("dot")
+                        .help("List the recursive functions and the cycles of functions calling each other."),
+                )
+                .arg(
+                    Arg::with_name("root")
+                        .long("root")
+                        .value_name("NAME")
+                        .help("Only the functions reached by following calls from NAME."),
+                )
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .value_name("N")
+                        .requires("root")
+                        .help("With --root, follow at most N calls from it."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("classes")
+                .about("List C++ classes with their member functions and fields."),
+        )
+        .subcommand(
+            SubCommand::with_name("includes")
+                .about("List the headers each file includes, or with --reverse the files including each header.")
+                .arg(
+                    Arg::with_name("name")
+                        .value_name("FILE")
+                        .help("Only this file, or header with --reverse, by its path or the end of it."),
+                )
+                .arg(
+                    Arg::with_name("reverse")
+                        .long("reverse")
+                        .help("Group by header: who includes it."),
+                )
+                .arg(
+                    Arg::with_name("dot")
+                        .long("dot")
+                        .help("Write a Graphviz digraph of the includes, for `dot -Tsvg`."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("members")
+                .about("List structs, unions and enums with their members nested beneath."),
+        )
+        .subcommand(
+            SubCommand::with_name("tui")
+                .about("Browse the files and definitions interactively, with a fuzzy search over every file."),
+        )
+        .subcommand(
+            SubCommand::with_name("lsp")
+                .about("Serve the Language Server Protocol on stdin and stdout: document and workspace symbols, definitions and references."),
+        )
+        .subcommand(
+            SubCommand::with_name("typedefs")
+                .about("List typedefs, or where the typedef NAME is defined and used.")
+                .arg(Arg::with_name("name").value_name("NAME")),
+        )
+        .subcommand(
+            SubCommand::with_name("types")
+                .about("List the classes, structs, unions, enums and typedefs of each file, or with --uses where TYPE is defined and used.")
+                .arg(
+                    Arg::with_name("uses")
+                        .long("uses")
+                        .value_name("TYPE")
+                        .help("Where the type TYPE is defined and every record referring to it."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("globals")
+                .about("List the global variables of each file.")
+                .arg(
+                    Arg::with_name("writes")
+                        .long("writes")
+                        .help("Also list every assignment, increment and decrement of each, with the function it is in."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("macros")
+                .about("List the #define macros of each file with their replacement text.")
+                .arg(
+                    Arg::with_name("object-like")
+                        .long("object-like")
+                        .conflicts_with("function-like")
+                        .help("Only list object-like macros."),
+                )
+                .arg(
+                    Arg::with_name("function-like")
+                        .long("function-like")
+                        .help("Only list function-like macros."),
+                )
+                .arg(
+                    Arg::with_name("uses")
+                        .long("uses")
+                        .help("Also list every place each macro is used."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("genfixture")
+                .about("Write a synthetic cscope database for testing and benchmarking.")
+                .arg(
+                    Arg::with_name("files")
+                        .long("files")
+                        .value_name("N")
+                        .default_value("10")
+                        .help("The number of source files."),
+                )
+                .arg(
+                    Arg::with_name("symbols-per-file")
+                        .long("symbols-per-file")
+                        .value_name("M")
+                        .default_value("100")
+                        .help("The number of symbols in each file."),
+                )
+                .arg(
+                    Arg::with_name("compressed")
+                        .long("compressed")
+                        .help("Compress the database like cscope does without '-c'."),
+                )
+                .arg(
+                    Arg::with_name("inverted-index")
+                        .short("q")
+                        .help("Flag the database as built with '-q' (no index files are written)."),
+                )
+                .arg(
+                    Arg::with_name("truncated")
+                        .long("truncated")
+                        .help("Cut the database off halfway through its records."),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .default_value("cscope.out")
+                        .help("Where to write the database."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rewrite")
+                .about("Write the records kept by the filters (--path, --kinds, .code-lsignore) to a new uncompressed database, with --lenient leaving out the malformed ones.")
+                .arg(Arg::with_name("output").value_name("OUTPUT").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("selfcheck")
+                .about("Write the database back out, parse it again and report what was lost.")
+                .arg(Arg::with_name("database").value_name("FILE").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Print the header (format version, directory, options, trailer offset) and the trailer of the database, checking the trailer is where the header says."),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Print the number of files, records, functions, structs and macros, the records of each kind, the largest files and what the header says.")
+                .arg(
+                    Arg::with_name("record")
+                        .long("record")
+                        .value_name("HISTORY")
+                        .help("Append a timestamped snapshot of the counts to HISTORY (JSON lines)."),
+                )
+                .arg(
+                    Arg::with_name("trend")
+                        .long("trend")
+                        .value_name("HISTORY")
+                        .help("Report how the counts changed from one snapshot in HISTORY to the next."),
+                )
+                .arg(
+                    Arg::with_name("metrics")
+                        .long("metrics")
+                        .conflicts_with_all(&["record", "trend"])
+                        .help("List each function's fan-in (distinct callers) and fan-out (distinct callees)."),
+                )
+                .arg(
+                    Arg::with_name("by")
+                        .long("by")
+                        .value_name("METRIC")
+                        .possible_values(&["fan-in", "fan-out", "name"])
+                        .requires("metrics")
+                        .help("With --metrics, order the functions by fan-in (the default) or fan-out, the most first, or by name."),
+                )
+                .arg(
+                    Arg::with_name("top")
+                        .long("top")
+                        .value_name("N")
+                        .requires("metrics")
+                        .help("With --metrics, only show the first N functions."),
+                )
+                .arg(
+                    Arg::with_name("function-sizes")
+                        .long("function-sizes")
+                        .conflicts_with_all(&["record", "trend", "metrics"])
+                        .help("Print a histogram of the number of lines the functions span."),
+                )
+                .arg(
+                    Arg::with_name("longer-than")
+                        .long("longer-than")
+                        .value_name("N")
+                        .requires("function-sizes")
+                        .help("With --function-sizes, also list the functions spanning more than N lines, the longest first."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("search")
+                .about("List the records whose name contains PATTERN, ignoring case.")
+                .arg(Arg::with_name("pattern").value_name("PATTERN").required(true))
+                .arg(
+                    Arg::with_name("rank")
+                        .long("rank")
+                        .help("Order by relevance: match quality, definitions first, then shallower paths."),
+                )
+                .arg(
+                    Arg::with_name("fuzzy")
+                        .long("fuzzy")
+                        .help("Match the characters of PATTERN in order anywhere in the name, as fzf does, ranked by how well they match."),
+                )
+                .arg(
+                    Arg::with_name("open")
+                        .long("open")
+                        .help("Open the matching definition in $VISUAL or $EDITOR, asking which one if there are several."),
+                )
+                .arg(
+                    Arg::with_name("first")
+                        .long("first")
+                        .requires("open")
+                        .help("With --open, open the first of several definitions without asking."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("text")
+                .about("List the source lines matching the regular expression PATTERN.")
+                .arg(Arg::with_name("pattern").value_name("PATTERN").required(true))
+                .args(&match_args())
+                .arg(
+                    Arg::with_name("sources")
+                        .long("sources")
+                        .help("Search every line of the source files, not only the lines with a symbol the database holds."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about("List the definitions added, removed and moved to another file from OLD to NEW.")
+                .arg(Arg::with_name("old").value_name("OLD").required(true))
+                .arg(
+                    Arg::with_name("new")
+                        .value_name("NEW")
+                        .help("The newer database, the one found or given with -f by default."),
+                )
+                .arg(
+                    Arg::with_name("lines")
+                        .long("lines")
+                        .help("Also list the definitions moved to another line of their file."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("case-collisions")
+                .about("List definitions whose names differ only by case (Foo_bar and foo_bar)."),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Write the definitions out for other documentation and indexing tools.")
+                .arg(
+                    Arg::with_name("doxytag")
+                        .long("doxytag")
+                        .value_name("FILE")
+                        .help("Write a Doxygen tagfile to FILE, for TAGFILES in a Doxyfile."),
+                )
+                .arg(
+                    Arg::with_name("kythe")
+                        .long("kythe")
+                        .value_name("FILE")
+                        .help("Write Kythe entries to FILE, as JSON lines for `entrystream --read_format=json`."),
+                )
+                .arg(
+                    Arg::with_name("ctags")
+                        .long("ctags")
+                        .value_name("FILE")
+                        .help("Write a sorted ctags file to FILE, e.g. tags, for editors that read those."),
+                )
+                .arg(
+                    Arg::with_name("etags")
+                        .long("etags")
+                        .value_name("FILE")
+                        .help("Write an Emacs TAGS file to FILE."),
+                )
+                .arg(
+                    Arg::with_name("sqlite")
+                        .long("sqlite")
+                        .value_name("FILE")
+                        .help("Write the files, records and calls to the SQLite database FILE, replacing it."),
+                )
+                .arg(
+                    Arg::with_name("html")
+                        .long("html")
+                        .value_name("DIR")
+                        .help("Write a static HTML report to DIR: an index of the files and a page per file, calls linked to definitions."),
+                )
+                .arg(
+                    Arg::with_name("sarif")
+                        .long("sarif")
+                        .value_name("FILE")
+                        .help("Write a SARIF log to FILE: the definitions, and results for the dead functions, the unknown marks and the other parser warnings."),
+                )
+                .arg(
+                    Arg::with_name("corpus")
+                        .long("corpus")
+                        .value_name("NAME")
+                        .default_value("cscope")
+                        .help("The corpus of the Kythe nodes."),
+                )
+                .group(
+                    ArgGroup::with_name("exports")
+                        .args(&["doxytag", "kythe", "ctags", "etags", "html", "sqlite", "sarif"])
+                        .multiple(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rename")
+                .about("Print a sed script or a patch renaming OLD to NEW wherever the database has it.")
+                .arg(Arg::with_name("old").value_name("OLD").required(true))
+                .arg(Arg::with_name("new").value_name("NEW").required(true))
+                .arg(
+                    Arg::with_name("emit")
+                        .long("emit")
+                        .value_name("FORM")
+                        .possible_values(&["sed", "patch"])
+                        .default_value("sed")
+                        .help("A shell script of sed commands, or a unified diff."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("annotate")
+                .about("Print a source file with the symbols each line defines or calls alongside.")
+                .arg(Arg::with_name("source").value_name("SOURCE").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("dead")
+                .about("List the functions defined but never called or referred to.")
+                .arg(
+                    Arg::with_name("exclude-main")
+                        .long("exclude-main")
+                        .help("Leave out main, which is called from outside."),
+                )
+                .arg(
+                    Arg::with_name("exclude")
+                        .long("exclude")
+                        .value_name("REGEX")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Leave out functions whose name matches REGEX, e.g. '^test_'; repeatable."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("header-functions")
+                .about("List the functions defined in headers, with counts per header."),
+        )
+        .subcommand(
+            SubCommand::with_name("badge")
+                .about("Print a shields.io endpoint badge (JSON) counting what the database holds.")
+                .arg(
+                    Arg::with_name("metric")
+                        .long("metric")
+                        .value_name("METRIC")
+                        .possible_values(&["functions", "files", "dead"])
+                        .default_value("functions")
+                        .help("Count the functions, the files, or the functions nothing calls."),
+                ),
+        )
+        .subcommands(vec![
+            report_command("longest", "Rank functions by the number of lines they span."),
+            report_command("most-called", "Rank functions by the number of call sites.")
+                .visible_alias("rank"),
+            report_command("most-included", "Rank headers by the number of #includes."),
+            report_command("freq", "Rank names by the number of records they appear in."),
+        ])
         .get_matches();
 
-    let fname = args.value_of("file").unwrap();
-    match cscope::parse_database(&Path::new(fname)) {
-        Ok(_) => (),
-        Err(e) => {
+    if let ("genfixture", Some(sub)) = args.subcommand() {
+        let options = fixture::FixtureOptions {
+            files: parse_count(sub.value_of("files").unwrap_or("10"), "--files") as usize,
+            symbols_per_file: parse_count(
+                sub.value_of("symbols-per-file").unwrap_or("100"),
+                "--symbols-per-file",
+            ) as usize,
+            write: writer::WriteOptions {
+                compressed: sub.is_present("compressed"),
+                inverted_index: sub.is_present("inverted-index"),
+            },
+            truncated: sub.is_present("truncated"),
+        };
+        let output = sub.value_of("output").unwrap_or("cscope.out");
+        if let Err(e) = std::fs::write(output, fixture::generate(&options)) {
+            exit_with_error(format!("{}: {}", output, e));
+        }
+        return;
+    }
+
+    if let ("selfcheck", Some(sub)) = args.subcommand() {
+        let fname = sub.value_of("database").unwrap_or("cscope.out");
+        let check = cscope::parse_database(Path::new(fname))
+            .and_then(|cscope| selfcheck::SelfCheck::run(fname, &cscope))
+            .unwrap_or_else(|e| exit_with_error(e));
+        print!("{}", check);
+        if !check.is_lossless() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // The trend of a history alone needs no database.
+    if let ("stats", Some(sub)) = args.subcommand() {
+        if let (Some(history), None, None) = (
+            sub.value_of("trend"),
+            sub.value_of("record"),
+            sub.value_of("file"),
+        ) {
+            let trend = stats::Trend::load(Path::new(history))
+                .unwrap_or_else(|e| exit_with_error(format!("{}: {}", history, e)));
+            print!("{}", trend);
+            return;
+        }
+    }
+
+    // Global arguments are propagated down to the subcommand's matches.
+    let sub_args = args.subcommand().1.unwrap_or(&args);
+    let fnames: Vec<String> = match sub_args.values_of("file") {
+        Some(names) => names.flat_map(database_files).collect(),
+        None => match sub_args.value_of("new").map(PathBuf::from).or_else(|| {
+            let found = find_database()?;
+            eprintln!("Using {}", found.display());
+            Some(found)
+        }) {
+            Some(path) => vec![path.to_string_lossy().into_owned()],
+            None => exit_with_error(
+                "No cscope.out here or in a parent directory, give one with '-f <FILE>'.",
+            ),
+        },
+    };
+    if fnames.is_empty() {
+        exit_with_error("No databases (*.out) in the directories given with -f.");
+    }
+    // The configuration and ignore files sit where cscope was run, next to
+    // the (first) database.
+    let fname = fnames[0].as_str();
+    let db_dir = match Path::new(fname).parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    // Read apart from the records, which a damaged database may not parse.
+    if let ("info", Some(sub)) = args.subcommand() {
+        let output = Output {
+            format: sub.value_of("format").unwrap_or("text"),
+            urls: None,
+            cache: None,
+            warnings: String::new(),
+        };
+        for (idx, name) in fnames.iter().enumerate() {
+            let info = cscope::read_database(Path::new(name))
+                .and_then(|db| info::Info::new(&db))
+                .unwrap_or_else(|e| exit_with_error(format!("{}: {}", name, e)));
+            if fnames.len() > 1 {
+                if idx > 0 {
+                    println!();
+                }
+                println!("{}:", name);
+            }
+            output.emit(&info, || info.tables());
+        }
+        return;
+    }
+    let shown = show(&args, &fnames, db_dir);
+    if sub_args.is_present("watch") {
+        // A query failing or finding nothing can do otherwise on the next
+        // database, so that does not end the watch.
+        if let Err(e) = &shown {
             eprintln!("Error: {}", e);
-            std::process::exit(1)
         }
+        let paths: Vec<&Path> = fnames.iter().map(Path::new).collect();
+        watch::watch(&paths, sub_args.value_of("exec"), || {
+            show(&args, &fnames, db_dir)
+        });
+    }
+    shown.unwrap_or_else(|e| exit_with_error(e));
+}
+
+// A database given with -f, or the databases in a directory given with -f
+// leaving out the inverted index cscope -q keeps next to each.
+fn database_files(name: &str) -> Vec<String> {
+    let dir = Path::new(name);
+    if !dir.is_dir() {
+        return vec![name.to_string()];
+    }
+    let entries =
+        std::fs::read_dir(dir).unwrap_or_else(|e| exit_with_error(format!("{}: {}", name, e)));
+    let mut files: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file())
+        .map(|p| p.to_string_lossy().into_owned())
+        .filter(|p| p.ends_with(".out") && !p.ends_with(".in.out") && !p.ends_with(".po.out"))
+        .collect();
+    files.sort();
+    files
+}
+
+// The subcommands worth caching the output of, those which go through every
+// record of the database more than once.
+const CACHED_QUERIES: &[&str] = &[
+    "search",
+    "query",
+    "callers",
+    "callees",
+    "graph",
+    "case-collisions",
+    "longest",
+    "most-called",
+    "most-included",
+    "freq",
+    "dead",
+    "includes",
+];
+
+// Parse the database and print what was asked for.
+fn show(args: &ArgMatches, fnames: &[String], db_dir: &Path) -> Result<(), Error> {
+    let fname = fnames[0].as_str();
+    let sub_args = args.subcommand().1.unwrap_or(args);
+    let format = sub_args.value_of("format").unwrap_or("text");
+
+    let color = match sub_args.value_of("color") {
+        Some("always") => true,
+        Some("never") => false,
+        _ => format == "text" && terminal_size::terminal_size_of(std::io::stdout()).is_some(),
+    };
+
+    // A query seen before against the same database prints what it did then.
+    let cached = args
+        .subcommand_name()
+        .is_some_and(|n| CACHED_QUERIES.contains(&n));
+    // The source lines of --context can change without the database.
+    // Nor is the cache keyed on more than one database.
+    let cache = if !cached
+        || sub_args.is_present("no-cache")
+        || sub_args.is_present("context")
+        || fnames.len() > 1
+    {
+        None
+    } else {
+        let mut query = cache::normalize_query(std::env::args().skip(1));
+        if sub_args.is_present("wrap") {
+            query.push(format!("columns={}", terminal_width()));
+        }
+        if color {
+            query.push("color".to_string());
+        }
+        let mut inputs: Vec<PathBuf> = config::user_config_path().into_iter().collect();
+        inputs.extend([
+            db_dir.join(config::CONFIG_FILE),
+            db_dir.join(filter::IGNORE_FILE),
+            db_dir.join(".gitignore"),
+        ]);
+        cache::Cache::new(Path::new(fname), &query, &inputs).ok()
+    };
+    if let Some((warnings, bytes)) = cache.as_ref().and_then(|c| c.get()) {
+        write_output(format, &bytes);
+        eprint!("{}", warnings);
+        return Ok(());
+    }
+    // The listing options are read from `list` when it is given.
+    let list_args = match args.subcommand() {
+        ("list", Some(sub)) => sub,
+        _ => args,
+    };
+    let kinds: Option<Vec<FileMark>> = list_args
+        .values_of("kind")
+        .map(|names| names.map(parse_kind).collect());
+    let path_filter = path_filter(sub_args, db_dir).map_err(failure)?;
+    let parse_options = cscope::ParseOptions {
+        lenient: sub_args.is_present("lenient"),
+        encoding: sub_args
+            .value_of("encoding")
+            .and_then(encoding::Encoding::from_name)
+            .unwrap_or_default(),
+        unknown_marks: match sub_args.value_of("unknown-marks") {
+            Some("error") => cscope::UnknownMarks::Error,
+            Some("ignore") => cscope::UnknownMarks::Ignore,
+            _ => cscope::UnknownMarks::Warn,
+        },
+        // Listing some kinds only needs their records read, and the
+        // functions around them for the FUNCTION column of -l.
+        kinds: sub_args.value_of("kinds").map(parse_kinds).or_else(|| {
+            kinds.as_ref().map(|k| {
+                let mut set = kind_set(k);
+                if list_args.is_present("long") {
+                    set.insert(FileMark::FunctionDefinition);
+                    set.insert(FileMark::FunctionEnd);
+                }
+                set
+            })
+        }),
+    };
+    let mut options = render::RenderOptions {
+        offsets: sub_args.is_present("offsets"),
+        wrap: if sub_args.is_present("wrap") {
+            Some(terminal_width())
+        } else {
+            None
+        },
+        color,
+        context: None,
+    };
+    if list_args.is_present("stream") {
+        if format != "text" {
+            return Err(failure("--stream only prints text."));
+        }
+        if fnames.len() > 1 {
+            return Err(failure("--stream reads a single database."));
+        }
+        stream(sub_args, fname, parse_options, &path_filter, options);
+        return Ok(());
+    }
+    let parse = |name: &str| {
+        if sub_args.is_present("no-cache") {
+            cscope::parse_database_with(Path::new(name), parse_options)
+        } else {
+            sidecar::parse_database_cached(Path::new(name), parse_options)
+        }
+    };
+    match parse(fname) {
+        Ok(mut cscope) => {
+            for name in fnames[1..].iter() {
+                match parse(name) {
+                    Ok(more) => cscope.merge(more),
+                    Err(e) => return Err(failure(format!("{}: {}", name, e))),
+                }
+            }
+            if sub_args.is_present("demangle") {
+                cscope.demangle_names();
+            }
+            if let Some(lines) = sub_args.value_of("context") {
+                let dir = source_dir(&cscope, db_dir);
+                let context = render::SourceContext::new(parse_count(lines, "--context"), dir);
+                options.context = Some(std::rc::Rc::new(context));
+            }
+            cscope.retain_symbols(|s| !path_filter.is_excluded(s.filename()));
+            let reverse = list_args.is_present("reverse");
+            let flip = |order: Ordering| if reverse { order.reverse() } else { order };
+            match sub_args.value_of("sort") {
+                Some("file") => cscope.sort_files(|a, b| flip(a.cmp(b))),
+                Some("natural") => cscope.sort_files(|a, b| flip(sort::natural_cmp(a, b))),
+                Some("count") => {
+                    // Most records first.
+                    let mut counts: HashMap<String, usize> = HashMap::new();
+                    for sym in cscope.symbols().iter() {
+                        *counts.entry(sym.filename().to_string()).or_insert(0) += 1;
+                    }
+                    cscope.sort_files(|a, b| flip(counts[b].cmp(&counts[a])));
+                }
+                _ => (),
+            }
+            let within = match sub_args.value_of("sort") {
+                Some("name") => sort::WithinFile::Name,
+                Some("line") => sort::WithinFile::Line,
+                Some("kind") => sort::WithinFile::Kind,
+                _ => sort::WithinFile::Database,
+            };
+            let output = Output {
+                cache,
+                warnings: warning_text(&cscope, parse_options),
+                format,
+                urls: sub_args
+                    .value_of("url-template")
+                    .map(|t| table::UrlTemplate {
+                        template: t.to_string(),
+                        revision: sub_args.value_of("revision").unwrap_or("HEAD").to_string(),
+                    }),
+            };
+            if args.is_present("line-mode") {
+                let (field, pattern) = LINE_QUERIES
+                    .iter()
+                    .enumerate()
+                    .find_map(|(n, (name, _))| Some((n as u8, args.value_of(name)?)))
+                    .ok_or_else(|| failure("-L needs a query, one of -0 to -9."))?;
+                let dir = source_dir(&cscope, db_dir);
+                let field = linemode::Field::from_number(field).unwrap_or(linemode::Field::Symbol);
+                let query = linemode::LineQuery::new(
+                    &cscope,
+                    field,
+                    pattern,
+                    args.is_present("ignore-case"),
+                    dir,
+                    |f| !path_filter.is_excluded(f),
+                )
+                .map_err(failure)?;
+                print!("{}", query);
+                return Ok(());
+            }
+            match args.subcommand() {
+                ("tui", _) => {
+                    if terminal_size::terminal_size_of(std::io::stdout()).is_none() {
+                        return Err(failure("The browser needs a terminal."));
+                    }
+                    let dir = source_dir(&cscope, db_dir);
+                    tui::run(&cscope, dir).map_err(failure)?;
+                }
+                ("lsp", _) => {
+                    let dir = source_dir(&cscope, db_dir);
+                    lsp::run(&cscope, dir).map_err(failure)?;
+                }
+                ("classes", _) => {
+                    let classes = outline::scopes(&cscope, |m| m == FileMark::ClassDefinition);
+                    let outline = outline::Outline::new(classes, options);
+                    output.emit(&outline, || vec![outline.table("classes")]);
+                }
+                ("includes", Some(sub)) => {
+                    let mut includes = includes::Includes::new(&cscope, options);
+                    if sub.is_present("reverse") {
+                        includes = includes.reverse();
+                    }
+                    if let Some(name) = sub.value_of("name") {
+                        includes = includes.only(name);
+                        if includes.is_empty() {
+                            return Err(failure(format!("No includes for '{}'.", name)));
+                        }
+                    }
+                    if sub.is_present("dot") {
+                        print!("{}", includes.dot());
+                    } else {
+                        output.emit(&includes, || vec![includes.table()]);
+                    }
+                }
+                ("members", _) => {
+                    let types = outline::scopes(&cscope, |m| {
+                        matches!(
+                            m,
+                            FileMark::StructDefinition
+                                | FileMark::UnionDefinition
+                                | FileMark::EnumDefinition
+                        )
+                    });
+                    let outline = outline::Outline::new(types, options);
+                    output.emit(&outline, || vec![outline.table("members")]);
+                }
+                ("typedefs", Some(sub)) => {
+                    let is_typedef = |s: &cscope::Symbol| s.mark() == FileMark::TypedefDefinition;
+                    match sub.value_of("name") {
+                        Some(name) => {
+                            let usage = listing::Usage::new(
+                                name,
+                                search::NameMatch::default(),
+                                cscope.symbols(),
+                                is_typedef,
+                                options,
+                            );
+                            if !usage.is_defined() {
+                                return Err(failure(format!("No typedef named '{}'.", name)));
+                            }
+                            output.emit(&usage, || vec![usage.table()]);
+                        }
+                        None => {
+                            let typedefs = cscope.symbols().iter().filter(|s| is_typedef(s));
+                            let listing = listing::Listing::new(typedefs.collect(), options);
+                            output.emit(&listing, || vec![listing.table("typedefs")]);
+                        }
+                    }
+                }
+                ("types", Some(sub)) => match sub.value_of("uses") {
+                    Some(name) => {
+                        let usage = listing::Usage::new(
+                            name,
+                            search::NameMatch::default(),
+                            cscope.symbols(),
+                            |s| TYPE_KINDS.contains(&s.mark()),
+                            options,
+                        );
+                        if !usage.is_defined() {
+                            return Err(failure(format!("No type named '{}'.", name)));
+                        }
+                        output.emit(&usage, || vec![usage.table()]);
+                    }
+                    None => {
+                        let listing = listing::KindListing::new(&cscope, TYPE_KINDS, options)
+                            .sorted(within, reverse);
+                        output.emit(&listing, || vec![listing.table()]);
+                    }
+                },
+                ("globals", Some(sub)) => {
+                    let mut globals = listing::Globals::new(&cscope, options);
+                    if sub.is_present("writes") {
+                        globals = globals.with_writes(&cscope);
+                    }
+                    output.emit(&globals, || globals.tables());
+                }
+                ("macros", Some(sub)) => {
+                    let object_like = sub.is_present("object-like");
+                    let function_like = sub.is_present("function-like");
+                    let macros = macros::macros(&cscope)
+                        .into_iter()
+                        .filter(|m| !object_like || !m.is_function_like())
+                        .filter(|m| !function_like || m.is_function_like());
+                    let mut listing = macros::MacroListing::new(macros.collect(), options);
+                    if sub.is_present("uses") {
+                        listing = listing.with_uses(&cscope);
+                    }
+                    output.emit(&listing, || listing.tables());
+                }
+                ("search", Some(sub)) => {
+                    let pattern = sub.value_of("pattern").unwrap_or("");
+                    let mut search = if sub.is_present("fuzzy") {
+                        search::Search::fuzzy(&cscope, pattern, options)
+                    } else {
+                        search::Search::new(&cscope, pattern, options)
+                    };
+                    if sub.is_present("rank") {
+                        search = search.rank();
+                    }
+                    if search.is_empty() {
+                        return Err(failure(format!("Nothing matches '{}'.", pattern)));
+                    }
+                    if sub.is_present("open") {
+                        let dir = source_dir(&cscope, db_dir);
+                        open::choose(&search.definitions(), sub.is_present("first"))
+                            .and_then(|sym| open::open(sym, dir))
+                            .map_err(failure)?;
+                        return Ok(());
+                    }
+                    output.emit(&search, || vec![search.table()]);
+                }
+                ("text", Some(sub)) => {
+                    let pattern = sub.value_of("pattern").unwrap_or("");
+                    let regex = name_match(sub).regex(pattern).map_err(failure)?;
+                    let text = if sub.is_present("sources") {
+                        let dir = source_dir(&cscope, db_dir);
+                        search::TextSearch::in_sources(&cscope, &regex, dir, options)
+                    } else {
+                        search::TextSearch::new(&cscope, &regex, options)
+                    };
+                    if text.is_empty() {
+                        return Err(failure(format!("Nothing matches '{}'.", pattern)));
+                    }
+                    output.emit(&text, || vec![text.table()]);
+                }
+                ("diff", Some(sub)) => {
+                    let old_name = sub.value_of("old").unwrap_or("");
+                    let mut old = cscope::parse_database_with(Path::new(old_name), parse_options)
+                        .map_err(|e| failure(format!("{}: {}", old_name, e)))?;
+                    if sub_args.is_present("demangle") {
+                        old.demangle_names();
+                    }
+                    old.retain_symbols(|s| !path_filter.is_excluded(s.filename()));
+                    let diff = diff::Diff::new(&old, &cscope, sub.is_present("lines"), options);
+                    output.emit(&diff, || vec![diff.table()]);
+                }
+                ("rewrite", Some(sub)) => {
+                    let path = sub.value_of("output").unwrap_or("");
+                    std::fs::File::create(path)
+                        .and_then(|mut file| cscope.write_to(&mut file))
+                        .map_err(|e| failure(format!("{}: {}", path, e)))?;
+                }
+                ("files", _) => {
+                    let files = listing::SourceFiles::new(&cscope, |f| !path_filter.is_excluded(f));
+                    output.emit(&files, || vec![files.table()]);
+                }
+                ("query", Some(sub)) => {
+                    let symbol = sub.value_of("symbol").unwrap_or("");
+                    let usage = listing::Usage::new(
+                        symbol,
+                        name_match(sub),
+                        cscope.symbols(),
+                        |s| s.mark().is_definition(),
+                        options,
+                    );
+                    if !usage.is_defined() && usage.is_unused() {
+                        return Err(failure(format!("Nothing is named '{}'.", symbol)));
+                    }
+                    output.emit(&usage, || vec![usage.table()]);
+                }
+                ("def", Some(sub)) => {
+                    let name = sub.value_of("name").unwrap_or("");
+                    let definitions = listing::Definitions::new(&cscope, name, name_match(sub));
+                    if definitions.is_empty() {
+                        return Err(failure(format!("No definition of '{}'.", name)));
+                    }
+                    output.emit(&definitions, || vec![definitions.table(name)]);
+                }
+                ("callers", Some(sub)) => {
+                    let name = sub.value_of("name").unwrap_or("");
+                    let callers = graph::Callers::new(&cscope, name, name_match(sub), options);
+                    if callers.is_empty() {
+                        return Err(failure(format!("Nothing calls '{}'.", name)));
+                    }
+                    output.emit(&callers, || vec![callers.table()]);
+                }
+                ("callees", Some(sub)) => {
+                    let name = sub.value_of("name").unwrap_or("");
+                    let callees =
+                        graph::CallGraph::new(&cscope, options).of_callers(name, name_match(sub));
+                    if !callees.is_defined() {
+                        return Err(failure(format!("No function named '{}'.", name)));
+                    }
+                    if !callees.has_calls() {
+                        return Err(failure(format!("'{}' calls nothing.", name)));
+                    }
+                    output.emit(&callees, || vec![callees.table()]);
+                }
+                ("whocalls", Some(sub)) => {
+                    let name = sub.value_of("name").unwrap_or("");
+                    let depth = sub
+                        .value_of("depth")
+                        .map(|n| parse_count(n, "--depth") as usize);
+                    let graph = graph::CallGraph::new(&cscope, options);
+                    let who = graph::WhoCalls::new(&graph, name, depth);
+                    if who.is_empty() {
+                        return Err(failure(format!("Nothing calls '{}'.", name)));
+                    }
+                    output.emit(&who, || vec![who.table()]);
+                }
+                ("callpaths", Some(sub)) => {
+                    let name = sub.value_of("name").unwrap_or("");
+                    let entries: Vec<&str> = sub.values_of("entry").into_iter().flatten().collect();
+                    let top = parse_count(sub.value_of("top").unwrap_or("5"), "--top") as usize;
+                    let graph = graph::CallGraph::new(&cscope, options);
+                    let paths = graph::CallPaths::new(&graph, name, &entries).top(top);
+                    if paths.is_empty() {
+                        return Err(failure(format!("No entry point calls '{}'.", name)));
+                    }
+                    output.emit(&paths, || vec![paths.table()]);
+                }
+                ("graph", Some(sub)) => {
+                    let mut graph = graph::CallGraph::new(&cscope, options);
+                    if let Some(root) = sub.value_of("root") {
+                        let depth = sub
+                            .value_of("depth")
+                            .map(|n| parse_count(n, "--depth") as usize);
+                        graph = graph.rooted_at(root, depth);
+                        if !graph.is_defined() {
+                            return Err(failure(format!("No function named '{}'.", root)));
+                        }
+                    }
+                    if sub.is_present("cycles") {
+                        let cycles = graph::Cycles::new(&graph);
+                        if cycles.is_empty() {
+                            return Err(failure("No function calls itself, directly or not."));
+                        }
+                        output.emit(&cycles, || vec![cycles.table()]);
+                    } else if sub.is_present("dot") {
+                        output.emit(&graph.dot(), || vec![graph.table()]);
+                    } else {
+                        output.emit(&graph, || vec![graph.table()]);
+                    }
+                }
+                ("case-collisions", _) => {
+                    let collisions = collisions::CaseCollisions::new(&cscope);
+                    output.emit(&collisions, || vec![collisions.table()]);
+                }
+                ("export", Some(sub)) => {
+                    if let Some(path) = sub.value_of("doxytag") {
+                        std::fs::write(path, doxytag::tagfile(&cscope))
+                            .map_err(|e| failure(format!("{}: {}", path, e)))?;
+                    }
+                    if let Some(path) = sub.value_of("ctags") {
+                        std::fs::write(path, tags::ctags(&cscope))
+                            .map_err(|e| failure(format!("{}: {}", path, e)))?;
+                    }
+                    if let Some(path) = sub.value_of("etags") {
+                        let dir = source_dir(&cscope, db_dir);
+                        std::fs::write(path, tags::etags(&cscope, dir))
+                            .map_err(|e| failure(format!("{}: {}", path, e)))?;
+                    }
+                    if let Some(path) = sub.value_of("sqlite") {
+                        sqlite::export(&cscope, Path::new(path))
+                            .map_err(|e| failure(format!("{}: {}", path, e)))?;
+                    }
+                    if let Some(dir) = sub.value_of("html") {
+                        write_report(Path::new(dir), html::report(&cscope))
+                            .map_err(|e| failure(format!("{}: {}", dir, e)))?;
+                    }
+                    if let Some(path) = sub.value_of("sarif") {
+                        let dir = source_dir(&cscope, db_dir);
+                        let db = Path::new(fname);
+                        std::fs::write(path, sarif::log(&cscope, db, dir))
+                            .map_err(|e| failure(format!("{}: {}", path, e)))?;
+                    }
+                    if let Some(path) = sub.value_of("kythe") {
+                        let corpus = sub.value_of("corpus").unwrap_or("cscope");
+                        std::fs::write(path, kythe::entries(&cscope, corpus))
+                            .map_err(|e| failure(format!("{}: {}", path, e)))?;
+                    }
+                }
+                ("rename", Some(sub)) => {
+                    let old = sub.value_of("old").unwrap_or("");
+                    let rename =
+                        rename::Rename::new(&cscope, old, sub.value_of("new").unwrap_or(""));
+                    if rename.is_empty() {
+                        return Err(failure(format!("Nothing is named '{}'.", old)));
+                    }
+                    let dir = source_dir(&cscope, db_dir);
+                    match sub.value_of("emit") {
+                        Some("patch") => {
+                            print!("{}", rename.patch(dir).map_err(failure)?)
+                        }
+                        _ => print!("{}", rename.sed_script(dir)),
+                    }
+                }
+                ("annotate", Some(sub)) => {
+                    let source = sub.value_of("source").unwrap_or("");
+                    let dir = source_dir(&cscope, db_dir);
+                    match annotate::Annotation::new(&cscope, source, dir) {
+                        Some(annotation) => print!("{}", annotation),
+                        None => {
+                            return Err(failure(format!("'{}' is not in the database.", source)))
+                        }
+                    }
+                }
+                ("dead", Some(sub)) => {
+                    let excluded: Vec<regex::Regex> = sub
+                        .values_of("exclude")
+                        .into_iter()
+                        .flatten()
+                        .map(regex::Regex::new)
+                        .collect::<Result<_, _>>()
+                        .map_err(failure)?;
+                    let mut dead = report::uncalled_functions(&cscope);
+                    if sub.is_present("exclude-main") {
+                        dead.retain(|s| s.name() != "main");
+                    }
+                    dead.retain(|s| !excluded.iter().any(|r| r.is_match(s.display_name())));
+                    let listing = listing::Listing::new(dead, options);
+                    output.emit(&listing, || vec![listing.table("dead")]);
+                }
+                ("header-functions", _) => {
+                    let report = report::HeaderFunctions::new(&cscope, options);
+                    output.emit(&report, || vec![report.table()]);
+                }
+                ("badge", Some(sub)) => {
+                    let metric = sub
+                        .value_of("metric")
+                        .and_then(badge::Metric::from_name)
+                        .unwrap_or(badge::Metric::Functions);
+                    println!("{}", badge::Badge::new(&cscope, metric).to_json());
+                }
+                ("stats", Some(sub)) => {
+                    if sub.is_present("metrics") {
+                        let order = match sub.value_of("by") {
+                            Some("fan-out") => graph::FanOrder::FanOut,
+                            Some("name") => graph::FanOrder::Name,
+                            _ => graph::FanOrder::FanIn,
+                        };
+                        let graph = graph::CallGraph::new(&cscope, options);
+                        let mut metrics = graph::FanMetrics::new(&graph, order);
+                        if let Some(n) = sub.value_of("top") {
+                            metrics = metrics.top(parse_count(n, "--top") as usize);
+                        }
+                        output.emit(&metrics, || vec![metrics.table()]);
+                    } else if sub.is_present("function-sizes") {
+                        let mut sizes = stats::FunctionSizes::new(&cscope);
+                        if let Some(n) = sub.value_of("longer-than") {
+                            sizes = sizes.longer_than(parse_count(n, "--longer-than"));
+                        }
+                        output.emit(&sizes, || sizes.tables());
+                    } else {
+                        let snapshot = stats::Snapshot::new(&cscope);
+                        if let Some(history) = sub.value_of("record") {
+                            snapshot
+                                .record(Path::new(history))
+                                .map_err(|e| failure(format!("{}: {}", history, e)))?;
+                        }
+                        match sub.value_of("trend") {
+                            // A workbook has room for every record as well.
+                            _ if format == "xlsx" => {
+                                let mut tables = snapshot.tables();
+                                tables.push(listing::symbols_table(&cscope, None));
+                                tables.push(report::Ranking::longest(&cscope).table("longest"));
+                                output.emit(&snapshot, || tables);
+                            }
+                            Some(history) => {
+                                let trend = stats::Trend::load(Path::new(history))
+                                    .map_err(|e| failure(format!("{}: {}", history, e)))?;
+                                print!("{}", trend);
+                            }
+                            None => output.emit(&snapshot, || snapshot.tables()),
+                        }
+                    }
+                }
+                (name @ "longest", Some(sub))
+                | (name @ "most-called", Some(sub))
+                | (name @ "most-included", Some(sub))
+                | (name @ "freq", Some(sub)) => {
+                    let ranking = match name {
+                        "longest" => report::Ranking::longest(&cscope),
+                        "most-called" => report::Ranking::most_called(&cscope),
+                        "most-included" => report::Ranking::most_included(&cscope),
+                        _ => report::Ranking::frequency(&cscope),
+                    };
+                    let top = sub
+                        .value_of("top")
+                        .map(|n| parse_count(n, "--top") as usize);
+                    let min_count = sub
+                        .value_of("min-count")
+                        .map_or(0, |n| parse_count(n, "--min-count"));
+                    let ranking = ranking.limit(top, min_count);
+                    output.emit(&ranking, || vec![ranking.table(name)]);
+                }
+                _ => {
+                    if list_args.is_present("rollup") {
+                        let rollup = report::Rollup::new(&cscope);
+                        output.emit(&rollup, || vec![rollup.table()]);
+                    } else if list_args.is_present("long") {
+                        // With --kind, the rows of those kinds only.
+                        let kinds = kinds.as_deref();
+                        let listing = listing::LongListing::sorted(&cscope, kinds, within, reverse);
+                        output.emit(&listing, || vec![listing.table()]);
+                    } else if let Some(kinds) = kinds.as_ref() {
+                        let listing = listing::KindListing::new(&cscope, kinds, options)
+                            .sorted(within, reverse);
+                        output.emit(&listing, || vec![listing.table()]);
+                    } else {
+                        // The tree has always ended with a blank line.
+                        // As JSON, CSV or TSV every record is wanted, for jq,
+                        // pandas and the like.
+                        let mut tree = cscope::Tree::new(&cscope, options).sorted(within, reverse);
+                        if list_args.is_present("tree-dirs") {
+                            tree = tree.with_dirs();
+                        }
+                        if list_args.is_present("verbose-functions") {
+                            tree = tree.verbose_functions();
+                        }
+                        output.emit(&format!("{}\n", tree), || match format {
+                            "json" | "csv" | "tsv" => vec![listing::symbols_table(&cscope, None)],
+                            _ => vec![tree.table()],
+                        });
+                    }
+                }
+            }
+            print_warnings(&cscope, parse_options);
+            Ok(())
+        }
+        Err(e) => Err(failure(e)),
+    }
+}
+
+// The tree of functions printed a file at a time, as the database is parsed.
+fn stream(
+    args: &ArgMatches,
+    fname: &str,
+    parse_options: cscope::ParseOptions,
+    path_filter: &filter::PathFilter,
+    options: render::RenderOptions,
+) {
+    use std::io::Write;
+    let demangle = args.is_present("demangle");
+    let verbose = args.is_present("verbose-functions");
+    let mut stdout = std::io::stdout().lock();
+    let cscope = cscope::parse_database_streaming(Path::new(fname), parse_options, |file| {
+        if demangle {
+            file.demangle_names();
+        }
+        file.retain_symbols(|s| !path_filter.is_excluded(s.filename()));
+        let mut tree = cscope::Tree::new(file, options.clone());
+        if verbose {
+            tree = tree.verbose_functions();
+        }
+        write!(stdout, "{}", tree)?;
+        Ok(())
+    })
+    .unwrap_or_else(|e| exit_with_error(e));
+    // The tree has always ended with a blank line.
+    writeln!(stdout).unwrap_or_else(|e| exit_with_error(e));
+    print_warnings(&cscope, parse_options);
+}
+
+// Without --lenient only the unknown marks are worth a mention.
+fn warning_text(cscope: &cscope::Cscope, parse_options: cscope::ParseOptions) -> String {
+    let warnings: Vec<warnings::Warning> = cscope
+        .warnings()
+        .iter()
+        .filter(|w| parse_options.lenient || w.kind == warnings::WarningKind::UnknownMark)
+        .copied()
+        .collect();
+    if warnings.is_empty() {
+        return String::new();
+    }
+    warnings::WarningSummary::new(&warnings).to_string()
+}
+
+fn print_warnings(cscope: &cscope::Cscope, parse_options: cscope::ParseOptions) {
+    eprint!("{}", warning_text(cscope, parse_options));
+}
+
+// The queries of cscope's line-oriented interface, by field number.
+const LINE_QUERIES: [(&str, &str); 10] = [
+    ("0", "With -L, find this C symbol."),
+    ("1", "With -L, find this global definition."),
+    ("2", "With -L, find the functions called by this function."),
+    ("3", "With -L, find the functions calling this function."),
+    ("4", "With -L, find this text string."),
+    (
+        "5",
+        "With -L, find this text string (cscope's change, which only searches here).",
+    ),
+    ("6", "With -L, find this egrep pattern."),
+    ("7", "With -L, find this file."),
+    ("8", "With -L, find the files #including this file."),
+    ("9", "With -L, find the assignments to this symbol."),
+];
+
+// cscope's own options, for the editor plugins running `cscope -d -L -1 NAME`.
+fn line_mode_args() -> Vec<Arg<'static, 'static>> {
+    let mut args = vec![
+        Arg::with_name("line-mode")
+            .short("L")
+            .help("Answer one of the queries -0 to -9 in cscope's line-oriented format, \"<file> <function> <line> <text>\"."),
+        Arg::with_name("ignore-case")
+            .short("C")
+            .requires("line-mode")
+            .help("With -L, ignore letter case when matching."),
+    ];
+    // Taken and ignored: code-ls never builds the database, nor reads the
+    // inverted index.
+    for flag in ["d", "k", "q"].iter() {
+        args.push(Arg::with_name(flag).short(flag).hidden(true));
+    }
+    for (field, help) in LINE_QUERIES.iter() {
+        args.push(
+            Arg::with_name(field)
+                .short(field)
+                .value_name("PATTERN")
+                .requires("line-mode")
+                .help(help),
+        );
+    }
+    args
+}
+
+// How the queries match NAME, or the text search its PATTERN.
+fn match_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("ignore-case")
+            .short("i")
+            .long("ignore-case")
+            .help("Ignore letter case when matching."),
+        Arg::with_name("word")
+            .short("w")
+            .long("word")
+            .help("Only match whole words of the text, names always match as a whole."),
+    ]
+}
+
+fn name_match(args: &ArgMatches) -> search::NameMatch {
+    search::NameMatch {
+        ignore_case: args.is_present("ignore-case"),
+        whole_word: args.is_present("word"),
+    }
+}
+
+// The options of the function listing, given with or without `list`.
+fn list_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("long")
+            .short("l")
+            .long("long")
+            .help("List every record with all of its fields in aligned columns, only those of --kind when given."),
+        Arg::with_name("kind")
+            .long("kind")
+            .value_name("KIND")
+            .multiple(true)
+            .number_of_values(1)
+            .help("List the records of this kind (e.g. struct, typedef, macro, global, enum) under each file, may be repeated."),
+        Arg::with_name("stream")
+            .long("stream")
+            .conflicts_with_all(&["long", "kind", "rollup", "sort", "context"])
+            .help("Print the functions of each file as soon as it is parsed, holding one file's records at a time."),
+        Arg::with_name("rollup")
+            .long("rollup")
+            .value_name("BY")
+            .possible_values(&["dir"])
+            .help("Print totals of functions, structs, macros and function lines per directory subtree."),
+        Arg::with_name("reverse")
+            .long("reverse")
+            .requires("sort")
+            .help("Reverse the order given by --sort."),
+        Arg::with_name("tree-dirs")
+            .long("tree-dirs")
+            .conflicts_with_all(&["long", "kind", "rollup", "stream"])
+            .help("Nest the files under their directories (src/ → net/ → file.c) rather than listing their paths."),
+        Arg::with_name("verbose-functions")
+            .long("verbose-functions")
+            .conflicts_with_all(&["long", "kind", "rollup"])
+            .help("Nest the parameters and block-local definitions of each function beneath it."),
+    ]
+}
+
+// A report subcommand, they all take the same options to limit their size.
+fn report_command<'a, 'b>(name: &'a str, about: &'b str) -> App<'a, 'b> {
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .value_name("N")
+                .help("Only show the first N rows."),
+        )
+        .arg(
+            Arg::with_name("min-count")
+                .long("min-count")
+                .value_name("N")
+                .help("Only show rows counting at least N."),
+        )
+}
+
+// The kinds of definition the types subcommand lists.
+const TYPE_KINDS: &[FileMark] = &[
+    FileMark::ClassDefinition,
+    FileMark::StructDefinition,
+    FileMark::UnionDefinition,
+    FileMark::EnumDefinition,
+    FileMark::TypedefDefinition,
+];
+
+// The text of a view, or its tables in one of the other formats.
+struct Output<'a> {
+    format: &'a str,
+    urls: Option<table::UrlTemplate>,
+    cache: Option<cache::Cache>,
+    // What the parse warned of, saved with the output for a cache hit to
+    // print again.
+    warnings: String,
+}
+
+impl<'a> Output<'a> {
+    fn emit(&self, text: &dyn std::fmt::Display, tables: impl FnOnce() -> Vec<table::Table>) {
+        let tables = || -> Vec<table::Table> {
+            match self.urls.as_ref() {
+                Some(urls) => tables().into_iter().map(|t| t.with_urls(urls)).collect(),
+                None => tables(),
+            }
+        };
+        let bytes = match self.format {
+            "xlsx" => xlsx::workbook(&tables()).unwrap_or_else(|e| exit_with_error(e)),
+            "json" => json::tables(&tables()).into_bytes(),
+            "rst" => markup::rst(&tables()).into_bytes(),
+            "asciidoc" => markup::asciidoc(&tables()).into_bytes(),
+            "markdown" => markup::markdown(&tables()).into_bytes(),
+            "csv" => delimited::csv(&tables()).into_bytes(),
+            "tsv" => delimited::tsv(&tables()).into_bytes(),
+            "grep" => grep::lines(&tables()).into_bytes(),
+            _ => text.to_string().into_bytes(),
+        };
+        write_output(self.format, &bytes);
+        // A cache that cannot be written to only makes the next run slower.
+        if let Some(cache) = self.cache.as_ref() {
+            let _ = cache.put(&self.warnings, &bytes);
+        }
+    }
+}
+
+// Write the pages of a report beneath `dir`, making the directories.
+fn write_report(dir: &Path, pages: Vec<(PathBuf, String)>) -> Result<(), Error> {
+    for (path, page) in pages {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, page)?;
+    }
+    Ok(())
+}
+
+// The output goes to stdout, spreadsheets as long as that is not a terminal.
+fn write_output(format: &str, bytes: &[u8]) {
+    use std::io::Write;
+    if format == "xlsx" && terminal_size::terminal_size_of(std::io::stdout()).is_some() {
+        exit_with_error("An xlsx workbook is binary, redirect it to a file.");
+    }
+    std::io::stdout()
+        .write_all(bytes)
+        .unwrap_or_else(|e| exit_with_error(e));
+}
+
+// Where cscope was run, which the paths of the database are relative to,
+// when that is still around, otherwise the directory of the database.
+fn source_dir<'a>(cscope: &'a cscope::Cscope, db_dir: &'a Path) -> &'a Path {
+    match cscope.current_dir() {
+        dir if dir.is_dir() => dir,
+        _ => db_dir,
+    }
+}
+
+// The database in $CSCOPE_DB (a file, or the directory holding cscope.out),
+// otherwise the first cscope.out in the working directory or one of its
+// parents.
+fn find_database() -> Option<PathBuf> {
+    if let Some(db) = std::env::var_os("CSCOPE_DB").filter(|db| !db.is_empty()) {
+        let db = PathBuf::from(db);
+        return Some(if db.is_dir() {
+            db.join("cscope.out")
+        } else {
+            db
+        });
+    }
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join("cscope.out"))
+        .find(|db| db.is_file())
+}
+
+// $COLUMNS when set, otherwise the width of the terminal, or 80 columns.
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .or_else(|| terminal_size::terminal_size().map(|(w, _)| w.0 as usize))
+        .unwrap_or(80)
+}
+
+fn parse_kinds(value: &str) -> cscope::Kinds {
+    let mut kinds = cscope::Kinds::default();
+    for name in value.split(',').map(|n| n.trim()) {
+        match FileMark::from_name(name) {
+            Some(mark) => kinds.insert(mark),
+            None => exit_with_error(format!("--kinds: unknown kind '{}'.", name)),
+        }
+    }
+    kinds
+}
+
+// A kind by its name, or the plural of it (e.g. structs).
+fn parse_kind(name: &str) -> FileMark {
+    FileMark::from_name(name)
+        .or_else(|| name.strip_suffix('s').and_then(FileMark::from_name))
+        .unwrap_or_else(|| exit_with_error(format!("--kind: unknown kind '{}'.", name)))
+}
+
+fn kind_set(marks: &[FileMark]) -> cscope::Kinds {
+    let mut kinds = cscope::Kinds::default();
+    for mark in marks.iter() {
+        kinds.insert(*mark);
+    }
+    kinds
+}
+
+fn parse_count(value: &str, flag: &str) -> u64 {
+    value
+        .parse()
+        .unwrap_or_else(|_| exit_with_error(format!("{} expects a number, not '{}'.", flag, value)))
+}
+
+// An error of a query, for show to return.
+fn failure(e: impl std::fmt::Display) -> Error {
+    Error::other(e.to_string())
+}
+
+fn exit_with_error(e: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {}", e);
+    std::process::exit(1)
+}
+
+// Which source files to leave out, from the flags, configuration and ignore
+// files.
+fn path_filter(args: &ArgMatches, db_dir: &Path) -> Result<filter::PathFilter, Error> {
+    let mut path_filter = filter::PathFilter::default();
+    if args.is_present("no-system") {
+        let extra: Vec<&str> = args
+            .values_of("system-prefix")
+            .map_or(vec![], |v| v.collect());
+        path_filter.exclude_system(&extra);
+    }
+
+    if let Some(globs) = args.values_of("path") {
+        path_filter.include_only(&globs.collect::<Vec<&str>>());
+    }
+
+    if !args.is_present("no-default-excludes") {
+        let config = config::Config::load(db_dir)?;
+        let excludes: Vec<&str> = match config.default_excludes.as_ref() {
+            Some(patterns) => patterns.iter().map(|p| p.as_str()).collect(),
+            None => config::DEFAULT_EXCLUDES.to_vec(),
+        };
+        path_filter.exclude_patterns(&excludes);
+    }
+
+    let mut ignore_files = vec![db_dir.join(filter::IGNORE_FILE)];
+    if args.is_present("gitignore") {
+        ignore_files.push(db_dir.join(".gitignore"));
+    }
+    for path in ignore_files.iter() {
+        path_filter
+            .add_ignore_file(path)
+            .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
     }
+    Ok(path_filter)
 }
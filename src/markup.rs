@@ -0,0 +1,198 @@
+use crate::table::{Cell, Table};
+
+// Source text is full of characters reStructuredText marks up with, e.g. the
+// '*' of a pointer or the '_' of a name.
+fn rst_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '`' | '_' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// reStructuredText list tables, one per table, for Sphinx.
+pub fn rst(tables: &[Table]) -> String {
+    let mut out = String::new();
+    for table in tables.iter() {
+        out.push_str(&format!(
+            ".. list-table:: {}\n   :header-rows: 1\n\n",
+            table.name
+        ));
+        let header: Vec<Cell> = table
+            .header
+            .iter()
+            .map(|h| Cell::from(h.as_str()))
+            .collect();
+        for row in std::iter::once(&header).chain(table.rows.iter()) {
+            for (col, cell) in row.iter().enumerate() {
+                let bullet = if col == 0 { "*" } else { " " };
+                let line = format!("   {} - {}", bullet, rst_escape(&cell.to_string()));
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// AsciiDoc tables, one per table, for Antora.
+pub fn asciidoc(tables: &[Table]) -> String {
+    let mut out = String::new();
+    for table in tables.iter() {
+        out.push_str(&format!(".{}\n[options=\"header\"]\n|===\n", table.name));
+        let header: Vec<String> = table.header.iter().map(|h| format!("|{}", h)).collect();
+        out.push_str(&header.join(" "));
+        out.push('\n');
+        for row in table.rows.iter() {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|c| format!("|{}", c.to_string().replace('|', "\\|")))
+                .collect();
+            out.push_str(&cells.join(" "));
+            out.push('\n');
+        }
+        out.push_str("|===\n\n");
+    }
+    out
+}
+
+// Characters markdown would take for emphasis, code, links, html or the
+// columns of a table.
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '`' | '_' | '|' | '<' | '[') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn markdown_table(out: &mut String, header: &[&String], rows: &[Vec<&Cell>]) {
+    let row_line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    out.push_str(&row_line(
+        header.iter().map(|h| markdown_escape(h)).collect(),
+    ));
+    out.push_str(&row_line(
+        header.iter().map(|_| "---".to_string()).collect(),
+    ));
+    for row in rows.iter() {
+        out.push_str(&row_line(
+            row.iter()
+                .map(|c| markdown_escape(&c.to_string()))
+                .collect(),
+        ));
+    }
+    out.push('\n');
+}
+
+// Markdown tables, for design docs and pull requests.  The rows of a table
+// with a file column are split into a section per file, in the order the
+// files first come, and a section leaves out the columns it has nothing in,
+// such as the demangled names of C functions.
+pub fn markdown(tables: &[Table]) -> String {
+    let mut out = String::new();
+    for table in tables.iter() {
+        out.push_str(&format!("## {}\n\n", markdown_escape(&table.name)));
+        let file = table.header.iter().position(|h| h == "file");
+        let mut sections: Vec<(String, Vec<&Vec<Cell>>)> = vec![];
+        for row in table.rows.iter() {
+            let name = file.map_or(String::new(), |idx| row[idx].to_string());
+            match sections.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, rows)) => rows.push(row),
+                None => sections.push((name, vec![row])),
+            }
+        }
+        if sections.is_empty() {
+            markdown_table(&mut out, &table.header.iter().collect::<Vec<_>>(), &[]);
+        }
+        for (name, rows) in sections.iter() {
+            if file.is_some() {
+                out.push_str(&format!("### {}\n\n", markdown_escape(name)));
+            }
+            let columns: Vec<usize> = (0..table.header.len())
+                .filter(|col| Some(*col) != file)
+                .filter(|col| rows.iter().any(|row| !row[*col].to_string().is_empty()))
+                .collect();
+            let header: Vec<&String> = columns.iter().map(|col| &table.header[*col]).collect();
+            let rows: Vec<Vec<&Cell>> = rows
+                .iter()
+                .map(|row| columns.iter().map(|col| &row[*col]).collect())
+                .collect();
+            markdown_table(&mut out, &header, &rows);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two calls of puts, one of them with markup characters in its text.
+    fn callers() -> Table {
+        let mut table = Table::new("puts", &["file", "function", "demangled", "line", "text"]);
+        for (file, function, line, text) in [
+            ("src/main.c", "main", 4, "puts(*argv);"),
+            ("src/util.c", "log_line", 9, "puts(a|b);"),
+        ] {
+            table.rows.push(vec![
+                Cell::from(file),
+                Cell::from(function),
+                Cell::from(""),
+                Cell::from(line),
+                Cell::from(text),
+            ]);
+        }
+        table
+    }
+
+    #[test]
+    fn rst_list_tables_escape_the_markup() {
+        assert_eq!(
+            rst(&[callers()]),
+            ".. list-table:: puts\n   :header-rows: 1\n\n   \
+             * - file\n     - function\n     - demangled\n     - line\n     - text\n   \
+             * - src/main.c\n     - main\n     -\n     - 4\n     - puts(\\*argv);\n   \
+             * - src/util.c\n     - log\\_line\n     -\n     - 9\n     - puts(a\\|b);\n\n"
+        );
+    }
+
+    #[test]
+    fn asciidoc_tables_escape_the_cell_separator() {
+        assert_eq!(
+            asciidoc(&[callers()]),
+            ".puts\n[options=\"header\"]\n|===\n\
+             |file |function |demangled |line |text\n\
+             |src/main.c |main | |4 |puts(*argv);\n\
+             |src/util.c |log_line | |9 |puts(a\\|b);\n\
+             |===\n\n"
+        );
+    }
+
+    #[test]
+    fn markdown_has_a_section_per_file_without_its_empty_columns() {
+        assert_eq!(
+            markdown(&[callers()]),
+            "## puts\n\n\
+             ### src/main.c\n\n\
+             | function | line | text |\n| --- | --- | --- |\n| main | 4 | puts(\\*argv); |\n\n\
+             ### src/util.c\n\n\
+             | function | line | text |\n| --- | --- | --- |\n| log\\_line | 9 | puts(a\\|b); |\n\n"
+        );
+    }
+
+    #[test]
+    fn markdown_keeps_the_header_of_an_empty_table() {
+        let table = Table::new("totals", &["what", "count"]);
+        assert_eq!(
+            markdown(&[table]),
+            "## totals\n\n| what | count |\n| --- | --- |\n\n"
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::cscope::Symbol;
+use std::io::{BufRead, Error, ErrorKind, IsTerminal, Write};
+use std::path::Path;
+use std::process::Command;
+
+// Pick one of several definitions: the first with `first`, or the one whose
+// number is typed in, like cscope does when a query has more than one hit.
+// Without a terminal to ask on, the first is taken as with `first`.
+pub fn choose<'a>(definitions: &[&'a Symbol], first: bool) -> Result<&'a Symbol, Error> {
+    let stdin = std::io::stdin();
+    let first = first || !stdin.is_terminal();
+    pick(
+        definitions,
+        first,
+        &mut stdin.lock(),
+        &mut std::io::stderr(),
+    )
+}
+
+// Ask on `output` and read the answer from `input`.
+fn pick<'a>(
+    definitions: &[&'a Symbol],
+    first: bool,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> Result<&'a Symbol, Error> {
+    match definitions {
+        [] => return Err(Error::new(ErrorKind::NotFound, "No definition to open.")),
+        [only] => return Ok(only),
+        [first_def, ..] if first => return Ok(first_def),
+        _ => (),
+    }
+    for (i, sym) in definitions.iter().enumerate() {
+        writeln!(
+            output,
+            "{:>3} {} {}:{}: {}",
+            i + 1,
+            sym.mark().name(),
+            sym.filename(),
+            sym.line_number(),
+            sym.line_text()
+        )?;
+    }
+    loop {
+        write!(output, "Open which (1-{}, q to quit)? ", definitions.len())?;
+        output.flush()?;
+        let mut answer = String::new();
+        if input.read_line(&mut answer)? == 0 || answer.trim() == "q" {
+            return Err(Error::new(ErrorKind::Interrupted, "Nothing opened."));
+        }
+        match answer.trim().parse::<usize>() {
+            Ok(n) if n >= 1 && n <= definitions.len() => return Ok(definitions[n - 1]),
+            _ => continue,
+        }
+    }
+}
+
+// Open the file at the line of `sym` in $VISUAL or $EDITOR (vi otherwise),
+// the file names are relative to where cscope was run, `dir`.
+pub fn open(sym: &Symbol, dir: &Path) -> Result<(), Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let path = dir.join(sym.filename());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\" \"$2\"", editor))
+        .arg("sh")
+        .arg(format!("+{}", sym.line_number()))
+        .arg(&path)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::other(format!("{} exited with {}", editor, status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use code_ls::Cscope;
+
+    // draw defined in src/draw.c and again in src/main.c.
+    fn database() -> Vec<u8> {
+        let body = "\t@src/draw.c\n\n\
+                    1 void \n\t$draw\n(void) {\n\n\
+                    2 }\n\n\t}\n\n\
+                    \t@src/main.c\n\n\
+                    1 static void \n\t$draw\n(int n) {\n\n\
+                    2 }\n\n\t}\n\n\
+                    \t@\n";
+        let header_len = "cscope 15 /nonexistent -c 0000000000\n".len();
+        let header = format!(
+            "cscope 15 /nonexistent -c {:010}\n",
+            header_len + body.len()
+        );
+        let mut db = (header + body).into_bytes();
+        db.extend_from_slice(b"1\n.\n0\n0\n1\n11\nsrc/main.c\n");
+        db
+    }
+
+    fn draws(cscope: &Cscope) -> Vec<&Symbol> {
+        cscope
+            .symbols()
+            .iter()
+            .filter(|s| s.display_name() == "draw")
+            .collect()
+    }
+
+    // The file of the definition picked with `answers` typed in, and what was
+    // asked.
+    fn picked(first: bool, answers: &str) -> (Result<String, ErrorKind>, String) {
+        let cscope = code_ls::parse_buffer(&database()).unwrap();
+        let mut asked = Vec::new();
+        let sym = pick(&draws(&cscope), first, &mut answers.as_bytes(), &mut asked);
+        (
+            sym.map(|s| s.filename().to_string()).map_err(|e| e.kind()),
+            String::from_utf8(asked).unwrap(),
+        )
+    }
+
+    #[test]
+    fn the_number_typed_in_picks_the_definition() {
+        let (sym, asked) = picked(false, "2\n");
+        assert_eq!(sym, Ok("src/main.c".to_string()));
+        assert_eq!(
+            asked,
+            "  1 function src/draw.c:1: void draw(void) {\n  \
+             2 function src/main.c:1: static void draw(int n) {\n\
+             Open which (1-2, q to quit)? "
+        );
+    }
+
+    #[test]
+    fn other_answers_are_asked_again() {
+        let (sym, asked) = picked(false, "3\nmain\n\n1\n");
+        assert_eq!(sym, Ok("src/draw.c".to_string()));
+        assert_eq!(asked.matches("Open which").count(), 4);
+    }
+
+    #[test]
+    fn quitting_or_no_answer_opens_nothing() {
+        assert_eq!(picked(false, "q\n").0, Err(ErrorKind::Interrupted));
+        assert_eq!(picked(false, "").0, Err(ErrorKind::Interrupted));
+        assert_eq!(picked(false, "7\n").0, Err(ErrorKind::Interrupted));
+    }
+
+    #[test]
+    fn first_and_single_definitions_are_taken_without_asking() {
+        assert_eq!(
+            picked(true, ""),
+            (Ok("src/draw.c".to_string()), String::new())
+        );
+        let cscope = code_ls::parse_buffer(&database()).unwrap();
+        let one = &draws(&cscope)[1..];
+        let mut asked = Vec::new();
+        let sym = pick(one, false, &mut "1\n".as_bytes(), &mut asked).unwrap();
+        assert_eq!(sym.filename(), "src/main.c");
+        assert!(asked.is_empty());
+        let none = pick(&[], true, &mut "".as_bytes(), &mut asked);
+        assert_eq!(none.unwrap_err().kind(), ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width, RenderOptions};
+use crate::table::{Cell, Table};
+
+// A type definition (class, struct, union or enum) and the records which make
+// up its body.
+pub struct Scope<'a> {
+    pub parent: &'a Symbol,
+    pub members: Vec<&'a Symbol>,
+}
+
+// Whether a record inside a type body declares a member of that type.  Fields
+// carry the member mark, member functions are either defined inline or only
+// declared.
+fn is_member(sym: &Symbol) -> bool {
+    match sym.mark() {
+        FileMark::EnumStructUnionMemberGlobalDefinition | FileMark::FunctionDefinition => true,
+        _ => sym.is_declaration(),
+    }
+}
+
+// A field of a struct or union, or an enumerator.
+fn is_field(sym: &Symbol) -> bool {
+    sym.mark() == FileMark::EnumStructUnionMemberGlobalDefinition
+}
+
+// Records that open a new definition once the body of a type has ended.
+fn is_definition(mark: FileMark) -> bool {
+    matches!(
+        mark,
+        FileMark::FunctionDefinition
+            | FileMark::Define
+            | FileMark::ClassDefinition
+            | FileMark::EnumDefinition
+            | FileMark::OtherGlobalDefinition
+            | FileMark::StructDefinition
+            | FileMark::TypedefDefinition
+            | FileMark::UnionDefinition
+    )
+}
+
+// Collect the members of each definition for which `is_parent` holds.
+//
+// The database does not record where a type body ends: the closing brace line
+// holds no symbols, and the optional ';' end mark is not written by every
+// cscope version.  So a body is taken to run from its definition to the
+// first unindented definition after it (or the end mark).  Fields are
+// members wherever they are, their mark only being given inside a body, but
+// member functions and declarations need to be indented, or on the line of
+// the definition, to tell them from the functions and prototypes following
+// a type.
+pub fn scopes<'a>(cscope: &'a Cscope, is_parent: impl Fn(FileMark) -> bool) -> Vec<Scope<'a>> {
+    let mut scopes: Vec<Scope> = vec![];
+    let mut current: Option<Scope> = None;
+    let mut line: Option<(&str, u64)> = None;
+    let mut indented = false;
+    for sym in cscope.symbols().iter() {
+        if sym.is_end_mark() {
+            continue;
+        }
+        let at = (sym.filename(), sym.line_number());
+        if line != Some(at) {
+            line = Some(at);
+            indented = sym.is_indented();
+        }
+
+        if let Some(scope) = current.as_mut() {
+            let parent = scope.parent;
+            let same_line = parent.line_number() == sym.line_number();
+            let ended = parent.filename() != sym.filename()
+                || sym.mark() == FileMark::EnumStructUnionDefinitonEnd
+                || (!indented && !same_line && is_definition(sym.mark()));
+            if ended {
+                scopes.extend(current.take());
+            } else if !is_parent(sym.mark())
+                && (indented || same_line || is_field(sym))
+                && is_member(sym)
+            {
+                scope.members.push(sym);
+            }
+        }
+
+        if is_parent(sym.mark()) {
+            scopes.extend(current.take());
+            current = Some(Scope {
+                parent: sym,
+                members: vec![],
+            });
+        }
+    }
+    scopes.extend(current);
+    scopes
+}
+
+// Renders definitions in the tree style with their members nested beneath.
+pub struct Outline<'a> {
+    scopes: Vec<Scope<'a>>,
+    options: RenderOptions,
+}
+
+impl<'a> Outline<'a> {
+    pub fn new(scopes: Vec<Scope<'a>>, options: RenderOptions) -> Outline<'a> {
+        Outline { scopes, options }
+    }
+
+    // One row per member, the definitions without any get a row of their own.
+    pub fn table(&self, name: &str) -> Table {
+        let mut table = Table::new(name, &["file", "parent", "member", "text", "line"]);
+        for scope in self.scopes.iter() {
+            let parent = scope.parent;
+            if scope.members.is_empty() {
+                table.rows.push(vec![
+                    Cell::from(parent.filename()),
+                    Cell::from(parent.display_name()),
+                    Cell::from(""),
+                    Cell::from(parent.line_text()),
+                    Cell::from(parent.line_number()),
+                ]);
+            }
+            for member in scope.members.iter() {
+                table.rows.push(vec![
+                    Cell::from(parent.filename()),
+                    Cell::from(parent.display_name()),
+                    Cell::from(member.display_name()),
+                    Cell::from(member.signature().unwrap_or(member.line_text())),
+                    Cell::from(member.line_number()),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for Outline<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut fname: &str = "";
+        let max_len: usize = self
+            .scopes
+            .iter()
+            .flat_map(|s| s.members.iter())
+            .map(|m| width(m.display_name()))
+            .max()
+            .unwrap_or(0);
+        for scope in self.scopes.iter() {
+            if scope.parent.filename() != fname {
+                fname = scope.parent.filename();
+                write!(f, "•{}:\n╰─╮\n", self.options.file(fname))?;
+            }
+            writeln!(
+                f,
+                "  ├ {}, {}",
+                scope.parent.display_name(),
+                self.options.location(scope.parent)
+            )?;
+            for (i, member) in scope.members.iter().enumerate() {
+                let branch = if i + 1 == scope.members.len() {
+                    '╰'
+                } else {
+                    '├'
+                };
+                let text = match member.signature() {
+                    Some(sig) => sig.to_string(),
+                    None => member.line_text().to_string(),
+                };
+                self.options.write_row(
+                    f,
+                    &format!("  │ {}", branch),
+                    &pad(member.display_name(), max_len),
+                    &text,
+                    member,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    #[test]
+    fn members_are_the_fields_and_indented_records_of_a_body() {
+        // The fields of point are not indented, as an indent(1) -i0 source
+        // has them, and a function and a prototype follow the struct.
+        let db = database(
+            "\t@src/main.c\n\n\
+             1 struct \n\tspoint\n {\n\n\
+             2 int \n\tmx\n;\n\n\
+             3 int \n\tmy\n;\n\n\
+             4  void \ndraw\n(void);\n\n\
+             6 int \n\t$norm\n(void) {\n\n\
+             7 }\n\n\t}\n\n\
+             8 void \nfree_point\n(void);\n\n\
+             \t@\n",
+        );
+        let cscope = parse_buffer(&db).unwrap();
+        let types = scopes(&cscope, |m| m == FileMark::StructDefinition);
+        let members: Vec<&str> = types[0].members.iter().map(|m| m.name()).collect();
+        assert_eq!(members, vec!["x", "y", "draw"]);
+    }
+
+    #[test]
+    fn classes_list_their_member_functions_and_fields() {
+        let db = database(
+            "\t@src/shape.cc\n\n\
+             1 class \n\tcshape\n {\n\n\
+             2  int \n\tmsides\n;\n\n\
+             3  double \n\t$area\n() {\n\n\
+             4  }\n\n\t}\n\n\
+             5  void \nscale\n(double);\n\n\
+             7 class \n\tccircle\n : public \nshape\n {\n\n\
+             8  double \n\tmradius\n;\n\n\
+             10 int \n\t$main\n(void) {\n\n\
+             11 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let cscope = parse_buffer(&db).unwrap();
+        let classes = scopes(&cscope, |m| m == FileMark::ClassDefinition);
+        let outline = Outline::new(classes, RenderOptions::default());
+        // main follows the last class unindented, so is not a member of it.
+        assert_eq!(
+            outline.to_string(),
+            "•src/shape.cc:\n╰─╮\n  \
+             ├ shape, line:1\n  \
+             │ ├ sides  int sides;      , line:2\n  \
+             │ ├ area   double area()   , line:3\n  \
+             │ ╰ scale  void scale(double), line:5\n  \
+             ├ circle, line:7\n  \
+             │ ╰ radius double radius;  , line:8\n"
+        );
+        assert_eq!(outline.table("classes").rows.len(), 4);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::cscope::Cscope;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Error;
+use std::path::Path;
+
+// Lines of context around each change of a unified diff.
+const CONTEXT: usize = 3;
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// `line` with every whole word `old` replaced by `new`.
+fn replace_word(line: &str, old: &str, new: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(idx) = rest.find(old) {
+        let before = rest[..idx].chars().next_back();
+        let after = rest[idx + old.len()..].chars().next();
+        out.push_str(&rest[..idx]);
+        if !before.is_some_and(is_ident) && !after.is_some_and(is_ident) {
+            out.push_str(new);
+        } else {
+            out.push_str(old);
+        }
+        rest = &rest[idx + old.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+// Quote for the shell, e.g. it's becomes 'it'\''s'.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+// Every line a record of OLD is on, as the places to rename it.  Only what
+// cscope recorded is touched: mentions in comments and strings are left as
+// they are.
+pub struct Rename {
+    old: String,
+    new: String,
+    sites: Vec<(String, BTreeSet<u64>)>,
+}
+
+impl Rename {
+    pub fn new(cscope: &Cscope, old: &str, new: &str) -> Rename {
+        let mut sites: Vec<(String, BTreeSet<u64>)> = vec![];
+        for sym in cscope.symbols().iter() {
+            if sym.is_end_mark() || sym.name() != old {
+                continue;
+            }
+            if sites.last().map(|s| s.0.as_str()) != Some(sym.filename()) {
+                sites.push((sym.filename().to_string(), BTreeSet::new()));
+            }
+            if let Some((_, lines)) = sites.last_mut() {
+                lines.insert(sym.line_number());
+            }
+        }
+        Rename {
+            old: old.to_string(),
+            new: new.to_string(),
+            sites,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    // A shell script running sed on each file, substituting on the lines with
+    // a record only.  The paths are those of the database, relative to `dir`.
+    pub fn sed_script(&self, dir: &Path) -> String {
+        let replacement = self
+            .new
+            .replace('\\', "\\\\")
+            .replace('/', "\\/")
+            .replace('&', "\\&");
+        let mut out = format!(
+            "#!/bin/sh\n# Rename {} to {}, run from {}\n",
+            self.old,
+            self.new,
+            dir.display()
+        );
+        for (file, lines) in self.sites.iter() {
+            out.push_str("sed -i");
+            for line in lines.iter() {
+                let command = format!("{}s/\\<{}\\>/{}/g", line, self.old, replacement);
+                out.push_str(&format!(" -e {}", shell_quote(&command)));
+            }
+            out.push_str(&format!(" {}\n", shell_quote(file)));
+        }
+        out
+    }
+
+    // A unified diff of the renamed lines, for patch -p1 or git apply.  The
+    // files are read from `dir`.
+    pub fn patch(&self, dir: &Path) -> Result<String, Error> {
+        let mut out = String::new();
+        for (file, lines) in self.sites.iter() {
+            let path = dir.join(file);
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+            // The lines keep a '\r' of theirs, for the patch to apply to files
+            // with CRLF line endings.
+            let old: Vec<&str> = text
+                .split_inclusive('\n')
+                .map(|line| line.strip_suffix('\n').unwrap_or(line))
+                .collect();
+            let no_newline = !text.is_empty() && !text.ends_with('\n');
+            let new: BTreeMap<usize, String> = lines
+                .iter()
+                .filter_map(|n| {
+                    let idx = (*n as usize).checked_sub(1)?;
+                    let renamed = replace_word(old.get(idx)?, &self.old, &self.new);
+                    (renamed != old[idx]).then_some((idx, renamed))
+                })
+                .collect();
+            if new.is_empty() {
+                continue;
+            }
+            let file = file.trim_start_matches("./");
+            out.push_str(&format!("--- a/{}\n+++ b/{}\n", file, file));
+
+            // Changes closer than twice the context share a hunk.
+            let changed: Vec<usize> = new.keys().copied().collect();
+            let mut hunks: Vec<(usize, usize)> = vec![];
+            for idx in changed.iter() {
+                match hunks.last_mut() {
+                    Some(hunk) if *idx <= hunk.1 + 2 * CONTEXT => hunk.1 = *idx,
+                    _ => hunks.push((*idx, *idx)),
+                }
+            }
+            for (first, last) in hunks {
+                let start = first.saturating_sub(CONTEXT);
+                let end = (last + CONTEXT + 1).min(old.len());
+                let len = end - start;
+                out.push_str(&format!(
+                    "@@ -{},{} +{},{} @@\n",
+                    start + 1,
+                    len,
+                    start + 1,
+                    len
+                ));
+                let mut push = |prefix: char, idx: usize, line: &str| {
+                    out.push_str(&format!("{}{}\n", prefix, line));
+                    if no_newline && idx + 1 == old.len() {
+                        out.push_str("\\ No newline at end of file\n");
+                    }
+                };
+                for (idx, line) in old.iter().enumerate().take(end).skip(start) {
+                    match new.get(&idx) {
+                        Some(renamed) => {
+                            push('-', idx, line);
+                            push('+', idx, renamed);
+                        }
+                        None => push(' ', idx, line),
+                    }
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A database of the calls to `old` on each of `lines` of src/a.c.
+    fn database(lines: &[u64]) -> Vec<u8> {
+        let mut body = "\t@src/a.c\n\n".to_string();
+        for line in lines.iter() {
+            body.push_str(&format!("{} \n\t`old\n();\n\n", line));
+        }
+        body.push_str("\t@\n");
+        let header_len = "cscope 15 /nonexistent -c 0000000000\n".len();
+        let mut db = format!(
+            "cscope 15 /nonexistent -c {:010}\n{}",
+            header_len + body.len(),
+            body
+        )
+        .into_bytes();
+        db.extend_from_slice(b"1\n.\n0\n0\n1\n8\nsrc/a.c\n");
+        db
+    }
+
+    // The patch renaming old to new on `lines` of a source of `text`.
+    fn renamed(lines: &[u64], text: &str) -> String {
+        // A directory of its own, the tests run at the same time.
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let run = RUNS.fetch_add(1, Ordering::SeqCst);
+        let dir =
+            std::env::temp_dir().join(format!("code-ls-rename-{}-{}", std::process::id(), run));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/a.c"), text).unwrap();
+        let cscope = parse_buffer(&database(lines)).unwrap();
+        let patch = Rename::new(&cscope, "old", "new").patch(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        patch.unwrap()
+    }
+
+    fn source(lines: usize) -> String {
+        (1..=lines).map(|n| format!("l{}\n", n)).collect()
+    }
+
+    #[test]
+    fn patch_merges_nearby_changes() {
+        let mut text = source(20);
+        text = text.replace("l2\n", "old();\n").replace("l8\n", "old();\n");
+        text = text.replace("l18\n", "old();\n");
+        let patch = renamed(&[2, 8, 18], &text);
+        let headers: Vec<&str> = patch.lines().filter(|l| l.starts_with("@@")).collect();
+        // 2 and 8 are six lines apart and share a hunk, 18 has its own.
+        assert_eq!(
+            headers,
+            vec!["@@ -1,11 +1,11 @@", "@@ -15,6 +15,6 @@"],
+            "{}",
+            patch
+        );
+        assert!(
+            patch.starts_with("--- a/src/a.c\n+++ b/src/a.c\n"),
+            "{}",
+            patch
+        );
+        assert_eq!(patch.matches("-old();\n+new();\n").count(), 3, "{}", patch);
+        assert!(patch.ends_with(" l20\n"), "{}", patch);
+    }
+
+    #[test]
+    fn patch_keeps_line_endings() {
+        let patch = renamed(&[2], "l1\r\nold();\r\nl3\r\nold();");
+        assert_eq!(
+            patch,
+            "--- a/src/a.c\n+++ b/src/a.c\n@@ -1,4 +1,4 @@\n \
+             l1\r\n-old();\r\n+new();\r\n l3\r\n old();\n\
+             \\ No newline at end of file\n"
+        );
+        let patch = renamed(&[2], "l1\nold();");
+        assert!(
+            patch.ends_with(
+                "-old();\n\\ No newline at end of file\n\
+                 +new();\n\\ No newline at end of file\n"
+            ),
+            "{}",
+            patch
+        );
+    }
+}
@@ -0,0 +1,287 @@
+use crate::cscope::Symbol;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
+
+// Wrapped text gets at least this many columns, however narrow the terminal.
+const MIN_WRAP_WIDTH: usize = 20;
+
+// Options shared by the tree style views.
+#[derive(Clone, Default)]
+pub struct RenderOptions {
+    // Follow each line number with the offset of the record in the database.
+    pub offsets: bool,
+    // Wrap the text column to fit this many terminal columns.
+    pub wrap: Option<usize>,
+    // Color the file names, symbol names and locations for a terminal.
+    pub color: bool,
+    // The source lines to show around each record.
+    pub context: Option<Rc<SourceContext>>,
+}
+
+// The lines around each record, read from the source files under the
+// directory cscope was run in.  Files that cannot be read are left out.
+pub struct SourceContext {
+    lines: u64,
+    dir: PathBuf,
+    files: RefCell<HashMap<String, Option<Vec<String>>>>,
+}
+
+impl SourceContext {
+    pub fn new(lines: u64, dir: &Path) -> SourceContext {
+        SourceContext {
+            lines,
+            dir: dir.to_path_buf(),
+            files: RefCell::new(HashMap::new()),
+        }
+    }
+
+    // The lines as grep -C prints them, "<n>:" for the line of the record and
+    // "<n>-" for those around it, beneath the row of the tree.
+    fn write(
+        &self,
+        f: &mut std::fmt::Formatter,
+        carry_on: &str,
+        sym: &Symbol,
+        options: &RenderOptions,
+    ) -> std::fmt::Result {
+        let mut files = self.files.borrow_mut();
+        let source = files.entry(sym.filename().to_string()).or_insert_with(|| {
+            let bytes = std::fs::read(self.dir.join(sym.filename())).ok()?;
+            let text = String::from_utf8_lossy(&bytes);
+            Some(text.lines().map(|l| l.to_string()).collect())
+        });
+        let source = match source {
+            Some(source) if sym.line_number() > 0 => source,
+            _ => return Ok(()),
+        };
+        let line = sym.line_number();
+        let first = line.saturating_sub(self.lines).max(1);
+        let last = (line + self.lines).min(source.len() as u64);
+        let digits = last.to_string().len();
+        for n in first..=last {
+            let text = &source[n as usize - 1];
+            if n == line {
+                writeln!(f, "{}    {:>w$}: {}", carry_on, n, text, w = digits)?;
+            } else {
+                let text = format!("{:>w$}- {}", n, text, w = digits);
+                writeln!(f, "{}    {}", carry_on, options.paint(DIM, &text))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ANSI escapes for the parts of the tree.
+const FILE_COLOR: &str = "\x1b[1;34m";
+const NAME_COLOR: &str = "\x1b[32m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+impl RenderOptions {
+    // The "line:<n>" at the end of a line of the tree.
+    pub fn location(&self, sym: &Symbol) -> String {
+        self.paint(DIM, &self.plain_location(sym))
+    }
+
+    // "line:<n>" for a line that has no record.
+    pub fn line(&self, number: u64) -> String {
+        self.paint(DIM, &format!("line:{}", number))
+    }
+
+    // "line:<n>,<m>" for the lines of several records.
+    pub fn lines(&self, numbers: &[u64]) -> String {
+        let numbers: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+        self.paint(DIM, &format!("line:{}", numbers.join(",")))
+    }
+
+    // A file name heading a part of the tree.
+    pub fn file(&self, name: &str) -> String {
+        self.paint(FILE_COLOR, name)
+    }
+
+    fn plain_location(&self, sym: &Symbol) -> String {
+        format!("line:{}{}", sym.line_number(), self.offset(sym))
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    // One line of the tree: <prefix> <name> <text>, line:<n>
+    // Text that would run past the wrap width carries on over indented
+    // continuation lines, the location follows its last part.
+    pub fn write_row(
+        &self,
+        f: &mut std::fmt::Formatter,
+        prefix: &str,
+        name: &str,
+        text: &str,
+        sym: &Symbol,
+    ) -> std::fmt::Result {
+        // The widths are of the text without the escapes.
+        let location = self.plain_location(sym);
+        let column = width(prefix) + width(name) + 2;
+        let parts = match self.wrap {
+            Some(columns) if column + width(text) + width(&location) + 2 > columns => {
+                let room = columns.saturating_sub(column + width(&location) + 2);
+                wrap(text, room.max(MIN_WRAP_WIDTH))
+            }
+            _ => vec![text.to_string()],
+        };
+        let indent = " ".repeat(width(name) + 4);
+        let location = self.paint(DIM, &location);
+        let name = &self.paint(NAME_COLOR, name);
+
+        // "├" carries on as "│" beneath it, and nothing carries on below "╰".
+        let carry_on: String = prefix
+            .chars()
+            .map(|c| match c {
+                '├' => '│',
+                '╰' => ' ',
+                c => c,
+            })
+            .collect();
+        if parts.len() == 1 {
+            writeln!(f, "{} {} {}, {}", prefix, name, pad(text, 16), location)?;
+        } else {
+            for (i, part) in parts.iter().enumerate() {
+                if i == 0 {
+                    writeln!(f, "{} {} {}", prefix, name, part)?;
+                } else if i + 1 < parts.len() {
+                    writeln!(f, "{}{}{}", carry_on, indent, part)?;
+                } else {
+                    writeln!(f, "{}{}{}, {}", carry_on, indent, part, location)?;
+                }
+            }
+        }
+        match self.context.as_ref() {
+            Some(context) => context.write(f, &carry_on, sym, self),
+            None => Ok(()),
+        }
+    }
+
+    // ", offset:<n>" when offsets were asked for.
+    pub fn offset(&self, sym: &Symbol) -> String {
+        if self.offsets {
+            format!(", offset:{}", sym.offset())
+        } else {
+            String::new()
+        }
+    }
+}
+
+// The number of terminal columns text takes up, wide characters count twice.
+pub fn width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+// Left align text in a column of the given width.
+pub fn pad(text: &str, columns: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(columns.saturating_sub(width(text)))
+    )
+}
+
+// Break text at blanks into lines of at most the given width, a word wider
+// than that gets a line of its own.
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && width(&line) + 1 + width(word) > columns {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::sample;
+
+    // The line of the tree write_row gives for the main of the sample.
+    fn row(options: &RenderOptions, prefix: &str, name: &str, text: &str) -> String {
+        struct Row<'a>(&'a RenderOptions, &'a str, &'a str, &'a str, &'a Symbol);
+        impl std::fmt::Display for Row<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                self.0.write_row(f, self.1, self.2, self.3, self.4)
+            }
+        }
+        let cscope = parse_buffer(&sample()).unwrap();
+        let main = &cscope.symbols()[1];
+        Row(options, prefix, name, text, main).to_string()
+    }
+
+    #[test]
+    fn wide_characters_take_two_columns() {
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("漢字"), 4);
+        assert_eq!(width("🦀"), 2);
+        assert_eq!(pad("漢字", 6), "漢字  ");
+        assert_eq!(pad("🦀x", 5), "🦀x  ");
+        assert_eq!(pad("漢字漢字", 6), "漢字漢字");
+        // The locations line up whatever the text is written in.
+        let options = RenderOptions::default();
+        let rows = [
+            row(&options, "  ├", "main", "/* 漢字 */"),
+            row(&options, "  ├", "main", "/* 🦀🦀 */"),
+            row(&options, "  ├", "main", "/* abcd */"),
+        ];
+        for r in rows.iter() {
+            let at = r.find(", line:3").unwrap();
+            assert_eq!(width(&r[..at]), width("  ├ main ") + 16);
+        }
+    }
+
+    #[test]
+    fn long_text_carries_on_beneath_itself() {
+        let options = RenderOptions {
+            wrap: Some(40),
+            ..RenderOptions::default()
+        };
+        let text = "int main(int argc, char **argv, char **envp) {";
+        // Two columns in from the text, under the tree line going on.
+        assert_eq!(
+            row(&options, "  ├", "main", text),
+            "  ├ main int main(int argc, char\n  \
+             │        **argv, char **envp) {, line:3\n"
+        );
+        assert_eq!(
+            row(&options, "  ╰", "main", text),
+            "  ╰ main int main(int argc, char\n           **argv, char **envp) {, line:3\n"
+        );
+        // Every line fits, but for a word wider than the room left.
+        let text = "a_function_name_far_longer_than_the_room(void)";
+        let wrapped = row(&options, "  ├", "main", &format!("int {} {{", text));
+        let lines: Vec<&str> = wrapped.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "  ├ main int",
+                &format!("  │        {}", text),
+                "  │        {, line:3"
+            ]
+        );
+        // Text that fits is not wrapped.
+        assert_eq!(
+            row(&options, "  ├", "main", "int main() {"),
+            "  ├ main int main() {    , line:3\n"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width, RenderOptions};
+use crate::table::{Cell, Table};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Totals for a directory and everything beneath it.
+#[derive(Default)]
+struct DirectoryTotals {
+    functions: u64,
+    structs: u64,
+    macros: u64,
+    function_lines: u64,
+}
+
+// Per directory subtree counts of the definitions in the database.
+pub struct Rollup {
+    dirs: BTreeMap<String, DirectoryTotals>,
+}
+
+// The directories a file is beneath, outermost first: "src/net/a.c" is in
+// ".", "src" and "src/net".
+fn ancestors(path: &str) -> Vec<String> {
+    let path = path.trim_start_matches("./");
+    let mut dirs: Vec<String> = vec![if path.starts_with('/') { "/" } else { "." }.to_string()];
+    for (idx, _) in path.match_indices('/').filter(|(idx, _)| *idx > 0) {
+        dirs.push(path[..idx].to_string());
+    }
+    dirs
+}
+
+impl Rollup {
+    pub fn new(cscope: &Cscope) -> Rollup {
+        let mut dirs: BTreeMap<String, DirectoryTotals> = BTreeMap::new();
+        for sym in cscope.symbols().iter() {
+            let (functions, structs, macros) = match sym.mark() {
+                FileMark::FunctionDefinition => (1, 0, 0),
+                FileMark::StructDefinition => (0, 1, 0),
+                FileMark::Define => (0, 0, 1),
+                _ => continue,
+            };
+            for dir in ancestors(sym.filename()) {
+                let totals = dirs.entry(dir).or_default();
+                totals.functions += functions;
+                totals.structs += structs;
+                totals.macros += macros;
+                totals.function_lines += sym.function_lines().unwrap_or(0);
+            }
+        }
+        Rollup { dirs }
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "rollup",
+            &[
+                "directory",
+                "functions",
+                "structs",
+                "macros",
+                "function lines",
+            ],
+        );
+        for (dir, totals) in self.dirs.iter() {
+            table.rows.push(vec![
+                Cell::from(dir.as_str()),
+                Cell::from(totals.functions),
+                Cell::from(totals.structs),
+                Cell::from(totals.macros),
+                Cell::from(totals.function_lines),
+            ]);
+        }
+        table
+    }
+}
+
+impl std::fmt::Display for Rollup {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let len = self.dirs.keys().map(|d| width(d)).max().unwrap_or(0).max(9);
+        writeln!(
+            f,
+            "{:<len$} {:>9} {:>9} {:>9} {:>14}",
+            "directory",
+            "functions",
+            "structs",
+            "macros",
+            "function lines",
+            len = len
+        )?;
+        for (dir, totals) in self.dirs.iter() {
+            writeln!(
+                f,
+                "{} {:>9} {:>9} {:>9} {:>14}",
+                pad(dir, len),
+                totals.functions,
+                totals.structs,
+                totals.macros,
+                totals.function_lines
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// A report of counted items, largest first.
+pub struct Ranking {
+    heading: &'static str,
+    rows: Vec<(String, u64)>,
+}
+
+impl Ranking {
+    // Order by count, then by name so the output is stable.
+    fn new(heading: &'static str, mut rows: Vec<(String, u64)>) -> Ranking {
+        rows.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ranking { heading, rows }
+    }
+
+    fn from_counts(heading: &'static str, counts: HashMap<String, u64>) -> Ranking {
+        Ranking::new(heading, counts.into_iter().collect())
+    }
+
+    // Functions by the number of lines they span.
+    pub fn longest(cscope: &Cscope) -> Ranking {
+        let rows = cscope
+            .symbols()
+            .iter()
+            .filter_map(|s| {
+                let lines = s.function_lines()?;
+                let label = format!("{} {}:{}", s.display_name(), s.filename(), s.line_number());
+                Some((label, lines))
+            })
+            .collect();
+        Ranking::new("lines", rows)
+    }
+
+    // Functions by the number of places they are called from.
+    pub fn most_called(cscope: &Cscope) -> Ranking {
+        Ranking::from_counts("calls", count_names(cscope, FileMark::FunctionCall))
+    }
+
+    // Headers by the number of times they are #included.
+    pub fn most_included(cscope: &Cscope) -> Ranking {
+        Ranking::from_counts("includes", count_names(cscope, FileMark::IncludeDirective))
+    }
+
+    // Names by the number of records, of any kind, they appear in.
+    pub fn frequency(cscope: &Cscope) -> Ranking {
+        let mut counts: HashMap<String, u64> = HashMap::new();
+        for sym in cscope.symbols().iter().filter(|s| !s.is_end_mark()) {
+            *counts.entry(sym.display_name().to_string()).or_default() += 1;
+        }
+        Ranking::from_counts("records", counts)
+    }
+
+    pub fn table(&self, name: &str) -> Table {
+        let mut table = Table::new(name, &[self.heading, "name"]);
+        for (label, count) in self.rows.iter() {
+            table
+                .rows
+                .push(vec![Cell::from(*count), Cell::from(label.as_str())]);
+        }
+        table
+    }
+
+    // Keep the rows counting at least `min_count`, at most `top` of them.
+    pub fn limit(mut self, top: Option<usize>, min_count: u64) -> Ranking {
+        self.rows.retain(|r| r.1 >= min_count);
+        if let Some(top) = top {
+            self.rows.truncate(top);
+        }
+        self
+    }
+}
+
+// The function definitions whose name is never called or otherwise referred
+// to, e.g. through a function pointer.  main is called from outside.
+pub fn dead_functions(cscope: &Cscope) -> Vec<&Symbol> {
+    let mut dead = uncalled_functions(cscope);
+    dead.retain(|s| s.name() != "main");
+    dead
+}
+
+// The function definitions whose name no call or other reference has,
+// main among them.  A prototype declares the function rather than using it.
+pub fn uncalled_functions(cscope: &Cscope) -> Vec<&Symbol> {
+    let used: HashSet<&str> = cscope
+        .symbols()
+        .iter()
+        .filter(|s| match s.mark() {
+            FileMark::FunctionCall => true,
+            FileMark::Reference => !s.is_declaration(),
+            _ => false,
+        })
+        .map(|s| s.name())
+        .collect();
+    cscope
+        .symbols()
+        .iter()
+        .filter(|s| s.mark() == FileMark::FunctionDefinition)
+        .filter(|s| !used.contains(s.name()))
+        .collect()
+}
+
+// How many records of the kind `mark` each name has.  The include directive
+// records hold the opening delimiter of the header name, <stdio.h or "foo.h.
+fn count_names(cscope: &Cscope, mark: FileMark) -> HashMap<String, u64> {
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for sym in cscope.symbols().iter().filter(|s| s.mark() == mark) {
+        let name = sym.display_name().trim_start_matches(&['<', '"'][..]);
+        *counts.entry(name.to_string()).or_default() += 1;
+    }
+    counts
+}
+
+impl std::fmt::Display for Ranking {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let len = self.heading.len().max(
+            self.rows
+                .iter()
+                .map(|r| r.1.to_string().len())
+                .max()
+                .unwrap_or(0),
+        );
+        writeln!(f, "{:>len$} name", self.heading, len = len)?;
+        for (name, count) in self.rows.iter() {
+            writeln!(f, "{:>len$} {}", count, name, len = len)?;
+        }
+        Ok(())
+    }
+}
+
+// Whether a file is a header by its extension.
+fn is_header(path: &str) -> bool {
+    let ext = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    matches!(ext, "h" | "hh" | "hpp" | "hxx" | "h++" | "inl" | "ipp")
+}
+
+// The functions defined in headers, for auditing inline helpers which are
+// compiled into every file that includes them.
+pub struct HeaderFunctions<'a> {
+    headers: Vec<(&'a str, Vec<&'a Symbol>)>,
+    in_sources: usize,
+    options: RenderOptions,
+}
+
+impl<'a> HeaderFunctions<'a> {
+    pub fn new(cscope: &'a Cscope, options: RenderOptions) -> HeaderFunctions<'a> {
+        let mut headers: Vec<(&str, Vec<&Symbol>)> = vec![];
+        let mut in_sources = 0;
+        for sym in cscope.symbols().iter() {
+            if sym.mark() != FileMark::FunctionDefinition {
+                continue;
+            }
+            if !is_header(sym.filename()) {
+                in_sources += 1;
+                continue;
+            }
+            if headers.last().map(|h| h.0) != Some(sym.filename()) {
+                headers.push((sym.filename(), vec![]));
+            }
+            if let Some((_, functions)) = headers.last_mut() {
+                functions.push(sym);
+            }
+        }
+        HeaderFunctions {
+            headers,
+            in_sources,
+            options,
+        }
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "header-functions",
+            &["file", "name", "demangled", "inline", "signature", "line"],
+        );
+        for (file, functions) in self.headers.iter() {
+            for sym in functions.iter() {
+                table.rows.push(vec![
+                    Cell::from(*file),
+                    Cell::from(sym.name()),
+                    Cell::from(sym.demangled().unwrap_or("")),
+                    Cell::from(if is_inline(sym) { "yes" } else { "no" }),
+                    Cell::from(sym.signature().unwrap_or("")),
+                    Cell::from(sym.line_number()),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+fn is_inline(sym: &Symbol) -> bool {
+    let text = sym.signature().unwrap_or(sym.text_before());
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|word| word == "inline" || word == "__inline" || word == "__inline__")
+}
+
+impl<'a> std::fmt::Display for HeaderFunctions<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let max_len = self
+            .headers
+            .iter()
+            .flat_map(|h| h.1.iter())
+            .map(|s| width(s.display_name()))
+            .max()
+            .unwrap_or(0);
+        for (file, functions) in self.headers.iter() {
+            let inline = functions.iter().filter(|s| is_inline(s)).count();
+            write!(
+                f,
+                "•{}: ({} fns, {} inline)\n╰─╮\n",
+                self.options.file(file),
+                functions.len(),
+                inline
+            )?;
+            for sym in functions.iter() {
+                self.options.write_row(
+                    f,
+                    "  ├",
+                    &pad(sym.display_name(), max_len),
+                    sym.signature().unwrap_or(""),
+                    sym,
+                )?;
+            }
+        }
+        let in_headers: usize = self.headers.iter().map(|h| h.1.len()).sum();
+        writeln!(
+            f,
+            "{} functions in {} headers, {} in sources",
+            in_headers,
+            self.headers.len(),
+            self.in_sources
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    #[test]
+    fn prototypes_do_not_keep_functions_alive() {
+        let db = database(
+            "\t@src/main.h\n\n\
+             1 int \n\tunused\n(void);\n\n\
+             \t@src/main.c\n\n\
+             2 int \n\t$unused\n(void) {\n\n\
+             3 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let cscope = parse_buffer(&db).unwrap();
+        let dead: Vec<&str> = uncalled_functions(&cscope)
+            .iter()
+            .map(|s| s.name())
+            .collect();
+        assert_eq!(dead, vec!["unused"]);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::report::uncalled_functions;
+use crate::warnings::WarningKind;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+// The base the source files are relative to, resolved through the
+// originalUriBaseIds of the run.
+const SRCROOT: &str = "SRCROOT";
+
+// The rules of the results, the id with its short description.
+const RULES: &[(&str, &str)] = &[
+    (
+        "dead-function",
+        "Function defined but never called or referred to",
+    ),
+    (
+        "unknown-mark",
+        "Record with a mark cscope does not document",
+    ),
+    ("skipped-record", "Record the parser skipped"),
+    ("invalid-utf8", "Text that is not valid UTF-8"),
+    ("invalid-trailer", "Trailer that could not be read"),
+    ("truncated", "Database cut short"),
+];
+
+fn rule(kind: WarningKind) -> &'static str {
+    match kind {
+        WarningKind::UnknownMark => "unknown-mark",
+        WarningKind::SkippedRecord => "skipped-record",
+        WarningKind::InvalidUtf8 => "invalid-utf8",
+        WarningKind::InvalidTrailer => "invalid-trailer",
+        WarningKind::Truncated => "truncated",
+    }
+}
+
+// The kind of logical location a definition is, of those SARIF has.
+fn logical_kind(mark: FileMark) -> &'static str {
+    match mark {
+        FileMark::FunctionDefinition => "function",
+        FileMark::Define => "declaration",
+        FileMark::EnumStructUnionMemberGlobalDefinition => "member",
+        FileMark::OtherGlobalDefinition => "variable",
+        _ => "type",
+    }
+}
+
+// A path as the relative reference of a URI, with what is not unreserved
+// percent encoded.
+fn uri_reference(path: &str) -> String {
+    let mut uri = String::new();
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+fn artifact(path: &str) -> Value {
+    match path.strip_prefix('/') {
+        Some(_) => json!({ "uri": format!("file://{}", uri_reference(path)) }),
+        None => json!({ "uri": uri_reference(path), "uriBaseId": SRCROOT }),
+    }
+}
+
+fn source_location(sym: &Symbol) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": artifact(sym.filename()),
+            "region": {
+                "startLine": sym.line_number(),
+                "snippet": { "text": sym.line_text() },
+            },
+        },
+    })
+}
+
+// A SARIF 2.1.0 log of the database, for the code review tools that show
+// SARIF results: the definitions as the logical locations of the run, and a
+// result for each function nothing calls, each record with an unknown mark,
+// and each other warning of the parser, the last placed at its byte offset
+// in `db`.  `dir` is where the relative paths of the database are from.
+pub fn log(cscope: &Cscope, db: &Path, dir: &Path) -> String {
+    let definitions: Vec<&Symbol> = cscope
+        .symbols()
+        .iter()
+        .filter(|s| s.mark().is_outer_definition())
+        .collect();
+    let index: HashMap<*const Symbol, usize> = definitions
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (*s as *const Symbol, i))
+        .collect();
+    let logical_locations: Vec<Value> = definitions
+        .iter()
+        .map(|s| json!({ "name": s.display_name(), "kind": logical_kind(s.mark()) }))
+        .collect();
+
+    let mut results = vec![];
+    for sym in uncalled_functions(cscope) {
+        let mut location = source_location(sym);
+        if let Some(i) = index.get(&(sym as *const Symbol)) {
+            location["logicalLocations"] = json!([{ "index": i, "name": sym.display_name() }]);
+        }
+        results.push(json!({
+            "ruleId": "dead-function",
+            "level": "note",
+            "message": {
+                "text": format!("{} is defined but never called or referred to.", sym.display_name()),
+            },
+            "locations": [location],
+        }));
+    }
+    for sym in cscope
+        .symbols()
+        .iter()
+        .filter(|s| s.mark() == FileMark::WTF)
+    {
+        results.push(json!({
+            "ruleId": "unknown-mark",
+            "level": "warning",
+            "message": {
+                "text": format!("The record of {} has a mark cscope does not document.", sym.display_name()),
+            },
+            "locations": [source_location(sym)],
+        }));
+    }
+    let db = std::fs::canonicalize(db).unwrap_or_else(|_| db.to_path_buf());
+    for warning in cscope.warnings().iter() {
+        if warning.kind == WarningKind::UnknownMark {
+            continue;
+        }
+        results.push(json!({
+            "ruleId": rule(warning.kind),
+            "level": "warning",
+            "message": { "text": format!("{} in the database.", warning.kind.name()) },
+            "locations": [{
+                "physicalLocation": {
+                    "artifactLocation": artifact(&db.to_string_lossy()),
+                    "region": { "byteOffset": warning.offset },
+                },
+            }],
+        }));
+    }
+    let dir = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let mut root = format!("file://{}", uri_reference(&dir.to_string_lossy()));
+    if !root.ends_with('/') {
+        root.push('/');
+    }
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|(id, text)| json!({ "id": id, "shortDescription": { "text": text } }))
+        .collect();
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "code-ls",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "originalUriBaseIds": { SRCROOT: { "uri": root } },
+            "logicalLocations": logical_locations,
+            "results": results,
+        }],
+    });
+    let mut text = serde_json::to_string_pretty(&log).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    #[test]
+    fn results_point_at_the_dead_functions_and_unknown_marks() {
+        let db = database(
+            "\t@src/my file.c\n\n\
+             1 int \n\t$main\n(void) {\n\n\
+             2 }\n\n\t}\n\n\
+             3 static int \n\t$helper\n(void) {\n\n\
+             4 \n\tQodd\n;\n\n\
+             5 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let cscope = parse_buffer(&db).unwrap();
+        let log: Value =
+            serde_json::from_str(&log(&cscope, Path::new("cscope.out"), Path::new("/src")))
+                .unwrap();
+        let run = &log["runs"][0];
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(run["originalUriBaseIds"][SRCROOT]["uri"], "file:///src/");
+
+        let results = run["results"].as_array().unwrap();
+        let rules: Vec<&str> = results
+            .iter()
+            .map(|r| r["ruleId"].as_str().unwrap())
+            .collect();
+        // Nothing in the database calls main either.
+        assert_eq!(
+            rules,
+            vec!["dead-function", "dead-function", "unknown-mark"]
+        );
+        let declared: Vec<&Value> = run["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| &r["id"])
+            .collect();
+        assert!(rules.iter().all(|r| declared.contains(&&Value::from(*r))));
+
+        let helper = &results[1]["locations"][0];
+        let physical = &helper["physicalLocation"];
+        assert_eq!(physical["artifactLocation"]["uri"], "src/my%20file.c");
+        assert_eq!(physical["artifactLocation"]["uriBaseId"], SRCROOT);
+        assert_eq!(physical["region"]["startLine"], 3);
+        assert_eq!(
+            physical["region"]["snippet"]["text"],
+            "static int helper(void) {"
+        );
+        let index = helper["logicalLocations"][0]["index"].as_u64().unwrap() as usize;
+        assert_eq!(run["logicalLocations"][index]["name"], "helper");
+        assert_eq!(run["logicalLocations"][index]["kind"], "function");
+
+        let unknown = &results[2]["locations"][0]["physicalLocation"];
+        assert_eq!(unknown["region"]["startLine"], 4);
+        assert!(results[2]["message"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("odd"));
+    }
+
+    #[test]
+    fn absolute_paths_are_file_uris() {
+        assert_eq!(
+            artifact("/usr/include/stdio.h"),
+            json!({ "uri": "file:///usr/include/stdio.h" })
+        );
+        assert_eq!(uri_reference("a#b?c%.c"), "a%23b%3Fc%25.c");
+    }
+}
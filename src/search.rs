@@ -0,0 +1,474 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width, RenderOptions};
+use crate::table::{Cell, Table};
+use regex::{Regex, RegexBuilder};
+use std::path::Path;
+
+// How the NAME or PATTERN given to a query is compared: the names of
+// records always match as a whole, optionally ignoring case, and text
+// patterns anywhere in a line unless `whole_word` keeps them to words.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NameMatch {
+    pub ignore_case: bool,
+    pub whole_word: bool,
+}
+
+impl NameMatch {
+    pub fn is_match(&self, name: &str, wanted: &str) -> bool {
+        if self.ignore_case {
+            name.to_lowercase() == wanted.to_lowercase()
+        } else {
+            name == wanted
+        }
+    }
+
+    // The regular expression for a text pattern.
+    pub fn regex(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let pattern = if self.whole_word {
+            format!(r"\b(?:{})\b", pattern)
+        } else {
+            pattern.to_string()
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+    }
+}
+
+// How well a name matches the pattern, better matches are larger.
+fn match_quality(name: &str, pattern: &str) -> Option<i64> {
+    let (folded, folded_pattern) = (name.to_lowercase(), pattern.to_lowercase());
+    if name == pattern {
+        Some(4)
+    } else if folded == folded_pattern {
+        Some(3)
+    } else if folded.starts_with(&folded_pattern) {
+        Some(2)
+    } else if folded.contains(&folded_pattern) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+// How well a name matches the pattern typed as an abbreviation of it: the
+// characters of the pattern appear in order, ignoring case.  A character
+// following the one matched before it scores most, then one starting a word
+// (after a separator or a lowercase letter, as in camelCase), and each run
+// of characters skipped over costs a point.  An empty pattern matches no
+// name.
+pub fn fuzzy_quality(name: &str, pattern: &str) -> Option<i64> {
+    if pattern.is_empty() {
+        return None;
+    }
+    let name: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    let mut at = 0;
+    let mut last: Option<usize> = None;
+    for p in pattern.chars().map(|c| c.to_ascii_lowercase()) {
+        let idx = at
+            + name[at..]
+                .iter()
+                .position(|c| c.to_ascii_lowercase() == p)?;
+        let word_start = idx == 0
+            || !name[idx - 1].is_alphanumeric()
+            || (name[idx - 1].is_lowercase() && name[idx].is_uppercase());
+        score += match last {
+            Some(last) if last + 1 == idx => 4,
+            _ if word_start => 3,
+            _ => 1,
+        };
+        if idx > at {
+            score -= 1;
+        }
+        last = Some(idx);
+        at = idx + 1;
+    }
+    Some(score)
+}
+
+// Definitions before declarations, then calls, then any other use.
+fn kind_priority(sym: &Symbol) -> i64 {
+    match sym.mark() {
+        FileMark::FunctionDefinition
+        | FileMark::Define
+        | FileMark::ClassDefinition
+        | FileMark::EnumDefinition
+        | FileMark::OtherGlobalDefinition
+        | FileMark::EnumStructUnionMemberGlobalDefinition
+        | FileMark::StructDefinition
+        | FileMark::TypedefDefinition
+        | FileMark::UnionDefinition => 3,
+        FileMark::FunctionCall => 1,
+        _ if sym.is_declaration() => 2,
+        _ => 0,
+    }
+}
+
+// A record whose name matches, scored by the quality of the match, the kind
+// of record and how deep in the tree its file is.
+pub struct Match<'a> {
+    pub symbol: &'a Symbol,
+    pub score: i64,
+}
+
+pub struct Search<'a> {
+    pattern: String,
+    matches: Vec<Match<'a>>,
+    ranked: bool,
+    options: RenderOptions,
+}
+
+impl<'a> Search<'a> {
+    // The records whose name contains the pattern, ignoring case, in the
+    // order of the database.
+    pub fn new(cscope: &'a Cscope, pattern: &str, options: RenderOptions) -> Search<'a> {
+        Search::scored(cscope, pattern, match_quality, options)
+    }
+
+    // The records whose name has the characters of the pattern in order, as
+    // fzf matches them, best first.
+    pub fn fuzzy(cscope: &'a Cscope, pattern: &str, options: RenderOptions) -> Search<'a> {
+        Search::scored(cscope, pattern, fuzzy_quality, options).rank()
+    }
+
+    fn scored(
+        cscope: &'a Cscope,
+        pattern: &str,
+        quality: fn(&str, &str) -> Option<i64>,
+        options: RenderOptions,
+    ) -> Search<'a> {
+        let matches = cscope
+            .symbols()
+            .iter()
+            .filter(|s| !s.is_end_mark())
+            .filter_map(|s| {
+                let quality = quality(s.display_name(), pattern)?;
+                let depth = s.filename().trim_start_matches("./").matches('/').count();
+                let score = quality * 100 + kind_priority(s) * 10 - depth.min(9) as i64;
+                Some(Match { symbol: s, score })
+            })
+            .collect();
+        Search {
+            pattern: pattern.to_string(),
+            matches,
+            ranked: false,
+            options,
+        }
+    }
+
+    // The most likely intended records first, ties keep the database order.
+    pub fn rank(mut self) -> Search<'a> {
+        self.matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+        self.ranked = true;
+        self
+    }
+
+    // The matching definitions, exact matches alone when there are any.
+    pub fn definitions(&self) -> Vec<&'a Symbol> {
+        let definitions: Vec<&Match> = self
+            .matches
+            .iter()
+            .filter(|m| kind_priority(m.symbol) == 3)
+            .collect();
+        let exact = |m: &&&Match| m.symbol.display_name().eq_ignore_ascii_case(&self.pattern);
+        if definitions.iter().any(|m| exact(&m)) {
+            definitions.iter().filter(exact).map(|m| m.symbol).collect()
+        } else {
+            definitions.iter().map(|m| m.symbol).collect()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new(
+            "search",
+            &["score", "kind", "file", "line", "name", "demangled", "text"],
+        );
+        for m in self.matches.iter() {
+            let sym = m.symbol;
+            table.rows.push(vec![
+                Cell::from(m.score.max(0) as u64),
+                Cell::from(sym.mark().name()),
+                Cell::from(sym.filename()),
+                Cell::from(sym.line_number()),
+                Cell::from(sym.name()),
+                Cell::from(sym.demangled().unwrap_or("")),
+                Cell::from(sym.line_text()),
+            ]);
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for Search<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "•{}: {} matches\n╰─╮\n",
+            self.pattern,
+            self.matches.len()
+        )?;
+        let len = self
+            .matches
+            .iter()
+            .map(|m| width(m.symbol.display_name()))
+            .max()
+            .unwrap_or(0);
+        let kind_len = self
+            .matches
+            .iter()
+            .map(|m| m.symbol.mark().name().len())
+            .max()
+            .unwrap_or(0);
+        for m in self.matches.iter() {
+            let sym = m.symbol;
+            let score = if self.ranked {
+                format!(", score:{}", m.score)
+            } else {
+                String::new()
+            };
+            writeln!(
+                f,
+                "  ├ {} {:<kind_len$} {}:{}{}{}",
+                pad(sym.display_name(), len),
+                sym.mark().name(),
+                sym.filename(),
+                sym.line_number(),
+                self.options.offset(sym),
+                score,
+                kind_len = kind_len
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The lines of source text matching a regular expression, as cscope's "Find
+// this text string" does.
+pub struct TextSearch<'a> {
+    // The file, the line number and the text of each matching line.
+    matches: Vec<(&'a str, u64, String)>,
+    options: RenderOptions,
+}
+
+impl<'a> TextSearch<'a> {
+    // The lines the database holds, those with a symbol on them.
+    pub fn new(cscope: &'a Cscope, pattern: &Regex, options: RenderOptions) -> TextSearch<'a> {
+        let mut matches = vec![];
+        let mut last: Option<(&str, u64)> = None;
+        for sym in cscope.symbols().iter() {
+            let line = (sym.filename(), sym.line_number());
+            if last == Some(line) {
+                continue;
+            }
+            last = Some(line);
+            if pattern.is_match(sym.line_text()) {
+                matches.push((line.0, line.1, sym.line_text().to_string()));
+            }
+        }
+        TextSearch { matches, options }
+    }
+
+    // Every line of the source files under `dir`, where cscope was run.
+    // Files that cannot be read there are searched in the database's copy of
+    // their lines.
+    pub fn in_sources(
+        cscope: &'a Cscope,
+        pattern: &Regex,
+        dir: &Path,
+        options: RenderOptions,
+    ) -> TextSearch<'a> {
+        let from_database = TextSearch::new(cscope, pattern, options);
+        let mut files: Vec<&str> = cscope.symbols().iter().map(|s| s.filename()).collect();
+        files.dedup();
+        let mut matches = vec![];
+        for file in files {
+            let source = match std::fs::read(dir.join(file)) {
+                Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Err(_) => {
+                    let stored = from_database.matches.iter().filter(|m| m.0 == file);
+                    matches.extend(stored.cloned());
+                    continue;
+                }
+            };
+            for (idx, line) in source.lines().enumerate() {
+                if pattern.is_match(line) {
+                    matches.push((file, idx as u64 + 1, line.trim().to_string()));
+                }
+            }
+        }
+        TextSearch {
+            matches,
+            ..from_database
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    // The file, the line number and the text of each matching line.
+    pub fn lines(&self) -> &[(&'a str, u64, String)] {
+        &self.matches
+    }
+
+    pub fn table(&self) -> Table {
+        let mut table = Table::new("text", &["file", "line", "text"]);
+        for (file, line, text) in self.matches.iter() {
+            table.rows.push(vec![
+                Cell::from(*file),
+                Cell::from(*line),
+                Cell::from(text.as_str()),
+            ]);
+        }
+        table
+    }
+}
+
+impl<'a> std::fmt::Display for TextSearch<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut heading: &str = "";
+        for (file, line, text) in self.matches.iter() {
+            if *file != heading {
+                heading = file;
+                write!(f, "•{}:\n╰─╮\n", self.options.file(heading))?;
+            }
+            writeln!(f, "  ├ {}, {}", text, self.options.line(*line))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    // The names in the order --fuzzy ranks them for `pattern`.
+    fn ranked<'n>(names: &[&'n str], pattern: &str) -> Vec<&'n str> {
+        let mut scored: Vec<(i64, &str)> = names
+            .iter()
+            .filter_map(|n| Some((fuzzy_quality(n, pattern)?, *n)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().map(|(_, n)| n).collect()
+    }
+
+    #[test]
+    fn fuzzy_matches_need_every_character_in_order() {
+        assert!(fuzzy_quality("make_point", "mkpt").is_some());
+        assert!(fuzzy_quality("make_point", "MKPT").is_some());
+        assert_eq!(fuzzy_quality("make_point", "tpkm"), None);
+        assert_eq!(fuzzy_quality("make", "makes"), None);
+        assert_eq!(fuzzy_quality("make_point", ""), None);
+    }
+
+    #[test]
+    fn fuzzy_ranks_word_starts_above_the_middle_of_words() {
+        assert_eq!(
+            ranked(&["fabric", "foo_bar"], "fb"),
+            vec!["foo_bar", "fabric"]
+        );
+        assert_eq!(
+            ranked(&["strip", "str_parse"], "sp"),
+            vec!["str_parse", "strip"]
+        );
+    }
+
+    #[test]
+    fn fuzzy_ranks_camel_case_humps_as_word_starts() {
+        assert_eq!(
+            ranked(&["grow", "getWidget"], "gw"),
+            vec!["getWidget", "grow"]
+        );
+        assert_eq!(
+            ranked(&["newurl", "newURL"], "nu"),
+            vec!["newURL", "newurl"]
+        );
+    }
+
+    #[test]
+    fn fuzzy_ranks_consecutive_characters_first() {
+        assert_eq!(
+            ranked(&["do_read_all_w", "draw_line", "redraw"], "draw"),
+            vec!["draw_line", "redraw", "do_read_all_w"]
+        );
+    }
+
+    // point defined in a header a directory down and used in main.c, with a
+    // few other names holding it.
+    fn points() -> Vec<u8> {
+        database(
+            "\t@src/main.c\n\n\
+             1 int \n\t$main\n(void) {\n\n\
+             2 \n\t`make_point\n();\n\n\
+             3 \n\t`Point\n();\n\n\
+             4 \npoint\n;\n\n\
+             5 }\n\n\t}\n\n\
+             \t@include/geo/point.h\n\n\
+             1 struct \n\tspoint\n {\n\n\
+             \t@src/make.c\n\n\
+             1 int \n\t$make_point\n(void) {\n\n\
+             2 }\n\n\t}\n\n\
+             \t@\n",
+        )
+    }
+
+    fn found(search: &Search) -> Vec<(String, u64)> {
+        search
+            .matches
+            .iter()
+            .map(|m| (m.symbol.display_name().to_string(), m.symbol.line_number()))
+            .collect()
+    }
+
+    #[test]
+    fn search_finds_the_names_holding_the_pattern_in_database_order() {
+        let cscope = parse_buffer(&points()).unwrap();
+        let search = Search::new(&cscope, "POINT", RenderOptions::default());
+        assert_eq!(
+            found(&search),
+            vec![
+                ("make_point".to_string(), 2),
+                ("Point".to_string(), 3),
+                ("point".to_string(), 4),
+                ("point".to_string(), 1),
+                ("make_point".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn rank_puts_exact_names_and_definitions_first() {
+        let cscope = parse_buffer(&points()).unwrap();
+        let search = Search::new(&cscope, "point", RenderOptions::default()).rank();
+        // The definition of point beats its use despite its deeper file,
+        // and Point only matches ignoring case.  Of the prefix matches the
+        // definition comes before the call.
+        assert_eq!(
+            found(&search),
+            vec![
+                ("point".to_string(), 1),
+                ("point".to_string(), 4),
+                ("Point".to_string(), 3),
+                ("make_point".to_string(), 1),
+                ("make_point".to_string(), 2),
+            ]
+        );
+        let definitions: Vec<&str> = search.definitions().iter().map(|s| s.filename()).collect();
+        assert_eq!(definitions, vec!["include/geo/point.h"]);
+    }
+
+    #[test]
+    fn definitions_are_all_matching_without_an_exact_one() {
+        let cscope = parse_buffer(&points()).unwrap();
+        let search = Search::new(&cscope, "poin", RenderOptions::default()).rank();
+        let definitions: Vec<&str> = search.definitions().iter().map(|s| s.name()).collect();
+        assert_eq!(definitions, vec!["point", "make_point"]);
+    }
+}
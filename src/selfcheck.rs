@@ -0,0 +1,80 @@
+use crate::cscope::{Cscope, Symbol};
+use crate::error::CscopeError;
+use crate::writer::{write_database, WriteOptions};
+
+// The outcome of writing a database back out and parsing it again.
+pub struct SelfCheck {
+    name: String,
+    records: usize,
+    differences: Vec<String>,
+}
+
+impl SelfCheck {
+    pub fn run(name: &str, cscope: &Cscope) -> Result<SelfCheck, CscopeError> {
+        let copy = crate::cscope::parse_buffer(&write_database(cscope, WriteOptions::default()))?;
+        let mut differences = vec![];
+        for (before, after) in cscope.symbols().iter().zip(copy.symbols()) {
+            for ((field, was), (_, is)) in fields(before).iter().zip(fields(after).iter()) {
+                if was != is {
+                    differences.push(format!(
+                        "{}:{} {}: {} {:?} became {:?}",
+                        before.filename(),
+                        before.line_number(),
+                        before.name(),
+                        field,
+                        was,
+                        is
+                    ));
+                }
+            }
+        }
+        let (read, copied) = (cscope.symbols().len(), copy.symbols().len());
+        if read > copied {
+            differences.push(format!("{} records lost", read - copied));
+        } else if copied > read {
+            differences.push(format!("{} records added", copied - read));
+        }
+        Ok(SelfCheck {
+            name: name.to_string(),
+            records: read,
+            differences,
+        })
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+// What a record holds, apart from where it is in the database.
+fn fields(sym: &Symbol) -> [(&'static str, String); 8] {
+    [
+        ("mark", sym.mark().name().to_string()),
+        ("file", sym.filename().to_string()),
+        ("line", sym.line_number().to_string()),
+        ("name", sym.name().to_string()),
+        ("text before", sym.text_before().to_string()),
+        ("text after", sym.text_after().to_string()),
+        ("line text", sym.raw_line_text().to_string()),
+        ("extent", format!("{:?}", sym.extent())),
+    ]
+}
+
+impl std::fmt::Display for SelfCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_lossless() {
+            return writeln!(f, "•{}: {} records, no loss", self.name, self.records);
+        }
+        write!(
+            f,
+            "•{}: {} records, {} differences\n╰─╮\n",
+            self.name,
+            self.records,
+            self.differences.len()
+        )?;
+        for difference in self.differences.iter() {
+            writeln!(f, "  ├ {}", difference)?;
+        }
+        Ok(())
+    }
+}
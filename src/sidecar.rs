@@ -0,0 +1,177 @@
+use crate::cscope::{parse_database_with, Cscope, ParseOptions};
+use crate::error::CscopeError;
+use serde::{Deserialize, Serialize};
+use std::io::{BufWriter, Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// Databases smaller than this parse about as fast as the sidecar loads.
+const MIN_BYTES: u64 = 1 << 20;
+
+// Starts each sidecar, the version of code-ls that wrote it going with it
+// since the records change shape between versions.
+const MAGIC: &[u8] = b"code-ls parsed database\n";
+
+// What the parse depends on: the database as it was on disk, and the
+// options it was parsed with.
+#[derive(PartialEq, Serialize, Deserialize)]
+struct Key {
+    version: String,
+    len: u64,
+    modified: u128,
+    options: ParseOptions,
+}
+
+impl Key {
+    fn new(db: &Path, options: ParseOptions) -> Result<Key, Error> {
+        let meta = std::fs::metadata(db)?;
+        let modified = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_err(Error::other)?;
+        Ok(Key {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            len: meta.len(),
+            modified: modified.as_nanos(),
+            options,
+        })
+    }
+}
+
+// The sidecar of a database, cscope.out.cls beside cscope.out.
+pub fn path(db: &Path) -> PathBuf {
+    let mut name = db.as_os_str().to_os_string();
+    name.push(".cls");
+    PathBuf::from(name)
+}
+
+fn load(sidecar: &Path, key: &Key) -> Result<Cscope, Error> {
+    let bytes = std::fs::read(sidecar)?;
+    let stale = || Error::new(ErrorKind::InvalidData, "stale sidecar");
+    let mut rest = bytes.strip_prefix(MAGIC).ok_or_else(stale)?;
+    let saved: Key = bincode::deserialize_from(&mut rest).map_err(Error::other)?;
+    if saved != *key {
+        return Err(stale());
+    }
+    let mut cscope: Cscope = bincode::deserialize(rest).map_err(Error::other)?;
+    cscope.share_strings();
+    Ok(cscope)
+}
+
+fn store(sidecar: &Path, key: &Key, cscope: &Cscope) -> Result<(), Error> {
+    // Written aside and renamed into place, so a reader never sees half.
+    let partial = sidecar.with_extension(format!("cls.{}", std::process::id()));
+    let write = || -> Result<(), Error> {
+        let mut file = BufWriter::new(std::fs::File::create(&partial)?);
+        std::io::Write::write_all(&mut file, MAGIC)?;
+        bincode::serialize_into(&mut file, key).map_err(Error::other)?;
+        bincode::serialize_into(&mut file, cscope).map_err(Error::other)?;
+        std::io::Write::flush(&mut file)
+    };
+    match write().and_then(|_| std::fs::rename(&partial, sidecar)) {
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(e)
+        }
+        ok => ok,
+    }
+}
+
+// Parse the database, or load it from its sidecar when that was written
+// from the same database with the same options.  A large database gets a
+// sidecar after it is parsed, one that cannot be written only means the
+// next run parses it again.
+pub fn parse_database_cached(db: &Path, options: ParseOptions) -> Result<Cscope, CscopeError> {
+    let key = match Key::new(db, options) {
+        Ok(key) if key.len >= MIN_BYTES => key,
+        _ => return parse_database_with(db, options),
+    };
+    let sidecar = path(db);
+    if let Ok(cscope) = load(&sidecar, &key) {
+        return Ok(cscope);
+    }
+    let cscope = parse_database_with(db, options)?;
+    let _ = store(&sidecar, &key, &cscope);
+    Ok(cscope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::fixture::{generate, FixtureOptions};
+    use crate::testdb::sample;
+
+    // A scratch directory holding `db` as cscope.out.
+    fn scratch(name: &str, db: &[u8]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("code-ls-sidecar-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("cscope.out"), db).unwrap();
+        dir
+    }
+
+    #[test]
+    fn large_databases_get_a_sidecar_loaded_the_same() {
+        let db = generate(&FixtureOptions {
+            files: 40,
+            symbols_per_file: 1500,
+            write: Default::default(),
+            truncated: false,
+        });
+        assert!(db.len() as u64 >= MIN_BYTES);
+        let dir = scratch("large", &db);
+        let path = dir.join("cscope.out");
+        let parsed = parse_database_cached(&path, ParseOptions::default()).unwrap();
+        assert!(super::path(&path).is_file());
+        let loaded = parse_database_cached(&path, ParseOptions::default()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(format!("{:?}", loaded), format!("{:?}", parsed));
+        // The records of a file share its name once more.
+        let symbols = loaded.symbols();
+        assert!(symbols[0].filename() == symbols[1].filename());
+        assert!(std::ptr::eq(symbols[0].filename(), symbols[1].filename()));
+    }
+
+    #[test]
+    fn small_databases_are_parsed_each_time() {
+        let dir = scratch("small", &sample());
+        let path = dir.join("cscope.out");
+        parse_database_cached(&path, ParseOptions::default()).unwrap();
+        assert!(!super::path(&path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sidecar_is_stale_once_the_database_or_the_options_change() {
+        let dir = scratch("stale", &sample());
+        let path = dir.join("cscope.out");
+        let sidecar = super::path(&path);
+        let cscope = parse_buffer(&sample()).unwrap();
+        let key = |options| Key::new(&path, options).unwrap();
+        let lenient = ParseOptions {
+            lenient: true,
+            ..Default::default()
+        };
+
+        store(&sidecar, &key(ParseOptions::default()), &cscope).unwrap();
+        assert!(load(&sidecar, &key(ParseOptions::default())).is_ok());
+        assert!(load(&sidecar, &key(lenient)).is_err());
+
+        // Written again at another time, the same length.
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+        assert!(load(&sidecar, &key(ParseOptions::default())).is_err());
+        store(&sidecar, &key(ParseOptions::default()), &cscope).unwrap();
+
+        // Grown, the modification time put back.
+        file.set_len(sample().len() as u64 + 1).unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+        assert!(load(&sidecar, &key(ParseOptions::default())).is_err());
+
+        std::fs::write(&sidecar, b"not a sidecar").unwrap();
+        assert!(load(&sidecar, &key(ParseOptions::default())).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use crate::cscope::Symbol;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// Compare strings with runs of digits ordered by their numeric value, so
+// "file2.c" comes before "file10.c".
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (a_ch, b_ch) = match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_ch), Some(b_ch)) => (a_ch, b_ch),
+        };
+        if a_ch.is_ascii_digit() && b_ch.is_ascii_digit() {
+            let (a_num, a_rest) = split_digits(a);
+            let (b_num, b_rest) = split_digits(b);
+            // Longer numbers (without their leading zeros) are larger, ties
+            // are broken by the digits and then by the number of zeros.
+            let (a_value, b_value) = (a_num.trim_start_matches('0'), b_num.trim_start_matches('0'));
+            let order = a_value
+                .len()
+                .cmp(&b_value.len())
+                .then_with(|| a_value.cmp(b_value))
+                .then_with(|| a_num.len().cmp(&b_num.len()));
+            if order != Ordering::Equal {
+                return order;
+            }
+            a = a_rest;
+            b = b_rest;
+        } else {
+            if a_ch != b_ch {
+                return a_ch.cmp(&b_ch);
+            }
+            a = &a[a_ch.len_utf8()..];
+            b = &b[b_ch.len_utf8()..];
+        }
+    }
+}
+
+// Split off the leading run of digits.
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+// How the listings order the records of each file.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WithinFile {
+    #[default]
+    Database,
+    Name,
+    Line,
+    // By kind, then by line.
+    Kind,
+}
+
+fn cmp_within_file(a: &Symbol, b: &Symbol, order: WithinFile) -> Ordering {
+    match order {
+        WithinFile::Database => Ordering::Equal,
+        WithinFile::Name => a.display_name().cmp(b.display_name()),
+        WithinFile::Line => a.line_number().cmp(&b.line_number()),
+        WithinFile::Kind => a
+            .mark()
+            .name()
+            .cmp(b.mark().name())
+            .then(a.line_number().cmp(&b.line_number())),
+    }
+}
+
+// Order the records of each file, keeping the files where they are.  The
+// sort is stable, ties keep the database order.
+pub fn sort_within_files<T>(
+    items: &mut [T],
+    symbol: impl Fn(&T) -> &Symbol,
+    order: WithinFile,
+    reverse: bool,
+) {
+    if order == WithinFile::Database {
+        return;
+    }
+    let mut files: HashMap<&str, usize> = HashMap::new();
+    for item in items.iter() {
+        let count = files.len();
+        files.entry(symbol(item).filename()).or_insert(count);
+    }
+    let files: HashMap<String, usize> = files
+        .into_iter()
+        .map(|(f, idx)| (f.to_string(), idx))
+        .collect();
+    items.sort_by(|a, b| {
+        let (a, b) = (symbol(a), symbol(b));
+        let within = cmp_within_file(a, b, order);
+        files[a.filename()]
+            .cmp(&files[b.filename()])
+            .then(if reverse { within.reverse() } else { within })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_digits_by_their_value() {
+        // Each pair in order, the first before the second.
+        let cases = [
+            ("file2", "file10"),
+            ("file2.c", "file10.c"),
+            ("file9z", "file10a"),
+            ("a1", "a01"),
+            ("a01", "a2"),
+            ("a007", "a0007"),
+            ("v1.2.9", "v1.2.10"),
+            ("x", "x0"),
+            ("2", "a"),
+            ("18446744073709551616", "18446744073709551617"),
+            ("99999999999999999999", "100000000000000000000"),
+            ("caf\u{e9}2", "caf\u{e9}10"),
+            ("cafe", "caf\u{e9}"),
+            ("\u{e9}t\u{e9}", "\u{fc}ber"),
+            ("\u{663}", "\u{664}"),
+        ];
+        for (a, b) in cases.iter() {
+            assert_eq!(natural_cmp(a, b), Ordering::Less, "{} {}", a, b);
+            assert_eq!(natural_cmp(b, a), Ordering::Greater, "{} {}", b, a);
+        }
+        for s in ["", "file10", "a01", "\u{e9}2\u{e9}"].iter() {
+            assert_eq!(natural_cmp(s, s), Ordering::Equal, "{}", s);
+        }
+    }
+}
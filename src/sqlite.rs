@@ -0,0 +1,219 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE files (
+    id INTEGER PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE
+);
+CREATE TABLE symbols (
+    id INTEGER PRIMARY KEY,
+    file_id INTEGER NOT NULL REFERENCES files(id),
+    line INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    demangled TEXT,
+    signature TEXT,
+    -- The function the record is in.
+    function_id INTEGER REFERENCES symbols(id),
+    -- The last line of a function or macro definition.
+    end_line INTEGER,
+    offset INTEGER NOT NULL,
+    line_text TEXT NOT NULL
+);
+CREATE TABLE calls (
+    -- The call site, and the function it is in.
+    call_id INTEGER NOT NULL REFERENCES symbols(id),
+    caller_id INTEGER NOT NULL REFERENCES symbols(id),
+    callee TEXT NOT NULL,
+    -- The definition called, when the database has it.
+    callee_id INTEGER REFERENCES symbols(id)
+);
+CREATE INDEX symbols_name ON symbols(name);
+CREATE INDEX symbols_kind ON symbols(kind);
+CREATE INDEX symbols_file_line ON symbols(file_id, line);
+CREATE INDEX calls_caller ON calls(caller_id);
+CREATE INDEX calls_callee ON calls(callee);
+CREATE INDEX calls_callee_id ON calls(callee_id);
+";
+
+// Write the records to a new SQLite database at `path`, replacing any file
+// there: the files, every record but the end marks, and the calls made from
+// each function with the definition they call.
+pub fn export(cscope: &Cscope, path: &Path) -> Result<(), Error> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Connection::open(path)
+            .and_then(|mut db| write(cscope, &mut db))
+            .map_err(Error::other),
+    }
+}
+
+fn write(cscope: &Cscope, db: &mut Connection) -> rusqlite::Result<()> {
+    let tx = db.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+
+    let records: Vec<(&Symbol, Option<&Symbol>)> = cscope
+        .with_enclosing_functions()
+        .into_iter()
+        .filter(|(s, _)| !s.is_end_mark())
+        .collect();
+    let id_of: HashMap<*const Symbol, i64> = records
+        .iter()
+        .enumerate()
+        .map(|(idx, (s, _))| (*s as *const Symbol, idx as i64 + 1))
+        .collect();
+    let id = |sym: &Symbol| id_of[&(sym as *const Symbol)];
+
+    // The definitions a call can be to, those in the same file first.
+    let mut defined: HashMap<&str, Vec<&Symbol>> = HashMap::new();
+    for (sym, _) in records.iter() {
+        if matches!(sym.mark(), FileMark::FunctionDefinition | FileMark::Define) {
+            defined.entry(sym.display_name()).or_default().push(sym);
+        }
+    }
+
+    {
+        let mut file = tx.prepare("INSERT INTO files (id, path) VALUES (?1, ?2)")?;
+        let mut symbol = tx.prepare(
+            "INSERT INTO symbols (id, file_id, line, kind, name, demangled, signature, \
+             function_id, end_line, offset, line_text) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )?;
+        let mut call = tx.prepare(
+            "INSERT INTO calls (call_id, caller_id, callee, callee_id) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut files: HashMap<&str, i64> = HashMap::new();
+        for (sym, function) in records.iter() {
+            let file_id = match files.get(sym.filename()) {
+                Some(file_id) => *file_id,
+                None => {
+                    let file_id = files.len() as i64 + 1;
+                    file.execute(params![file_id, sym.filename()])?;
+                    files.insert(sym.filename(), file_id);
+                    file_id
+                }
+            };
+            symbol.execute(params![
+                id(sym),
+                file_id,
+                sym.line_number() as i64,
+                sym.mark().name(),
+                sym.name(),
+                sym.demangled(),
+                sym.signature(),
+                function.map(id),
+                sym.extent().map(|(_, end)| end as i64),
+                sym.offset() as i64,
+                sym.line_text(),
+            ])?;
+            if let (FileMark::FunctionCall, Some(function)) = (sym.mark(), function) {
+                let callee = defined.get(sym.display_name()).and_then(|d| {
+                    d.iter()
+                        .find(|d| d.filename() == sym.filename())
+                        .or_else(|| d.first())
+                });
+                call.execute(params![
+                    id(sym),
+                    id(function),
+                    sym.display_name(),
+                    callee.map(|c| id(c)),
+                ])?;
+            }
+        }
+    }
+    tx.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    // main calling draw, which both files define, and puts.
+    fn exported() -> Connection {
+        let db = database(
+            "\t@src/main.c\n\n\
+             1 void \n\t$draw\n(void) {\n\n\
+             2 }\n\n\t}\n\n\
+             3 int \n\t$main\n(void) {\n\n\
+             4 \n\t`draw\n();\n\n\
+             5 \n\t`puts\n(\"hi\");\n\n\
+             6 }\n\n\t}\n\n\
+             \t@src/other.c\n\n\
+             1 void \n\t$draw\n(void) {\n\n\
+             2 }\n\n\t}\n\n\
+             \t@\n",
+        );
+        let mut conn = Connection::open_in_memory().unwrap();
+        write(&parse_buffer(&db).unwrap(), &mut conn).unwrap();
+        conn
+    }
+
+    fn strings(conn: &Connection, sql: &str) -> Vec<String> {
+        let mut query = conn.prepare(sql).unwrap();
+        let rows = query.query_map([], |row| row.get::<_, String>(0)).unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn records_but_the_end_marks_are_kept_with_their_file_and_function() {
+        let conn = exported();
+        assert_eq!(
+            strings(&conn, "SELECT path FROM files ORDER BY id"),
+            vec!["src/main.c", "src/other.c"]
+        );
+        assert_eq!(
+            strings(
+                &conn,
+                "SELECT s.kind || ' ' || s.name || ' ' || f.path || ':' || s.line \
+                 FROM symbols s JOIN files f ON f.id = s.file_id ORDER BY s.id"
+            ),
+            vec![
+                "function draw src/main.c:1",
+                "function main src/main.c:3",
+                "call draw src/main.c:4",
+                "call puts src/main.c:5",
+                "function draw src/other.c:1",
+            ]
+        );
+        let end: i64 = conn
+            .query_row(
+                "SELECT end_line FROM symbols WHERE name = 'main'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(end, 6);
+        assert_eq!(
+            strings(
+                &conn,
+                "SELECT c.name FROM symbols c JOIN symbols f ON f.id = c.function_id \
+                 WHERE f.name = 'main' ORDER BY c.id"
+            ),
+            vec!["draw", "puts"]
+        );
+    }
+
+    #[test]
+    fn calls_are_to_the_definition_in_the_same_file() {
+        let conn = exported();
+        assert_eq!(
+            strings(
+                &conn,
+                "SELECT caller.name || ' ' || c.callee || ' ' || \
+                 IFNULL(f.path || ':' || callee.line, '-') \
+                 FROM calls c \
+                 JOIN symbols caller ON caller.id = c.caller_id \
+                 LEFT JOIN symbols callee ON callee.id = c.callee_id \
+                 LEFT JOIN files f ON f.id = callee.file_id \
+                 ORDER BY c.call_id"
+            ),
+            vec!["main draw src/main.c:1", "main puts -"]
+        );
+    }
+}
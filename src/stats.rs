@@ -0,0 +1,520 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use crate::render::{pad, width};
+use crate::table::{Cell, Table};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// How many grown files the trend lists for each snapshot.
+const SHOWN_FILES: usize = 5;
+
+// How many of the largest files the stats list.
+const LARGEST_FILES: usize = 10;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Totals {
+    pub files: u64,
+    pub records: u64,
+    pub functions: u64,
+    pub structs: u64,
+    pub macros: u64,
+    pub function_lines: u64,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FileTotals {
+    pub records: u64,
+    pub functions: u64,
+}
+
+// What the header and trailer say about the database itself.
+#[derive(Clone, Default)]
+pub struct Database {
+    pub version: u32,
+    pub compressed: bool,
+    // The terms of the inverted index, when built with -q.
+    pub inverted_index_terms: Option<u64>,
+    // The source files listed in the trailer.
+    pub source_files: u64,
+}
+
+// The size of the database at one point in time.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub totals: Totals,
+    pub files: BTreeMap<String, FileTotals>,
+    // The number of records of each kind, by the kind's name.
+    #[serde(default)]
+    pub kinds: BTreeMap<String, u64>,
+    // Not kept in a history.
+    #[serde(skip)]
+    pub database: Database,
+}
+
+impl Snapshot {
+    pub fn new(cscope: &Cscope) -> Snapshot {
+        let mut totals = Totals::default();
+        let mut files: BTreeMap<String, FileTotals> = BTreeMap::new();
+        let mut kinds: BTreeMap<String, u64> = BTreeMap::new();
+        for sym in cscope.symbols().iter() {
+            let file = files.entry(sym.filename().to_string()).or_default();
+            file.records += 1;
+            totals.records += 1;
+            *kinds.entry(sym.mark().name().to_string()).or_insert(0) += 1;
+            match sym.mark() {
+                FileMark::FunctionDefinition => {
+                    file.functions += 1;
+                    totals.functions += 1;
+                    totals.function_lines += sym.function_lines().unwrap_or(0);
+                }
+                FileMark::StructDefinition => totals.structs += 1,
+                FileMark::Define => totals.macros += 1,
+                _ => (),
+            }
+        }
+        totals.files = files.len() as u64;
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let database = Database {
+            version: cscope.version(),
+            compressed: cscope.is_compressed(),
+            inverted_index_terms: cscope.inverted_index_terms(),
+            source_files: cscope.trailer().source_files.len() as u64,
+        };
+        Snapshot {
+            time,
+            totals,
+            files,
+            kinds,
+            database,
+        }
+    }
+
+    // The kinds with the most records first.
+    fn kinds(&self) -> Vec<(&str, u64)> {
+        let mut kinds: Vec<(&str, u64)> =
+            self.kinds.iter().map(|(k, n)| (k.as_str(), *n)).collect();
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        kinds
+    }
+
+    // The files with the most records first.
+    fn largest_files(&self) -> Vec<(&str, &FileTotals)> {
+        let mut files: Vec<(&str, &FileTotals)> =
+            self.files.iter().map(|(f, t)| (f.as_str(), t)).collect();
+        files.sort_by(|a, b| b.1.records.cmp(&a.1.records).then(a.0.cmp(b.0)));
+        files
+    }
+
+    // The header and trailer facts as name and value.
+    fn about(&self) -> [(&'static str, String); 4] {
+        let db = &self.database;
+        let yes_no = |b: bool| if b { "yes" } else { "no" }.to_string();
+        [
+            ("version", db.version.to_string()),
+            ("source files", db.source_files.to_string()),
+            ("compressed", yes_no(db.compressed)),
+            (
+                "inverted index",
+                db.inverted_index_terms
+                    .map_or(yes_no(false), |n| format!("{} terms", n)),
+            ),
+        ]
+    }
+
+    // The totals, the database, the records of each kind and the counts of
+    // each file.
+    pub fn tables(&self) -> Vec<Table> {
+        let mut totals = Table::new("totals", &["count", "total"]);
+        for (name, count) in self.counts() {
+            totals.rows.push(vec![Cell::from(name), Cell::from(count)]);
+        }
+        let mut database = Table::new("database", &["property", "value"]);
+        for (name, value) in self.about() {
+            database
+                .rows
+                .push(vec![Cell::from(name), Cell::from(value)]);
+        }
+        let mut kinds = Table::new("kinds", &["kind", "records"]);
+        for (kind, count) in self.kinds() {
+            kinds.rows.push(vec![Cell::from(kind), Cell::from(count)]);
+        }
+        let mut files = Table::new("files", &["file", "records", "functions"]);
+        for (name, file) in self.files.iter() {
+            files.rows.push(vec![
+                Cell::from(name.as_str()),
+                Cell::from(file.records),
+                Cell::from(file.functions),
+            ]);
+        }
+        vec![totals, database, kinds, files]
+    }
+
+    fn counts(&self) -> [(&'static str, u64); 6] {
+        let t = &self.totals;
+        [
+            ("files", t.files),
+            ("records", t.records),
+            ("functions", t.functions),
+            ("structs", t.structs),
+            ("macros", t.macros),
+            ("function lines", t.function_lines),
+        ]
+    }
+
+    // Add the snapshot to a history file, one JSON object per line.
+    pub fn record(&self, history: &Path) -> Result<(), Error> {
+        let line =
+            serde_json::to_string(self).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(history)?;
+        writeln!(file, "{}", line)
+    }
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (name, count) in self.counts() {
+            writeln!(f, "{:<14} {:>9}", name, count)?;
+        }
+        for (name, value) in self.about() {
+            writeln!(f, "{:<14} {:>9}", name, value)?;
+        }
+        writeln!(f, "\nrecords by kind:")?;
+        for (kind, count) in self.kinds() {
+            writeln!(f, "  {:<12} {:>9}", kind, count)?;
+        }
+        writeln!(f, "\nlargest files:")?;
+        let files = self.largest_files();
+        let files = &files[..files.len().min(LARGEST_FILES)];
+        let len = files.iter().map(|(name, _)| width(name)).max().unwrap_or(0);
+        for (name, file) in files.iter() {
+            writeln!(
+                f,
+                "  {} {:>9} records, {} functions",
+                pad(name, len),
+                file.records,
+                file.functions
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// The changes between the snapshots of a history file.
+pub struct Trend {
+    name: String,
+    snapshots: Vec<Snapshot>,
+}
+
+impl Trend {
+    pub fn load(history: &Path) -> Result<Trend, Error> {
+        let file = std::fs::File::open(history)?;
+        let mut snapshots = vec![];
+        for (idx, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let snapshot: Snapshot = serde_json::from_str(&line).map_err(|e| {
+                Error::new(ErrorKind::InvalidData, format!("line {}: {}", idx + 1, e))
+            })?;
+            snapshots.push(snapshot);
+        }
+        Ok(Trend {
+            name: history.display().to_string(),
+            snapshots,
+        })
+    }
+}
+
+// A count followed by how much it changed, e.g. "functions:340 (+12)".
+fn change(name: &str, was: u64, is: u64) -> String {
+    format!("{}:{} ({:+})", name, is, is as i64 - was as i64)
+}
+
+impl std::fmt::Display for Trend {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "•{}: {} snapshots\n╰─╮\n",
+            self.name,
+            self.snapshots.len()
+        )?;
+        let empty = Totals::default();
+        let mut previous: Option<&Snapshot> = None;
+        for snapshot in self.snapshots.iter() {
+            let (was, is) = (previous.map_or(&empty, |p| &p.totals), &snapshot.totals);
+            writeln!(
+                f,
+                "  ├ {} {}, {}, {}, {}",
+                utc_time(snapshot.time),
+                change("files", was.files, is.files),
+                change("functions", was.functions, is.functions),
+                change("structs", was.structs, is.structs),
+                change("macros", was.macros, is.macros)
+            )?;
+
+            // The files with more records than before, the most grown first.
+            if let Some(previous) = previous {
+                let mut grown: Vec<(&str, u64, i64)> = snapshot
+                    .files
+                    .iter()
+                    .filter_map(|(name, file)| {
+                        let old = previous.files.get(name).cloned().unwrap_or_default();
+                        let functions = file.functions as i64 - old.functions as i64;
+                        (file.records > old.records)
+                            .then(|| (name.as_str(), file.records - old.records, functions))
+                    })
+                    .collect();
+                grown.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+                for (i, (name, records, functions)) in grown.iter().take(SHOWN_FILES).enumerate() {
+                    let branch = if i + 1 == grown.len().min(SHOWN_FILES) {
+                        '╰'
+                    } else {
+                        '├'
+                    };
+                    writeln!(
+                        f,
+                        "  │ {} {} records:{:+}, functions:{:+}",
+                        branch, name, records, functions
+                    )?;
+                }
+            }
+            previous = Some(snapshot);
+        }
+        Ok(())
+    }
+}
+
+// "YYYY-MM-DD HH:MM" in UTC for seconds since the Unix epoch.
+fn utc_time(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let (hour, minute) = (secs % 86400 / 3600, secs % 3600 / 60);
+
+    // From the days since 1970-01-01 to a date of the proleptic Gregorian
+    // calendar, counting in 400 year eras that start on March 1st.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
+}
+
+// The widest bar of the histogram of function sizes.
+const HISTOGRAM_WIDTH: u64 = 40;
+
+// The number of lines each function definition spans, from its name to the
+// closing brace cscope marks the end of it with, counted in buckets of
+// 1, 2-3, 4-7, 8-15 lines and so on.
+pub struct FunctionSizes<'a> {
+    // The functions with the lines they span, the longest first.
+    functions: Vec<(&'a Symbol, u64)>,
+    // The bucket counts, of the functions before any were left out.
+    buckets: Vec<u64>,
+    // Only the functions longer than this are listed.
+    longer_than: Option<u64>,
+}
+
+// The bucket of a function of `lines` lines, 2^n to 2^(n+1) - 1 lines.
+fn bucket(lines: u64) -> usize {
+    (64 - lines.max(1).leading_zeros() - 1) as usize
+}
+
+// The first and last number of lines of a bucket.
+fn bucket_range(idx: usize) -> (u64, u64) {
+    (1 << idx, (1 << (idx + 1)) - 1)
+}
+
+impl<'a> FunctionSizes<'a> {
+    pub fn new(cscope: &'a Cscope) -> FunctionSizes<'a> {
+        let mut functions: Vec<(&Symbol, u64)> = cscope
+            .symbols()
+            .iter()
+            .filter_map(|s| Some((s, s.function_lines()?)))
+            .collect();
+        functions.sort_by_key(|(_, lines)| Reverse(*lines));
+        let mut buckets: Vec<u64> = vec![];
+        for (_, lines) in functions.iter() {
+            let idx = bucket(*lines);
+            if buckets.len() <= idx {
+                buckets.resize(idx + 1, 0);
+            }
+            buckets[idx] += 1;
+        }
+        FunctionSizes {
+            functions,
+            buckets,
+            longer_than: None,
+        }
+    }
+
+    // List only the functions spanning more than `lines` lines.
+    pub fn longer_than(mut self, lines: u64) -> FunctionSizes<'a> {
+        self.functions.retain(|(_, n)| *n > lines);
+        self.longer_than = Some(lines);
+        self
+    }
+
+    pub fn tables(&self) -> Vec<Table> {
+        let mut histogram = Table::new("sizes", &["from", "to", "functions"]);
+        for (idx, count) in self.buckets.iter().enumerate() {
+            let (from, to) = bucket_range(idx);
+            histogram
+                .rows
+                .push(vec![Cell::from(from), Cell::from(to), Cell::from(*count)]);
+        }
+        let mut functions = Table::new("functions", &["file", "name", "line", "lines"]);
+        for (sym, lines) in self.functions.iter() {
+            functions.rows.push(vec![
+                Cell::from(sym.filename()),
+                Cell::from(sym.display_name()),
+                Cell::from(sym.line_number()),
+                Cell::from(*lines),
+            ]);
+        }
+        vec![histogram, functions]
+    }
+}
+
+// The histogram, then with --longer-than the functions over the limit.
+impl<'a> std::fmt::Display for FunctionSizes<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let most = self.buckets.iter().copied().max().unwrap_or(0).max(1);
+        writeln!(f, "{:>11} {:>9}", "lines", "functions")?;
+        for (idx, count) in self.buckets.iter().enumerate() {
+            let (from, to) = bucket_range(idx);
+            let bar = (count * HISTOGRAM_WIDTH).div_ceil(most) as usize;
+            let range = if from == to {
+                from.to_string()
+            } else {
+                format!("{}-{}", from, to)
+            };
+            let row = format!("{:>11} {:>9} {}", range, count, "█".repeat(bar));
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        if let Some(limit) = self.longer_than {
+            writeln!(f, "\nlonger than {} lines: {}", limit, self.functions.len())?;
+            let len = self
+                .functions
+                .iter()
+                .map(|(s, _)| width(s.display_name()))
+                .max()
+                .unwrap_or(0);
+            for (sym, lines) in self.functions.iter() {
+                writeln!(
+                    f,
+                    "  {} {:>6} lines, {}:{}",
+                    pad(sym.display_name(), len),
+                    lines,
+                    sym.filename(),
+                    sym.line_number()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cscope::parse_buffer;
+    use crate::testdb::database;
+
+    // A macro, a struct and a five line function in src/draw.c, a two line
+    // main in src/main.c.
+    fn sizes() -> Cscope {
+        parse_buffer(&database(
+            "\t@src/draw.c\n\n\
+             1 #define \n\t#MAX\n 10\n\n\t)\n\n\
+             2 struct \n\tspoint\n {\n\n\
+             3 void \n\t$draw\n(void) {\n\n\
+             4 \n\t`puts\n(\"hi\");\n\n\
+             7 }\n\n\t}\n\n\
+             \t@src/main.c\n\n\
+             1 int \n\t$main\n(void) {\n\n\
+             2 }\n\n\t}\n\n\
+             \t@\n",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn stats_count_the_kinds_and_files() {
+        let snapshot = Snapshot::new(&sizes());
+        assert_eq!(
+            snapshot.to_string(),
+            "files                  2\n\
+             records                8\n\
+             functions              2\n\
+             structs                1\n\
+             macros                 1\n\
+             function lines         7\n\
+             version               15\n\
+             source files           1\n\
+             compressed            no\n\
+             inverted index        no\n\
+             \n\
+             records by kind:\n  \
+             function             2\n  \
+             function-end         2\n  \
+             call                 1\n  \
+             macro                1\n  \
+             macro-end            1\n  \
+             struct               1\n\
+             \n\
+             largest files:\n  \
+             src/draw.c         6 records, 1 functions\n  \
+             src/main.c         2 records, 1 functions\n"
+        );
+        let files: Vec<Vec<String>> = snapshot.tables()[3]
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|c| c.to_string()).collect())
+            .collect();
+        assert_eq!(
+            files,
+            vec![vec!["src/draw.c", "6", "1"], vec!["src/main.c", "2", "1"]]
+        );
+    }
+
+    #[test]
+    fn function_sizes_pair_each_definition_with_its_end() {
+        let cscope = sizes();
+        let histogram = "      lines functions\n          \
+                         1         0\n        \
+                         2-3         1 ████████████████████████████████████████\n        \
+                         4-7         1 ████████████████████████████████████████\n";
+        assert_eq!(FunctionSizes::new(&cscope).to_string(), histogram);
+        // The histogram still counts the functions left out of the list.
+        assert_eq!(
+            FunctionSizes::new(&cscope).longer_than(3).to_string(),
+            format!(
+                "{}\nlonger than 3 lines: 1\n  draw      5 lines, src/draw.c:3\n",
+                histogram
+            )
+        );
+        assert_eq!(
+            (bucket(1), bucket(3), bucket(4), bucket(7), bucket(8)),
+            (0, 1, 2, 2, 3)
+        );
+        assert_eq!(bucket_range(3), (8, 15));
+    }
+}
@@ -0,0 +1,90 @@
+// A report as rows of cells, for the output formats that are not text trees.
+pub struct Table {
+    pub name: String,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+// Numbers are kept apart from text for formats that type their cells.
+#[derive(Clone)]
+pub enum Cell {
+    Text(String),
+    Number(u64),
+}
+
+impl Table {
+    pub fn new(name: &str, header: &[&str]) -> Table {
+        Table {
+            name: name.to_string(),
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows: vec![],
+        }
+    }
+}
+
+// A permalink for a place in the sources, e.g.
+// "https://github.com/o/r/blob/{rev}/{path}#L{line}".
+pub struct UrlTemplate {
+    pub template: String,
+    pub revision: String,
+}
+
+impl UrlTemplate {
+    // None for absolute paths, e.g. system headers, which are not part of
+    // the sources being linked to.
+    pub fn url(&self, path: &str, line: &str) -> Option<String> {
+        if path.starts_with('/') {
+            return None;
+        }
+        let url = self
+            .template
+            .replace("{path}", path.trim_start_matches("./"))
+            .replace("{line}", line)
+            .replace("{rev}", &self.revision);
+        Some(url)
+    }
+}
+
+impl Table {
+    // Add a url column to a table that has file and line columns.
+    pub fn with_urls(mut self, urls: &UrlTemplate) -> Table {
+        let column = |name: &str| self.header.iter().position(|h| h == name);
+        let (file, line) = match (column("file"), column("line")) {
+            (Some(file), Some(line)) => (file, line),
+            _ => return self,
+        };
+        self.header.push("url".to_string());
+        for row in self.rows.iter_mut() {
+            let url = urls.url(&row[file].to_string(), &row[line].to_string());
+            row.push(Cell::from(url.unwrap_or_default()));
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Cell::Text(text) => write!(f, "{}", text),
+            Cell::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl From<&str> for Cell {
+    fn from(text: &str) -> Cell {
+        Cell::Text(text.to_string())
+    }
+}
+
+impl From<String> for Cell {
+    fn from(text: String) -> Cell {
+        Cell::Text(text)
+    }
+}
+
+impl From<u64> for Cell {
+    fn from(n: u64) -> Cell {
+        Cell::Number(n)
+    }
+}
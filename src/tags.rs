@@ -0,0 +1,130 @@
+use crate::cscope::{Cscope, FileMark, Symbol};
+use std::path::Path;
+
+// The Universal Ctags kind letter of a definition, for the C kinds cscope
+// knows about.  Locals and parameters are left out, as ctags does by default.
+fn kind(mark: FileMark) -> Option<char> {
+    match mark {
+        FileMark::FunctionDefinition => Some('f'),
+        FileMark::Define => Some('d'),
+        FileMark::ClassDefinition => Some('c'),
+        FileMark::EnumDefinition => Some('g'),
+        FileMark::OtherGlobalDefinition => Some('v'),
+        FileMark::EnumStructUnionMemberGlobalDefinition => Some('m'),
+        FileMark::StructDefinition => Some('s'),
+        FileMark::TypedefDefinition => Some('t'),
+        FileMark::UnionDefinition => Some('u'),
+        _ => None,
+    }
+}
+
+// The definitions a tags file lists, with their kind letters.
+fn definitions(cscope: &Cscope) -> Vec<(&Symbol, char)> {
+    cscope
+        .symbols()
+        .iter()
+        .filter_map(|s| kind(s.mark()).map(|k| (s, k)))
+        .collect()
+}
+
+// A `tags` file in the extended ctags format, sorted by name.  cscope keeps
+// each line with its whitespace squeezed, so the tags address the lines by
+// number rather than by a search pattern.
+pub fn ctags(cscope: &Cscope) -> String {
+    let mut tags: Vec<String> = definitions(cscope)
+        .iter()
+        .map(|(sym, kind)| {
+            format!(
+                "{}\t{}\t{};\"\t{}\tline:{}\n",
+                sym.name(),
+                sym.filename(),
+                sym.line_number(),
+                kind,
+                sym.line_number()
+            )
+        })
+        .collect();
+    // ctags sorts bytewise, as `LC_ALL=C sort` does.
+    tags.sort();
+    tags.dedup();
+
+    let mut out = String::from(
+        "!_TAG_FILE_FORMAT\t2\t/extended format; --format=1 will not append ;\" to lines/\n\
+         !_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n\
+         !_TAG_PROGRAM_NAME\tcode-ls\t//\n",
+    );
+    out.extend(tags);
+    out
+}
+
+// An Emacs TAGS file: a section per source file headed by its size in bytes,
+// each tag the start of its line up to the name, then the name, line number
+// and byte offset of the line.  The lines and offsets come from the sources
+// when they are found under `dir`, where cscope was run; otherwise the tag
+// has the database's copy of the line and no offset.
+pub fn etags(cscope: &Cscope, dir: &Path) -> String {
+    let mut files: Vec<(&str, Vec<&Symbol>)> = vec![];
+    for (sym, _) in definitions(cscope) {
+        // The records of a file are together, so only the last can match.
+        match files.last_mut() {
+            Some((f, syms)) if *f == sym.filename() => syms.push(sym),
+            _ => files.push((sym.filename(), vec![sym])),
+        }
+    }
+
+    let mut out = String::new();
+    for (fname, syms) in files.iter_mut() {
+        syms.sort_by_key(|s| s.line_number());
+        let source = std::fs::read(dir.join(&fname)).ok();
+        let lines: Vec<(usize, &[u8])> = match source.as_deref() {
+            Some(bytes) => line_offsets(bytes),
+            None => vec![],
+        };
+        let mut section = String::new();
+        for sym in syms.iter() {
+            let line = sym.line_number() as usize;
+            match lines.get(line.wrapping_sub(1)) {
+                Some((offset, text)) => {
+                    let text = String::from_utf8_lossy(text);
+                    section.push_str(&format!(
+                        "{}\x7f{}\x01{},{}\n",
+                        tag_text(&text, sym.name()),
+                        sym.name(),
+                        line,
+                        offset
+                    ));
+                }
+                None => section.push_str(&format!(
+                    "{}\x7f{}\x01{},\n",
+                    tag_text(sym.line_text(), sym.name()),
+                    sym.name(),
+                    line
+                )),
+            }
+        }
+        out.push_str(&format!("\x0c\n{},{}\n{}", fname, section.len(), section));
+    }
+    out
+}
+
+// The start of each line of a file, and the line without its newline.
+fn line_offsets(bytes: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut offset = 0;
+    bytes
+        .split(|b| *b == b'\n')
+        .map(|line| {
+            let start = offset;
+            offset += line.len() + 1;
+            (start, line.strip_suffix(b"\r").unwrap_or(line))
+        })
+        .collect()
+}
+
+// A line up to the end of the name it defines, the whole line when the name
+// is not on it.
+fn tag_text<'a>(line: &'a str, name: &str) -> &'a str {
+    match line.find(name) {
+        Some(idx) => &line[..idx + name.len()],
+        None => line,
+    }
+}
@@ -0,0 +1,52 @@
+// Databases written out by hand for the tests.
+
+// The records of `body` with a header and the trailer listing src/main.c.
+pub fn database(body: &str) -> Vec<u8> {
+    database_in("/nonexistent", body)
+}
+
+// The same for a database built in `dir`, where the sources are read from.
+pub fn database_in(dir: &str, body: &str) -> Vec<u8> {
+    database_of(dir, body.as_bytes())
+}
+
+// Records that are not all UTF-8.
+pub fn database_of(dir: &str, body: &[u8]) -> Vec<u8> {
+    let header_len = format!("cscope 15 {} -c 0000000000\n", dir).len();
+    let header = format!("cscope 15 {} -c {:010}\n", dir, header_len + body.len());
+    let mut db = header.into_bytes();
+    db.extend_from_slice(body);
+    db.extend_from_slice(b"1\n.\n0\n0\n1\n11\nsrc/main.c\n");
+    db
+}
+
+// A small uncompressed database: a struct, a function and a call.
+pub fn sample() -> Vec<u8> {
+    database(
+        "\t@src/main.c\n\n\
+         1 struct \n\tspoint\n {\n\n\
+         3 int \n\t$main\n(void) {\n\n\
+         4 \n\t`puts\n(\"hi\");\n\n\
+         5 }\n\n\t}\n\n\
+         \t@\n",
+    )
+}
+
+// src/main.c defining each of `functions` in turn, a line for each call in
+// its body.
+pub fn calls(functions: &[(&str, &[&str])]) -> Vec<u8> {
+    let mut body = "\t@src/main.c\n\n".to_string();
+    let mut line = 0;
+    for (function, callees) in functions.iter() {
+        line += 1;
+        body.push_str(&format!("{} int \n\t${}\n(void) {{\n\n", line, function));
+        for callee in callees.iter() {
+            line += 1;
+            body.push_str(&format!("{} \n\t`{}\n();\n\n", line, callee));
+        }
+        line += 1;
+        body.push_str(&format!("{} }}\n\n\t}}\n\n", line));
+    }
+    body.push_str("\t@\n");
+    database(&body)
+}
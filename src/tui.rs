@@ -0,0 +1,365 @@
+use crate::open;
+use code_ls::cscope::{Cscope, Symbol};
+use code_ls::search::fuzzy_quality;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::Error;
+use std::path::Path;
+
+// At most this many matches of a search are listed.
+const MAX_MATCHES: usize = 500;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Focus {
+    Files,
+    Symbols,
+    Search,
+}
+
+// The files with the definitions in each, the selection in both panes and
+// the search typed so far.
+struct Browser<'a> {
+    files: Vec<(&'a str, Vec<&'a Symbol>)>,
+    file: ListState,
+    symbol: ListState,
+    focus: Focus,
+    query: String,
+    // The definitions of every file matching the query, best first.
+    matches: Vec<&'a Symbol>,
+}
+
+impl<'a> Browser<'a> {
+    fn new(cscope: &'a Cscope) -> Browser<'a> {
+        let mut files: Vec<(&str, Vec<&Symbol>)> = vec![];
+        for sym in cscope.symbols().iter() {
+            if !sym.mark().is_outer_definition() {
+                continue;
+            }
+            if files.last().map(|f| f.0) != Some(sym.filename()) {
+                files.push((sym.filename(), vec![]));
+            }
+            if let Some((_, syms)) = files.last_mut() {
+                syms.push(sym);
+            }
+        }
+        Browser {
+            files,
+            file: ListState::default().with_selected(Some(0)),
+            symbol: ListState::default().with_selected(Some(0)),
+            focus: Focus::Files,
+            query: String::new(),
+            matches: vec![],
+        }
+    }
+
+    // What the symbol pane lists: the matches while searching, otherwise the
+    // definitions of the selected file.
+    fn symbols(&self) -> &[&'a Symbol] {
+        if !self.query.is_empty() {
+            return &self.matches;
+        }
+        self.file
+            .selected()
+            .and_then(|i| self.files.get(i))
+            .map_or(&[], |(_, syms)| syms.as_slice())
+    }
+
+    fn selected_symbol(&self) -> Option<&'a Symbol> {
+        self.symbol
+            .selected()
+            .and_then(|i| self.symbols().get(i))
+            .copied()
+    }
+
+    fn search(&mut self) {
+        let mut scored: Vec<(i64, &Symbol)> = self
+            .files
+            .iter()
+            .flat_map(|(_, syms)| syms.iter())
+            .filter_map(|s| Some((fuzzy_quality(s.display_name(), &self.query)?, *s)))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.truncate(MAX_MATCHES);
+        self.matches = scored.into_iter().map(|(_, s)| s).collect();
+        self.symbol.select(Some(0));
+    }
+
+    // Move the selection of the focused pane by `by` rows.
+    fn step(&mut self, by: isize) {
+        let (state, len) = match self.focus {
+            Focus::Files => (&mut self.file, self.files.len()),
+            _ => {
+                let len = self.symbols().len();
+                (&mut self.symbol, len)
+            }
+        };
+        if len == 0 {
+            return;
+        }
+        let at = state.selected().unwrap_or(0) as isize + by;
+        state.select(Some(at.clamp(0, len as isize - 1) as usize));
+        if self.focus == Focus::Files {
+            self.symbol.select(Some(0));
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [search, body, help] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [files, symbols] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(body);
+
+        let focused = self.focus;
+        let block = |title: &str, focus: Focus| {
+            let block = Block::bordered().title(title.to_string());
+            if focused == focus {
+                block.border_style(Style::new().add_modifier(Modifier::BOLD))
+            } else {
+                block
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(self.query.as_str()).block(block("search", Focus::Search)),
+            search,
+        );
+
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+        let file_items: Vec<ListItem> = self
+            .files
+            .iter()
+            .map(|(f, syms)| ListItem::new(format!("{} ({})", f, syms.len())))
+            .collect();
+        let file_list = List::new(file_items)
+            .block(block("files", Focus::Files))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(file_list, files, &mut self.file);
+
+        let searching = !self.query.is_empty();
+        let symbol_items: Vec<ListItem> = self
+            .symbols()
+            .iter()
+            .map(|s| {
+                let location = if searching {
+                    format!("{}:{}", s.filename(), s.line_number())
+                } else {
+                    format!("line:{}", s.line_number())
+                };
+                ListItem::new(format!(
+                    "{} {}, {}",
+                    s.display_name(),
+                    s.mark().name(),
+                    location
+                ))
+            })
+            .collect();
+        let title = if searching {
+            format!("{} matches", self.matches.len())
+        } else {
+            "definitions".to_string()
+        };
+        let symbol_list = List::new(symbol_items)
+            .block(block(&title, Focus::Symbols))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(symbol_list, symbols, &mut self.symbol);
+
+        frame.render_widget(
+            Line::from("/ search  tab switch panes  enter open in $EDITOR  q quit"),
+            help,
+        );
+    }
+}
+
+// Browse the database until q is pressed.  Enter opens the selected
+// definition in the editor, the file names are relative to `dir`.
+pub fn run(cscope: &Cscope, dir: &Path) -> Result<(), Error> {
+    let mut browser = Browser::new(cscope);
+    let mut terminal = ratatui::try_init()?;
+    let result = browse(&mut terminal, &mut browser, dir);
+    ratatui::try_restore()?;
+    result
+}
+
+// What a key press leaves the browser to do.
+enum Action<'a> {
+    Stay,
+    Quit,
+    Open(&'a Symbol),
+}
+
+impl<'a> Browser<'a> {
+    // Update the state for a key pressed.
+    fn key(&mut self, code: KeyCode) -> Action<'a> {
+        if self.focus == Focus::Search {
+            match code {
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.search();
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.search();
+                }
+                KeyCode::Enter | KeyCode::Down | KeyCode::Tab => self.focus = Focus::Symbols,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.focus = Focus::Files;
+                }
+                _ => (),
+            }
+            return Action::Stay;
+        }
+        match code {
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Esc if self.query.is_empty() => return Action::Quit,
+            KeyCode::Esc => self.query.clear(),
+            KeyCode::Char('/') => self.focus = Focus::Search,
+            KeyCode::Up | KeyCode::Char('k') => self.step(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.step(1),
+            KeyCode::PageUp => self.step(-20),
+            KeyCode::PageDown => self.step(20),
+            KeyCode::Left | KeyCode::Char('h') => self.focus = Focus::Files,
+            KeyCode::Right | KeyCode::Char('l') => self.focus = Focus::Symbols,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Files => Focus::Symbols,
+                    _ => Focus::Files,
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(sym) = self.selected_symbol() {
+                    return Action::Open(sym);
+                }
+            }
+            _ => (),
+        }
+        Action::Stay
+    }
+}
+
+fn browse(terminal: &mut DefaultTerminal, browser: &mut Browser, dir: &Path) -> Result<(), Error> {
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match browser.key(key.code) {
+            Action::Stay => (),
+            Action::Quit => return Ok(()),
+            Action::Open(sym) => {
+                // The editor gets the terminal to itself until it exits.
+                ratatui::try_restore()?;
+                let opened = open::open(sym, dir);
+                *terminal = ratatui::try_init()?;
+                opened?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // draw and point in src/draw.c, main and make_point in src/main.c.
+    fn database() -> Vec<u8> {
+        let body = "\t@src/draw.c\n\n\
+                    1 void \n\t$draw\n(void) {\n\n\
+                    2 }\n\n\t}\n\n\
+                    3 struct \n\tspoint\n {\n\n\
+                    \t@src/main.c\n\n\
+                    1 int \n\t$main\n(void) {\n\n\
+                    2 }\n\n\t}\n\n\
+                    3 int \n\t$make_point\n(void) {\n\n\
+                    4 }\n\n\t}\n\n\
+                    \t@\n";
+        let header_len = "cscope 15 /nonexistent -c 0000000000\n".len();
+        let header = format!(
+            "cscope 15 /nonexistent -c {:010}\n",
+            header_len + body.len()
+        );
+        let mut db = (header + body).into_bytes();
+        db.extend_from_slice(b"1\n.\n0\n0\n1\n11\nsrc/main.c\n");
+        db
+    }
+
+    fn names(browser: &Browser) -> Vec<String> {
+        browser
+            .symbols()
+            .iter()
+            .map(|s| s.display_name().to_string())
+            .collect()
+    }
+
+    // The definition Enter opens.
+    fn opened<'a>(browser: &mut Browser<'a>) -> Option<&'a Symbol> {
+        match browser.key(KeyCode::Enter) {
+            Action::Open(sym) => Some(sym),
+            _ => None,
+        }
+    }
+
+    fn press(browser: &mut Browser, keys: &[KeyCode]) {
+        for key in keys.iter() {
+            assert!(matches!(browser.key(*key), Action::Stay));
+        }
+    }
+
+    #[test]
+    fn files_list_their_definitions_and_keys_move_the_selection() {
+        let cscope = code_ls::parse_buffer(&database()).unwrap();
+        let mut browser = Browser::new(&cscope);
+        assert_eq!(names(&browser), vec!["draw", "point"]);
+        press(&mut browser, &[KeyCode::Down]);
+        assert_eq!(names(&browser), vec!["main", "make_point"]);
+        // The selection stays on the last file.
+        press(&mut browser, &[KeyCode::Char('j'), KeyCode::PageDown]);
+        assert_eq!(browser.file.selected(), Some(1));
+        press(&mut browser, &[KeyCode::Tab, KeyCode::Down]);
+        assert_eq!(
+            browser.selected_symbol().unwrap().display_name(),
+            "make_point"
+        );
+        let make_point = browser.selected_symbol().unwrap();
+        assert!(opened(&mut browser).is_some_and(|s| std::ptr::eq(s, make_point)));
+        press(&mut browser, &[KeyCode::Left, KeyCode::Up]);
+        assert_eq!(names(&browser), vec!["draw", "point"]);
+        assert_eq!(browser.symbol.selected(), Some(0));
+        assert!(matches!(browser.key(KeyCode::Char('q')), Action::Quit));
+    }
+
+    #[test]
+    fn search_lists_the_matches_of_every_file() {
+        let cscope = code_ls::parse_buffer(&database()).unwrap();
+        let mut browser = Browser::new(&cscope);
+        press(
+            &mut browser,
+            &[KeyCode::Char('/'), KeyCode::Char('p'), KeyCode::Char('t')],
+        );
+        assert_eq!(browser.query, "pt");
+        assert_eq!(names(&browser), vec!["point", "make_point"]);
+        // 'q' is typed into the search rather than quitting.
+        press(&mut browser, &[KeyCode::Char('q')]);
+        assert!(names(&browser).is_empty());
+        press(
+            &mut browser,
+            &[KeyCode::Backspace, KeyCode::Enter, KeyCode::Down],
+        );
+        let make_point = browser.selected_symbol().unwrap();
+        assert_eq!(make_point.filename(), "src/main.c");
+        assert!(opened(&mut browser).is_some_and(|s| std::ptr::eq(s, make_point)));
+        // Esc clears the search, and a second one quits.
+        press(&mut browser, &[KeyCode::Esc]);
+        assert_eq!(names(&browser), vec!["draw", "point"]);
+        assert!(matches!(browser.key(KeyCode::Esc), Action::Quit));
+    }
+}
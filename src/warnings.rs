@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+// How many offsets of each kind of warning the summary shows.
+const SHOWN_OFFSETS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum WarningKind {
+    UnknownMark,
+    SkippedRecord,
+    InvalidUtf8,
+    InvalidTrailer,
+    Truncated,
+}
+
+impl WarningKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningKind::UnknownMark => "unknown mark",
+            WarningKind::SkippedRecord => "skipped record",
+            WarningKind::InvalidUtf8 => "invalid utf-8",
+            WarningKind::InvalidTrailer => "invalid trailer",
+            WarningKind::Truncated => "truncated",
+        }
+    }
+}
+
+// Something off in the database that the parser got past.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Warning {
+    pub kind: WarningKind,
+    // Where in the database file it was found.
+    pub offset: u64,
+    // The mark character, for unknown marks.
+    pub mark: Option<u8>,
+}
+
+// The warnings counted by kind, with the first few offsets of each.
+pub struct WarningSummary<'a> {
+    warnings: &'a [Warning],
+}
+
+impl<'a> WarningSummary<'a> {
+    pub fn new(warnings: &'a [Warning]) -> WarningSummary<'a> {
+        WarningSummary { warnings }
+    }
+}
+
+impl<'a> std::fmt::Display for WarningSummary<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut kinds: Vec<WarningKind> = self.warnings.iter().map(|w| w.kind).collect();
+        kinds.sort();
+        kinds.dedup();
+        write!(f, "•warnings: {}\n╰─╮\n", self.warnings.len())?;
+        for kind in kinds {
+            let offsets: Vec<u64> = self
+                .warnings
+                .iter()
+                .filter(|w| w.kind == kind)
+                .map(|w| w.offset)
+                .collect();
+            let mut marks: Vec<String> = vec![];
+            for w in self.warnings.iter().filter(|w| w.kind == kind) {
+                let mark = w.mark.map(|m| format!("{:?}", m as char));
+                if let Some(mark) = mark.filter(|m| !marks.contains(m)) {
+                    marks.push(mark);
+                }
+            }
+            let shown: Vec<String> = offsets
+                .iter()
+                .take(SHOWN_OFFSETS)
+                .map(|o| o.to_string())
+                .collect();
+            writeln!(
+                f,
+                "  ├ {:<14} {:>6}, offset:{}{}{}",
+                kind.name(),
+                offsets.len(),
+                shown.join(","),
+                if offsets.len() > SHOWN_OFFSETS {
+                    ",..."
+                } else {
+                    ""
+                },
+                if marks.is_empty() {
+                    String::new()
+                } else {
+                    format!(", marks:{}", marks.join(","))
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
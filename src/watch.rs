@@ -0,0 +1,100 @@
+use std::io::Error;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+// How often the database is looked at.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// When the database was last written and how long it is, None while it does
+// not exist (cscope removes it while rebuilding).
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+// The stamps of each of the databases.
+fn stamps(paths: &[&Path]) -> Vec<Option<(SystemTime, u64)>> {
+    paths.iter().map(|p| stamp(p)).collect()
+}
+
+// The `exec` command run through sh for the database at `path`.  The path
+// goes to sh as $1 rather than into the command, where its blanks and
+// metacharacters would be taken as shell syntax.
+fn hook(exec: &str, path: &Path) -> Command {
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(exec.replace("{}", "\"$1\""))
+        .arg("sh")
+        .arg(path);
+    command
+}
+
+// Run `show` again each time one of the databases is regenerated, and the
+// `exec` command after it, its "{}" standing for the path of that database.
+// A change only counts once the files have stayed the same for a poll, so a
+// database that is still being written is not read.  An error of `show` is
+// printed and the watch goes on.
+pub fn watch(
+    paths: &[&Path],
+    exec: Option<&str>,
+    mut show: impl FnMut() -> Result<(), Error>,
+) -> ! {
+    let mut seen = stamps(paths);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let now = stamps(paths);
+        if now.iter().any(|s| s.is_none()) || now == seen {
+            continue;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        if stamps(paths) != now {
+            continue;
+        }
+        let changed = (0..paths.len())
+            .find(|&idx| now[idx] != seen[idx])
+            .unwrap_or(0);
+        seen = now;
+        if let Err(e) = show() {
+            eprintln!("Error: {}", e);
+        }
+        // The hook runs whether or not the query succeeded, since it may be
+        // what reacts to the new database.
+        if let Some(exec) = exec {
+            match hook(exec, paths[changed]).status() {
+                Ok(status) if !status.success() => {
+                    eprintln!("'{}' exited with {}", exec, status)
+                }
+                Ok(_) => (),
+                Err(e) => eprintln!("'{}': {}", exec, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hook_passes_the_path_as_a_single_argument() {
+        let dir = std::env::temp_dir().join(format!("code-ls-hook-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("my db; rm -rf x $(y).out");
+        let out = hook("printf '%s|' {} {}", &path).output().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(out.status.success());
+        let shown = path.display().to_string();
+        assert_eq!(
+            String::from_utf8(out.stdout).unwrap(),
+            format!("{}|{}|", shown, shown)
+        );
+    }
+
+    #[test]
+    fn hook_without_a_placeholder_runs_as_given() {
+        let out = hook("echo done", Path::new("cscope.out")).output().unwrap();
+        assert_eq!(out.stdout, b"done\n");
+    }
+}
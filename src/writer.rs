@@ -0,0 +1,227 @@
+use crate::cscope::{Cscope, FileMark, Trailer, DICHAR1, DICHAR2, KEYWORDS};
+use std::collections::HashSet;
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+// The keyword, with what follows it, that the text at `idx` starts with.
+fn keyword_at(bytes: &[u8], idx: usize) -> Option<(u8, usize)> {
+    if idx > 0 && is_ident(bytes[idx - 1]) {
+        return None;
+    }
+    let rest = &bytes[idx..];
+    KEYWORDS
+        .iter()
+        .enumerate()
+        .find_map(|(code, (keyword, delim))| {
+            let len = keyword.len() + delim.len();
+            let matched = !keyword.is_empty()
+                && !matches!(code, 9 | 10)
+                && rest.starts_with(keyword.as_bytes())
+                && rest[keyword.len()..].starts_with(delim.as_bytes())
+                && (!delim.is_empty() || !rest.get(len).copied().is_some_and(is_ident));
+            matched.then_some((code as u8, len))
+        })
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct WriteOptions {
+    // Compress the non-symbol text like cscope does without '-c'.
+    pub compressed: bool,
+    // Flag the database as having an inverted index ('-q'), the index files
+    // themselves are not written.
+    pub inverted_index: bool,
+}
+
+// Builds a cscope database one source line at a time.
+pub struct Writer {
+    options: WriteOptions,
+    body: Vec<u8>,
+    files: Vec<String>,
+    symbols: usize,
+    trailer: Option<Trailer>,
+}
+
+impl Writer {
+    pub fn new(options: WriteOptions) -> Writer {
+        Writer {
+            options,
+            body: vec![],
+            files: vec![],
+            symbols: 0,
+            trailer: None,
+        }
+    }
+
+    // The directories and source files to list after the records, rather
+    // than the current directory and the files written.
+    pub fn trailer(&mut self, trailer: Trailer) {
+        self.trailer = Some(trailer);
+    }
+
+    // Start the records of another source file.
+    pub fn file(&mut self, path: &str) {
+        self.body
+            .extend_from_slice(format!("\t@{}\n\n", path).as_bytes());
+        self.files.push(path.to_string());
+    }
+
+    // A source line: the text leading up to the first symbol, then each symbol
+    // with its mark (None when unmarked) and the text following it.
+    pub fn line(
+        &mut self,
+        line_number: u64,
+        text: &str,
+        symbols: &[(Option<FileMark>, &str, &str)],
+    ) {
+        self.body
+            .extend_from_slice(format!("{} ", line_number).as_bytes());
+        self.text(text);
+        for (mark, name, text) in symbols.iter() {
+            if let Some(mark) = mark {
+                self.body.push(b'\t');
+                self.body.push(*mark as u8);
+            }
+            self.body.extend_from_slice(name.as_bytes());
+            self.body.push(b'\n');
+            self.text(text);
+            self.symbols += 1;
+        }
+        self.body.push(b'\n');
+    }
+
+    // A function or #define end mark, closing the definition above.
+    pub fn end_mark(&mut self, mark: FileMark) {
+        self.body.push(b'\t');
+        self.body.push(mark as u8);
+        self.body.extend_from_slice(b"\n\n");
+    }
+
+    // The header, the records and the trailer listing the source files.
+    pub fn finish(mut self, current_dir: &str) -> Vec<u8> {
+        self.body.extend_from_slice(b"\t@\n");
+        let mut flags = String::new();
+        if !self.options.compressed {
+            flags.push_str(" -c");
+        }
+        if self.options.inverted_index {
+            flags.push_str(&format!(" -q {:010}", self.symbols));
+        }
+        let header = |offset: usize| format!("cscope 15 {}{} {:010}\n", current_dir, flags, offset);
+        let trailer_offset = header(0).len() + self.body.len();
+
+        let mut db = header(trailer_offset).into_bytes();
+        db.append(&mut self.body);
+        let trailer = self.trailer.take().unwrap_or_else(|| Trailer {
+            source_dirs: vec![".".to_string()],
+            include_dirs: vec![],
+            source_files: std::mem::take(&mut self.files),
+        });
+        for dirs in [&trailer.source_dirs, &trailer.include_dirs] {
+            db.extend_from_slice(format!("{}\n", dirs.len()).as_bytes());
+            for dir in dirs.iter() {
+                db.extend_from_slice(format!("{}\n", dir).as_bytes());
+            }
+        }
+        let files = &trailer.source_files;
+        let names: usize = files.iter().map(|f| f.len() + 1).sum();
+        db.extend_from_slice(format!("0\n{}\n{}\n", files.len(), names).as_bytes());
+        for file in files.iter() {
+            db.extend_from_slice(file.as_bytes());
+            db.push(b'\n');
+        }
+        db
+    }
+
+    // Non-symbol text ends with a newline, compressed if asked for.
+    fn text(&mut self, text: &str) {
+        let bytes = text.as_bytes();
+        let mut idx = 0;
+        while idx < bytes.len() {
+            if let Some((code, len)) = keyword_at(bytes, idx).filter(|_| self.options.compressed) {
+                self.body.push(code);
+                idx += len;
+                continue;
+            }
+            let pair = (
+                DICHAR1.iter().position(|&c| c == bytes[idx]),
+                bytes
+                    .get(idx + 1)
+                    .and_then(|n| DICHAR2.iter().position(|c| c == n)),
+            );
+            match pair {
+                (Some(first), Some(second)) if self.options.compressed => {
+                    self.body.push(0x80 + (first * 8 + second) as u8);
+                    idx += 2;
+                }
+                _ => {
+                    self.body.push(bytes[idx]);
+                    idx += 1;
+                }
+            }
+        }
+        self.body.push(b'\n');
+    }
+}
+
+// Serialize a parsed database. The records are written back as they were
+// read, only at new offsets; source lines without symbols are only kept when
+// an end mark follows them.
+pub fn write_database(cscope: &Cscope, options: WriteOptions) -> Vec<u8> {
+    let mut writer = Writer::new(options);
+    let mut symbols = cscope.symbols().iter().peekable();
+    let mut file: Option<&str> = None;
+    let mut written_line: Option<u64> = None;
+    while let Some(sym) = symbols.next() {
+        if file != Some(sym.filename()) {
+            writer.file(sym.filename());
+            file = Some(sym.filename());
+            written_line = None;
+        }
+        if sym.is_end_mark() {
+            if written_line != Some(sym.line_number()) {
+                writer.line(sym.line_number(), sym.raw_line_text(), &[]);
+                written_line = Some(sym.line_number());
+            }
+            writer.end_mark(sym.mark());
+            continue;
+        }
+
+        // The symbols sharing the source line.
+        let mut line = vec![sym];
+        while let Some(next) = symbols.peek() {
+            if next.is_end_mark()
+                || next.filename() != sym.filename()
+                || next.line_number() != sym.line_number()
+            {
+                break;
+            }
+            line.extend(symbols.next());
+        }
+        let records: Vec<(Option<FileMark>, &str, &str)> = line
+            .iter()
+            .map(|s| {
+                let mark = Some(s.mark()).filter(|&m| m != FileMark::Reference);
+                (mark, s.name(), s.text_after())
+            })
+            .collect();
+        writer.line(sym.line_number(), sym.text_before(), &records);
+        written_line = Some(sym.line_number());
+    }
+    // The source files listed, less those none of the records are from.
+    let trailer = cscope.trailer();
+    if !trailer.source_files.is_empty() {
+        let written: HashSet<&str> = cscope.symbols().iter().map(|s| s.filename()).collect();
+        writer.trailer(Trailer {
+            source_files: trailer
+                .source_files
+                .iter()
+                .filter(|f| written.contains(f.as_str()))
+                .cloned()
+                .collect(),
+            ..trailer.clone()
+        });
+    }
+    writer.finish(&cscope.current_dir().to_string_lossy())
+}
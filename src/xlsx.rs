@@ -0,0 +1,79 @@
+use crate::table::{Cell, Table};
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use std::io::Error;
+
+// A workbook with a sheet for each table, the header row in bold.
+pub fn workbook(tables: &[Table]) -> Result<Vec<u8>, Error> {
+    build(tables).map_err(|e| Error::other(e.to_string()))
+}
+
+fn build(tables: &[Table]) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    for table in tables.iter() {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(&table.name)?;
+        for (col, heading) in table.header.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, heading, &bold)?;
+        }
+        for (row, cells) in table.rows.iter().enumerate() {
+            let row = row as u32 + 1;
+            for (col, cell) in cells.iter().enumerate() {
+                match cell {
+                    Cell::Text(text) => sheet.write_string(row, col as u16, text)?,
+                    Cell::Number(n) => sheet.write_number(row, col as u16, *n as f64)?,
+                };
+            }
+        }
+    }
+    workbook.save_to_buffer()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    // The text of a part of the workbook.
+    fn part(workbook: &[u8], name: &str) -> String {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(workbook)).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn each_table_is_a_sheet_of_text_and_numbers() {
+        let mut files = Table::new("files", &["file", "records"]);
+        files
+            .rows
+            .push(vec![Cell::from("src/main.c"), Cell::from(42)]);
+        let totals = Table::new("totals", &["what", "count"]);
+        let workbook = workbook(&[files, totals]).unwrap();
+
+        let book = part(&workbook, "xl/workbook.xml");
+        assert!(
+            book.contains("<sheet name=\"files\" sheetId=\"1\""),
+            "{}",
+            book
+        );
+        assert!(
+            book.contains("<sheet name=\"totals\" sheetId=\"2\""),
+            "{}",
+            book
+        );
+        let strings = part(&workbook, "xl/sharedStrings.xml");
+        for text in ["file", "records", "src/main.c"] {
+            assert!(strings.contains(&format!("<t>{}</t>", text)), "{}", strings);
+        }
+        let sheet = part(&workbook, "xl/worksheets/sheet1.xml");
+        // The header is bold, the count a number rather than a string.
+        assert!(sheet.contains("<c r=\"A1\" s=\"1\" t=\"s\">"), "{}", sheet);
+        assert!(sheet.contains("<c r=\"B2\"><v>42</v></c>"), "{}", sheet);
+        assert!(sheet.contains("<c r=\"A2\" t=\"s\">"), "{}", sheet);
+    }
+}